@@ -0,0 +1,156 @@
+#!/usr/bin/env python3
+
+
+"""
+A script to benchmark the per-run maps of postoga on large inputs.
+
+Writes a synthetic isoforms file and .fai index with --n transcripts
+(default: 1M, 4 per gene) and times the current loaders against the
+line-by-line ones they replaced, each building the same maps: both
+directions of the isoforms map and the name -> entry .fai index. The
+best of --repeat runs is reported.
+
+``` bash
+./benchmark.py --n 2000000 --repeat 3
+```
+"""
+
+
+import argparse
+import os
+import tempfile
+import time
+from collections import defaultdict
+from modules.isoform_map import IsoformMap
+from modules.seq_store import read_fai
+
+
+__author__ = "Alejandro Gonzales-Irribarren"
+__email__ = "jose.gonzalesdezavala1@unmsm.edu.pe"
+__github__ = "https://github.com/alejandrogzi"
+__version__ = "0.7.0-devel"
+
+
+def write_inputs(path: str, n: int) -> tuple:
+    """
+    Writes an isoforms file and a .fai index of n transcripts
+
+    @type path: str
+    @param path: output directory
+    @type n: int
+    @param n: number of transcripts
+    """
+    isoforms, fai = os.path.join(path, "isoforms.txt"), os.path.join(path, "x.fai")
+
+    with open(isoforms, "w") as iso, open(fai, "w") as idx:
+        for i in range(n):
+            name = f"ENST{i:011d}.{i % 97 + 1}"
+            iso.write(f"ENSG{i // 4:011d}\t{name}\n")
+            idx.write(f"{name}\t{1000 + i % 5000}\t{i * 1100 + 20}\t60\t61\n")
+
+    return isoforms, fai
+
+
+def legacy_isoforms(isoforms: str) -> tuple:
+    """
+    Loads an isoforms file the way IsoformMap did before the one-pass
+    loader (its from_file and __init__, unchanged), into both directions
+
+    @type isoforms: str
+    @param isoforms: path to the isoforms file
+    """
+    with open(isoforms, "r") as f:
+        pairs = [line.rstrip("\n").split("\t")[:2] for line in f if line.strip()]
+
+    tx2gene, gene2tx = {}, defaultdict(list)
+    for gene, transcript in pairs:
+        if transcript not in tx2gene:
+            gene2tx[gene].append(transcript)
+        tx2gene[transcript] = gene
+
+    return tx2gene, gene2tx
+
+
+def legacy_fai(fai: str) -> dict:
+    """
+    Reads a .fai index the way read_fai did before the one-pass loader
+
+    @type fai: str
+    @param fai: path to .fai file
+    """
+    index = {}
+
+    with open(fai, "r") as f:
+        for line in f:
+            fields = line.rstrip("\n").split("\t")
+            index[fields[0]] = tuple(int(x) for x in fields[1:5])
+
+    return index
+
+
+def current_isoforms(isoforms: str) -> tuple:
+    """
+    Loads an isoforms file with IsoformMap.from_file into both directions
+    (IsoformMap.load caches per file, from_file always reads it)
+
+    @type isoforms: str
+    @param isoforms: path to the isoforms file
+    """
+    isoforms = IsoformMap.from_file(isoforms)
+
+    return isoforms.tx2gene, isoforms.gene2tx
+
+
+def best_of(repeat: int, step) -> float:
+    """Returns the fastest of repeat runs of step(), in seconds"""
+    times = []
+    for _ in range(repeat):
+        start = time.perf_counter()
+        step()
+        times.append(time.perf_counter() - start)
+
+    return min(times)
+
+
+def main():
+    app = argparse.ArgumentParser(description=__doc__.strip().split("\n")[0])
+    app.add_argument(
+        "-n",
+        "--n",
+        help="Number of transcripts (default: 1000000)",
+        type=int,
+        default=1000000,
+    )
+    app.add_argument(
+        "-r",
+        "--repeat",
+        help="Runs per step, the fastest is reported (default: 3)",
+        type=int,
+        default=3,
+    )
+    args = app.parse_args()
+
+    with tempfile.TemporaryDirectory() as path:
+        isoforms, fai = write_inputs(path, args.n)
+
+        steps = [
+            ("isoforms", legacy_isoforms, current_isoforms, isoforms),
+            (".fai index", legacy_fai, read_fai, fai),
+        ]
+        rows = [
+            (
+                name,
+                best_of(args.repeat, lambda: old(x)),
+                best_of(args.repeat, lambda: new(x)),
+            )
+            for name, old, new, x in steps
+        ]
+
+    print(f"{args.n} transcripts, best of {args.repeat}")
+    print(f"{'':<16}{'line by line':>14}{'one pass':>14}{'speedup':>10}")
+    for name, old, new in rows:
+        print(f"{name:<16}{old:>12.3f} s{new:>12.3f} s{old / new:>8.2f} x")
+
+
+if __name__ == "__main__":
+    main()
@@ -31,8 +31,11 @@ class Constants:
     DESCRIPTION = (
         "postoga is a tool that automates the post-processing of TOGA results."
     )
+    # Release of postoga as a whole, reported in logs, plots and capabilities
+    VERSION = "0.7.0-devel"
 
     TEMP = "temp"
+    CACHE_DIR = os.path.join("~", ".cache", "postoga")
     ORTHOLOGY_TYPE = {
         "one2one": "o2o",
         "one2many": "o2m",
@@ -51,6 +54,31 @@ class Constants:
         "N": 8,
         "NF": 9,
     }
+    # (status, classes, minimum supporting evidence types), first match wins
+    GENE_STATUS_RULES = [
+        ("annotated", {"I"}, 0),
+        ("annotated", {"PI", "UL", "M", "PM"}, 1),
+        ("putative", {"PI", "UL", "M", "PM"}, 0),
+        ("putative", {"L", "PG", "N"}, 2),
+    ]
+    MIN_EXPRESSION = 1.0
+    MAX_EVALUE = 1e-5
+    MIN_BITSCORE = 50.0
+    SEARCH_FLANK = 10000
+    SEARCH_CLASSES = {"L", "M"}
+    RECONCILE_POLICIES = ["report", "add", "upgrade"]
+    DENSITY_WINDOW = 100000
+    PEPTIDE_FLANK = 15
+    # Guardrails are off unless set (None), long genes can span megabases
+    LIMITS = {"max_exons": None, "max_span": None, "max_attribute_length": None}
+    # Suffix TOGA gives transcripts joined from fragments instead of a chain id
+    FRAGMENT_SUFFIX = "-1"
+    FRAGMENT_GENE_PREFIX = "fragment_"
+    ORF_MIN_FRACTION = 0.8
+    RETROCOPY_MAX_INTRON = 30
+    TANDEM_MAX_DISTANCE = 100000
+    CONTIG_CATEGORIES = ["autosome", "sex", "unplaced", "MT"]
+    RANK_WEIGHTS = {"pred": 1.0, "class": 0.5, "confidence_level": 0.25}
     TAXA_ORDER = [
         "Hominoidea",
         "Scandentia",
@@ -118,6 +146,39 @@ class Constants:
         "User": "red",
     }
     SPECIES_DEFAULT = "human"
+    SOURCE = "postoga"
+    QUERY_TABLE_SCHEMA = 2
+    GENOME_STEPS = [
+        "prep_quant",
+        "vcf",
+        "nf_tags",
+        "rescue_starts",
+        "kozak",
+        "orf_rescue",
+        "find_gaps",
+        "junction_peptides",
+    ]
+    GAP_MARGIN = 1000
+    CONTIG_END_MARGIN = 1000
+    # Amino acids in TCAG codon order (TTT, TTC, TTA, TTG, TCT, ...)
+    CODON_BASES = "TCAG"
+    GENETIC_CODES = {
+        "standard": "FFLLSSSSYY**CC*WLLLLPPPPHHQQRRRRIIIMTTTTNNKKSSRRVVVVAAAADDEEGGGG",
+        "vertebrate_mitochondrial": "FFLLSSSSYY**CC*WLLLLPPPPHHQQRRRRIIMMTTTTNNKKSS**VVVVAAAADDEEGGGG",
+    }
+    START_CODONS = {
+        "standard": ["ATG"],
+        "vertebrate_mitochondrial": ["ATG", "ATA", "ATT", "ATC", "GTG"],
+    }
+    DUPLICATE_POLICIES = ["error", "keep_first", "keep_longest"]
+    REFORMAT_DUPLICATE_POLICIES = DUPLICATE_POLICIES + ["rename"]
+    FAI_CHUNK_SIZE = 64 * 1024 * 1024
+    MISSING_POLICIES = ["error", "skip", "warn", "emit_empty"]
+    ATTRIBUTE_SEPARATORS = {"space": " ", "tab": "\t", "none": ""}
+    LINE_ENDINGS = {"lf": "\n", "crlf": "\r\n"}
+    SORT_MODES = ["position", "transcript", "none"]
+    COMPRESSION_EXTENSIONS = {"gz": ".gz", "bgzf": ".gz", "zstd": ".zst", "xz": ".xz"}
+    MT_CONTIGS = ["chrM", "chrMT", "MT", "M", "mito", "mitochondrion"]
     PLOTSTAMP = """Generated on {} by postoga \nversion: {}, branch: {}, commit: {}.\n
     This report provides a basic analysis of the data and results 
     obtained by TOGA and is intended to be used as a preliminary 
@@ -129,6 +190,9 @@ class Constants:
         BED2GTF = "bed2gtf"
         BED2GFF = "bed2gff"
         COMPLEASM = "compleasm"
+        BEDGRAPHTOBIGWIG = "bedGraphToBigWig"
+        BEDTOBIGBED = "bedToBigBed"
+        BGZIP = "bgzip"
 
     class FileNames:
         SUPPLY_FOLDER = resources.files(supply)
@@ -155,6 +219,51 @@ class Constants:
         LOGO_IMG = SUPPLY_FOLDER.joinpath("postoga_logo.png")
         FONT = SUPPLY_FOLDER.joinpath("font/Arial.ttf")
         PDF = "POSTOGA_REPORT.pdf"
+        UNION_GTF = "union.gtf"
+        GENES_BED = "genes.bed"
+        TRANSCRIPTOME = "transcripts.fa"
+        TX2GENE = "tx2gene.tsv"
+        GENTROME = "gentrome.fa"
+        DECOYS = "decoys.txt"
+        CONSEQUENCES = "variant_consequences.tsv"
+        CHROM_SIZES = "chrom.sizes"
+        SCORE_BEDGRAPH = "{}_track.bedGraph"
+        DENSITY_BEDGRAPH = "{}_density.bedGraph"
+        COVERAGE_BEDGRAPH = "{}_coverage.bedGraph"
+        DIFF = "diff_runs.tsv"
+        WARNINGS = "postoga_warnings.tsv"
+        CDS_CHECK = "cds_length_check.tsv"
+        RESCUED_BED = "rescued.bed"
+        CIRCULAR_BED = "circular.bed"
+        GAPS = "assembly_gaps.bed"
+        CONTIG_QUALITY = "contig_quality.tsv"
+        SUBSAMPLE_BED = "subsample.bed"
+        TOP_BED = "top{}.bed"
+        GUARDED_BED = "guarded.bed"
+        REMAPPED = "remapped"
+        FRAGMENT_ISOFORMS = "fragment.isoforms.txt"
+        CHUNKS = "chunks"
+        BED_VALIDATION = "bed_validation.tsv"
+        DIGESTS = "digests.tsv"
+        ORF_BED = "orf.bed"
+        RETROCOPY_BED = "retrocopies.bed"
+        TANDEM_DUPLICATES = "tandem_duplicates.tsv"
+        TRANSLATED = "translated.fasta"
+        CONTIG_CATEGORIES = "contig_categories.tsv"
+        SUBSAMPLE_ISOFORMS = "subsample.isoforms.txt"
+        SYNTHETIC_GENOME = "query_genome.fa"
+        ROUND_TRIP_BED = "roundtrip.bed"
+        QUERY_TABLE = "query_table.tsv"
+        LOCK = ".postoga.lock"
+        MANIFEST = "postoga_manifest.json"
+        CLASS_MATRIX = "class_matrix.{}.tsv"
+        NEVER_PROJECTED = "never_projected.tsv"
+        GENE_STATUS = "gene_status.tsv"
+        SEARCH_REGIONS = "search_regions.bed"
+        RECONCILED_BED = "reconciled.bed"
+        RECONCILED_ISOFORMS = "reconciled.isoforms.txt"
+        RECONCILIATION = "reconciliation.tsv"
+        JUNCTION_PEPTIDES = "junction_peptides.fa"
 
     class Commands:
         COMMIT = "git rev-parse --short HEAD"
@@ -163,3 +272,9 @@ class Constants:
     class Metadata:
         BED2GTF_METADATA = "https://github.com/alejandrogzi/bed2gtf"
         BED2GFF_METADATA = "https://github.com/alejandrogzi/bed2gff"
+
+    TOGA_FASTA = {
+        "nucleotide": FileNames.NUCLEOTIDE,
+        "codon": FileNames.CODON,
+        "protein": FileNames.PROTEIN,
+    }
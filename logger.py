@@ -53,7 +53,7 @@ class Log:
 
     def __init__(self, path: str, log_file: str):
         self.log_file = os.path.join(path, log_file)
-        self.version = __version__
+        self.version = Constants.VERSION
         self.commit = shell(Constants.Commands.COMMIT)
         self.branch = shell(Constants.Commands.BRANCH)
 
@@ -78,6 +78,9 @@ class Log:
     def record(self, message, timestamp=True):
         logging.info(message)
 
+    def warning(self, message):
+        logging.warning(message)
+
     @classmethod
     def connect(cls, path, log_file):
         log = cls(path, log_file)
@@ -0,0 +1,545 @@
+#!/usr/bin/env python3
+
+
+"""
+A module with the AnnotationSet data model shared by postoga steps.
+
+An AnnotationSet holds genes -> transcripts -> exons/CDS, with the
+attributes of each transcript, built from any format that
+`formats.open_annotation` reads and written back as BED12. Steps
+that filter, merge, compare or summarize annotations work on it
+instead of re-parsing files into their own tuples. Identical attributes
+are stored once per set (and freed with it) and copied only when a
+transcript modifies them, with typed getters for numeric values:
+
+``` python
+from modules.annotation_set import AnnotationSet
+
+annotation = AnnotationSet.from_file("query_annotation.gtf.gz")
+coding = annotation.filter(lambda tx: tx.is_coding)
+coding.write_bed("coding.bed")
+score = next(iter(coding)).attributes.get_float("bed_score", 0.0)
+
+# one chromosome in memory at a time
+for chrom, subset in AnnotationSet.stream("query_annotation.bed"):
+    ...
+```
+"""
+
+
+import sys
+from itertools import groupby
+from modules.bed import BedRecord
+from modules.errors import PostogaValueError
+from modules.formats import detect_format, open_annotation
+from modules.gxf import CODING, TRANSCRIPT
+from modules.isoform_map import IsoformMap
+from modules.utils import natural_key
+
+
+__author__ = "Alejandro Gonzales-Irribarren"
+__email__ = "jose.gonzalesdezavala1@unmsm.edu.pe"
+__github__ = "https://github.com/alejandrogzi"
+__version__ = "0.7.0-devel"
+
+
+def merge_intervals(intervals: list) -> list:
+    """
+    Merges overlapping or book-ended intervals
+
+    @type intervals: list
+    @param intervals: list of (start, end) tuples
+    """
+    merged = []
+
+    for start, end in sorted(intervals):
+        if merged and start <= merged[-1][1]:
+            merged[-1][1] = max(merged[-1][1], end)
+        else:
+            merged.append([start, end])
+
+    return [tuple(x) for x in merged]
+
+
+def freeze(value):
+    """Returns a hashable, interned version of an attribute value"""
+    if isinstance(value, (list, tuple)):
+        return tuple(freeze(x) for x in value)
+
+    return sys.intern(value) if isinstance(value, str) else value
+
+
+class Attributes:
+    """
+    A class to hold key -> value attributes, sharing one dictionary
+    between all holders of identical attributes of the same pool until
+    one of them is modified (copy-on-write).
+    """
+
+    __slots__ = ("data", "owned")
+
+    def __init__(self, data: dict = None, pool: dict = None) -> None:
+        """
+        Constructs all the necessary attributes for the Attributes object.
+
+        @type data: dict
+        @param data: ordered key -> value pairs (strings or lists of strings)
+        @type pool: dict
+        @param pool: shared dictionaries keyed by their frozen items (e.g.
+            AnnotationSet.pool), a private copy is kept if None
+        """
+
+        data = data.data if isinstance(data, Attributes) else (data or {})
+        key = tuple((freeze(k), freeze(v)) for k, v in data.items())
+
+        if pool is None:
+            self.data = {k: list(v) if isinstance(v, tuple) else v for k, v in key}
+            self.owned = True
+            return
+
+        if key not in pool:
+            pool[key] = {k: list(v) if isinstance(v, tuple) else v for k, v in key}
+
+        self.data = pool[key]
+        self.owned = False
+
+    def __getitem__(self, key: str):
+        return self.data[key]
+
+    def __setitem__(self, key: str, value) -> None:
+        self.detach()
+        self.data[key] = value
+
+    def __delitem__(self, key: str) -> None:
+        self.detach()
+        del self.data[key]
+
+    def __contains__(self, key: str) -> bool:
+        return key in self.data
+
+    def __iter__(self):
+        return iter(self.data)
+
+    def __len__(self) -> int:
+        return len(self.data)
+
+    def __eq__(self, other) -> bool:
+        other = other.data if isinstance(other, Attributes) else other
+        return self.data == other
+
+    def detach(self) -> None:
+        """Makes a private copy of the shared dictionary before a write"""
+        if not self.owned:
+            self.data = {
+                k: list(v) if isinstance(v, list) else v for k, v in self.data.items()
+            }
+            self.owned = True
+
+    def get(self, key: str, default=None):
+        return self.data.get(key, default)
+
+    def items(self):
+        return self.data.items()
+
+    def pop(self, key: str, default=None):
+        self.detach()
+        return self.data.pop(key, default)
+
+    def to_dict(self) -> dict:
+        return dict(self.data)
+
+    def get_str(self, key: str, default: str = None) -> str:
+        """Returns a value as a string, lists joined by commas"""
+        value = self.data.get(key)
+        if value is None:
+            return default
+
+        return ",".join(value) if isinstance(value, list) else value
+
+    def get_float(self, key: str, default: float = None) -> float:
+        """Returns a value as a float, default if missing, '.' or not numeric"""
+        try:
+            return float(self.get_str(key))
+        except (TypeError, ValueError):
+            return default
+
+    def get_int(self, key: str, default: int = None) -> int:
+        """Returns a value as an int, default if missing or not an integer"""
+        try:
+            return int(self.get_str(key))
+        except (TypeError, ValueError):
+            return default
+
+    def get_list(self, key: str) -> list:
+        """Returns a value as a list (repeated keys or comma-separated)"""
+        value = self.data.get(key)
+        if value is None:
+            return []
+
+        return list(value) if isinstance(value, list) else value.split(",")
+
+
+class Transcript:
+    """A class to represent a transcript with its exons and CDS span."""
+
+    def __init__(
+        self,
+        name: str,
+        chrom: str,
+        strand: str,
+        exons: list,
+        thick: tuple = None,
+        gene: str = None,
+        attributes: dict = None,
+        pool: dict = None,
+    ) -> None:
+        """
+        Constructs all the necessary attributes for the Transcript object.
+
+        @type name: str
+        @param name: transcript name
+        @type chrom: str
+        @param chrom: chromosome name
+        @type strand: str
+        @param strand: + or -
+        @type exons: list
+        @param exons: (start, end) 0-based half-open exons
+        @type thick: tuple
+        @param thick: (start, end) of the CDS, None if non-coding
+        @type gene: str
+        @param gene: gene name, the transcript itself if None
+        @type attributes: dict | Attributes
+        @param attributes: extra key -> value pairs (score, rgb, ...)
+        @type pool: dict
+        @param pool: attribute pool to share identical attributes in
+        """
+
+        self.name = name
+        self.chrom = chrom
+        self.strand = strand
+        self.exons = sorted(exons)
+        self.thick = thick if thick and thick[0] < thick[1] else None
+        self.gene = gene or name
+        self.attributes = Attributes(attributes, pool)
+
+    @classmethod
+    def from_bed(
+        cls, record: BedRecord, gene: str = None, pool: dict = None
+    ) -> "Transcript":
+        """
+        Builds a transcript from a BED12 record, keeping score and itemRgb
+
+        @type record: BedRecord
+        @param record: BED12 projection
+        @type gene: str
+        @param gene: gene name
+        @type pool: dict
+        @param pool: attribute pool (see Attributes)
+        """
+        return cls(
+            record.name,
+            record.chrom,
+            record.strand,
+            record.exons(),
+            (record.thick_start, record.thick_end),
+            gene,
+            {"bed_score": record.score, "item_rgb": record.rgb},
+            pool,
+        )
+
+    @classmethod
+    def from_features(
+        cls, name: str, features: list, pool: dict = None
+    ) -> "Transcript":
+        """
+        Builds a transcript from its .gtf/.gff features. Exon lines define
+        the exons; if there are none (e.g. a CDS-only file) the coding
+        features are used instead
+
+        @type name: str
+        @param name: transcript name
+        @type features: list
+        @param features: GxfFeature objects of the transcript
+        @type pool: dict
+        @param pool: attribute pool (see Attributes)
+        """
+        exons = [(x.start - 1, x.end) for x in features if x.feature == "exon"]
+        coding = [(x.start - 1, x.end) for x in features if x.feature in CODING]
+
+        transcript = next((x for x in features if x.feature in TRANSCRIPT), None)
+        attributes = transcript.attributes if transcript else {}
+        gene = features[0].attributes.get("gene_id") or attributes.get("Parent")
+
+        thick = None
+        if coding:
+            thick = (min(x[0] for x in coding), max(x[1] for x in coding))
+
+        return cls(
+            name,
+            features[0].chrom,
+            features[0].strand,
+            merge_intervals(exons or coding),
+            thick,
+            gene,
+            attributes,
+            pool,
+        )
+
+    @property
+    def start(self) -> int:
+        return self.exons[0][0]
+
+    @property
+    def end(self) -> int:
+        return self.exons[-1][1]
+
+    @property
+    def is_coding(self) -> bool:
+        return self.thick is not None
+
+    def cds(self) -> list:
+        """Returns the exons clipped to the CDS, empty if non-coding"""
+        if not self.thick:
+            return []
+
+        return [
+            (max(s, self.thick[0]), min(e, self.thick[1]))
+            for s, e in self.exons
+            if e > self.thick[0] and s < self.thick[1]
+        ]
+
+    def to_bed(self) -> BedRecord:
+        """Returns the transcript as a BED12 record"""
+        thick = self.thick or (self.start, self.start)
+
+        return BedRecord(
+            [
+                self.chrom,
+                self.start,
+                self.end,
+                self.name,
+                self.attributes.get("bed_score", "0"),
+                self.strand,
+                thick[0],
+                thick[1],
+                self.attributes.get("item_rgb", "0,0,0"),
+                len(self.exons),
+                ",".join(str(e - s) for s, e in self.exons),
+                ",".join(str(s - self.start) for s, _ in self.exons),
+            ]
+        )
+
+
+class Gene:
+    """A class to represent a gene as the group of its transcripts."""
+
+    def __init__(self, name: str) -> None:
+        """
+        Constructs all the necessary attributes for the Gene object.
+
+        @type name: str
+        @param name: gene name
+        """
+
+        self.name = name
+        self.transcripts = []
+
+    def __iter__(self):
+        return iter(self.transcripts)
+
+    def __len__(self) -> int:
+        return len(self.transcripts)
+
+    @property
+    def chroms(self) -> set:
+        return {tx.chrom for tx in self.transcripts}
+
+    @property
+    def start(self) -> int:
+        return min(tx.start for tx in self.transcripts)
+
+    @property
+    def end(self) -> int:
+        return max(tx.end for tx in self.transcripts)
+
+
+def read_transcripts(records, fmt: str, isoforms=None, pool: dict = None) -> list:
+    """
+    Builds Transcript objects from the records of open_annotation
+
+    @type records: iterable
+    @param records: BedRecord or GxfFeature objects
+    @type fmt: str
+    @param fmt: format of the records (see formats.FORMATS)
+    @type isoforms: str | IsoformMap
+    @param isoforms: gene <-> transcript map, overrides the file genes
+    @type pool: dict
+    @param pool: attribute pool (see Attributes)
+    """
+    if fmt in ("gtf", "gff"):
+        features = {}
+        for feature in records:
+            if feature.transcript_id:
+                features.setdefault(feature.transcript_id, []).append(feature)
+        transcripts = [
+            Transcript.from_features(k, v, pool) for k, v in features.items()
+        ]
+    else:
+        transcripts = [Transcript.from_bed(x, pool=pool) for x in records]
+
+    if isoforms is not None:
+        isoforms = IsoformMap.load(isoforms)
+        for transcript in transcripts:
+            transcript.gene = isoforms.get(transcript.name, transcript.gene)
+
+    return transcripts
+
+
+class AnnotationSet:
+    """A class to hold genes and transcripts of an annotation in file order."""
+
+    def __init__(self, transcripts: list = (), pool: dict = None) -> None:
+        """
+        Constructs all the necessary attributes for the AnnotationSet object.
+
+        @type transcripts: list
+        @param transcripts: Transcript objects
+        @type pool: dict
+        @param pool: attribute pool of the transcripts, shared by the sets
+            derived from this one and freed with the last of them
+        """
+
+        self.genes = {}
+        self.transcripts = {}
+        self.pool = {} if pool is None else pool
+
+        for transcript in transcripts:
+            self.add(transcript)
+
+    @classmethod
+    def from_file(cls, path: str, isoforms=None, fmt: str = None) -> "AnnotationSet":
+        """
+        Reads a .bed/.gtf/.gff/genePred file (optionally gzipped). Genes
+        come from the isoforms map if given, else from the file itself
+        (gene_id/Parent for .gtf/.gff, the transcript for BED/genePred)
+
+        @type path: str
+        @param path: path to the annotation file
+        @type isoforms: str | IsoformMap
+        @param isoforms: gene <-> transcript map
+        @type fmt: str
+        @param fmt: format to use instead of detecting it (see FORMATS)
+        """
+        fmt = fmt or detect_format(path)
+        pool = {}
+
+        return cls(
+            read_transcripts(open_annotation(path, fmt), fmt, isoforms, pool), pool
+        )
+
+    @classmethod
+    def stream(cls, path: str, isoforms=None):
+        """
+        Lazily yields (chrom, AnnotationSet) pairs from a file whose lines
+        are grouped by chromosome, holding one chromosome in memory at a
+        time. Each set is independent, so chromosomes can be handed to
+        worker processes as they come
+
+        @type path: str
+        @param path: path to the annotation file
+        @type isoforms: str | IsoformMap
+        @param isoforms: gene <-> transcript map
+        """
+        fmt = detect_format(path)
+        done = set()
+
+        for chrom, records in groupby(open_annotation(path, fmt), lambda x: x.chrom):
+            if chrom in done:
+                raise PostogaValueError(
+                    f"{path} is not grouped by chromosome ({chrom} appears twice), "
+                    "sort it first",
+                    path,
+                )
+            done.add(chrom)
+            pool = {}
+            yield chrom, cls(read_transcripts(records, fmt, isoforms, pool), pool)
+
+    def __iter__(self):
+        return iter(self.transcripts.values())
+
+    def __len__(self) -> int:
+        return len(self.transcripts)
+
+    def __contains__(self, name: str) -> bool:
+        return name in self.transcripts
+
+    def add(self, transcript: Transcript) -> None:
+        """
+        Adds a transcript, replacing any previous one with the same name
+
+        @type transcript: Transcript
+        @param transcript: transcript to add
+        """
+        if transcript.name in self.transcripts:
+            self.remove(transcript.name)
+
+        self.transcripts[transcript.name] = transcript
+        self.genes.setdefault(transcript.gene, Gene(transcript.gene))
+        self.genes[transcript.gene].transcripts.append(transcript)
+
+    def remove(self, name: str) -> None:
+        """
+        Removes a transcript, and its gene if it was the last one
+
+        @type name: str
+        @param name: transcript name
+        """
+        transcript = self.transcripts.pop(name)
+        gene = self.genes[transcript.gene]
+        gene.transcripts.remove(transcript)
+
+        if not gene.transcripts:
+            del self.genes[transcript.gene]
+
+    def chroms(self) -> list:
+        """Returns the chromosomes of the set in natural order"""
+        return sorted({tx.chrom for tx in self}, key=natural_key)
+
+    def by_chrom(self):
+        """
+        Yields (chrom, AnnotationSet) pairs in natural chromosome order,
+        each holding the transcripts of one chromosome. Transcripts are
+        grouped in a single pass over the set
+        """
+        chroms = {}
+        for tx in self:
+            chroms.setdefault(tx.chrom, []).append(tx)
+
+        for chrom in sorted(chroms, key=natural_key):
+            yield chrom, AnnotationSet(chroms[chrom], self.pool)
+
+    def filter(self, keep) -> "AnnotationSet":
+        """
+        Returns a new set with the transcripts for which keep(tx) is True
+
+        @type keep: callable
+        @param keep: predicate on Transcript objects
+        """
+        return AnnotationSet((tx for tx in self if keep(tx)), self.pool)
+
+    def isoform_map(self) -> IsoformMap:
+        """Returns the gene <-> transcript map of the set"""
+        return IsoformMap((tx.gene, tx.name) for tx in self)
+
+    def write_bed(self, output: str) -> str:
+        """
+        Writes the transcripts as BED12, in insertion order
+
+        @type output: str
+        @param output: path to the output .bed file
+        """
+        with open(output, "w") as out:
+            for transcript in self:
+                out.write(transcript.to_bed().to_line() + "\n")
+
+        return output
@@ -0,0 +1,160 @@
+#!/usr/bin/env python3
+
+
+"""
+A module to flag projections next to or spanning assembly gaps.
+
+Assembly gaps are a frequent source of false gene losses: exons
+falling into N-runs cannot be aligned. Gaps are read from a .bed
+file or computed as N-runs of the query genome, and every projection
+spanning a gap or lying within a margin of one is flagged.
+"""
+
+
+import os
+import re
+from bisect import bisect_left
+from collections import defaultdict
+from constants import Constants
+from logger import Log
+from modules.bed import read_bed
+from modules.fasta import read_fasta
+from modules.run_warnings import RunWarnings
+
+
+__author__ = "Alejandro Gonzales-Irribarren"
+__email__ = "jose.gonzalesdezavala1@unmsm.edu.pe"
+__github__ = "https://github.com/alejandrogzi"
+__version__ = "0.7.0-devel"
+
+
+N_RUN = re.compile(r"[Nn]+")
+
+
+def read_gaps(gaps: str) -> dict:
+    """
+    Reads an assembly gaps .bed file into chrom -> sorted (start, end) lists
+
+    @type gaps: str
+    @param gaps: path to the gaps .bed file
+    """
+    intervals = defaultdict(list)
+
+    with open(gaps, "r") as f:
+        for line in f:
+            if not line.strip() or line.startswith(("#", "track", "browser")):
+                continue
+            chrom, start, end = line.split("\t")[:3]
+            intervals[chrom].append((int(start), int(end)))
+
+    return {chrom: sorted(blocks) for chrom, blocks in intervals.items()}
+
+
+def find_gaps(genome: str, output: str, min_size: int = 1) -> str:
+    """
+    Writes the N-runs of a genome as an assembly gaps .bed file
+
+    @type genome: str
+    @param genome: path to the query genome .fasta
+    @type output: str
+    @param output: path to the gaps .bed file
+    @type min_size: int
+    @param min_size: minimum N-run length
+    """
+    with open(output, "w") as out:
+        for header, seq in read_fasta(genome):
+            chrom = header.split()[0]
+            for run in N_RUN.finditer(seq):
+                if run.end() - run.start() >= min_size:
+                    out.write(f"{chrom}\t{run.start()}\t{run.end()}\n")
+
+    return output
+
+
+def gap_flag(record, gaps: list, starts: list, margin: int) -> str:
+    """
+    Returns contains_gap if a gap lies within the projection span,
+    near_gap if one lies within margin bp of it, None otherwise
+
+    @type record: BedRecord
+    @param record: projection
+    @type gaps: list
+    @param gaps: sorted (start, end) gaps of the projection contig
+    @type starts: list
+    @param starts: gap starts, for bisection
+    @type margin: int
+    @param margin: distance in bp
+    """
+    # Gaps are sorted and do not overlap, so ends are sorted too
+    i = max(bisect_left(starts, record.start - margin) - 1, 0)
+    flag = None
+
+    for start, end in gaps[i:]:
+        if start >= record.end + margin:
+            break
+        if end <= record.start - margin:
+            continue
+        if start < record.end and end > record.start:
+            return "contains_gap"
+        flag = "near_gap"
+
+    return flag
+
+
+def flag_gaps(
+    path: str,
+    bed: str,
+    gaps: str = None,
+    genome: str = None,
+    margin: int = Constants.GAP_MARGIN,
+) -> dict:
+    """
+    Flags projections spanning or near assembly gaps
+
+    @type path: str
+    @param path: path to the results directory
+    @type bed: str
+    @param bed: path to .bed file
+    @type gaps: str
+    @param gaps: path to an assembly gaps .bed file
+    @type genome: str
+    @param genome: path to the query genome .fasta, used if there is no gaps file
+    @type margin: int
+    @param margin: distance in bp to consider a projection near a gap
+    @rtype: dict
+    @return: projection -> contains_gap / near_gap
+    """
+
+    log = Log.connect(path, Constants.FileNames.LOG)
+
+    if not gaps:
+        gaps = find_gaps(genome, os.path.join(path, Constants.FileNames.GAPS))
+        log.record(f"assembly gaps computed from {genome} and written to {gaps}")
+
+    intervals = read_gaps(gaps)
+    starts = {chrom: [x[0] for x in blocks] for chrom, blocks in intervals.items()}
+    flags = {}
+
+    for record in read_bed(bed):
+        if record.chrom not in intervals:
+            continue
+        flag = gap_flag(record, intervals[record.chrom], starts[record.chrom], margin)
+        if flag:
+            flags[record.name] = flag
+
+    warnings = RunWarnings.connect(path)
+    for flag in ("contains_gap", "near_gap"):
+        warnings.add(
+            flag,
+            [name for name, x in flags.items() if x == flag],
+            f"projection {'spans' if flag == 'contains_gap' else f'is within {margin} bp of'} an assembly gap, losses may be artifacts",
+        )
+
+    info = [
+        f"{sum(len(x) for x in intervals.values())} assembly gaps read from {gaps}",
+        f"gap flags: { {x: list(flags.values()).count(x) for x in set(flags.values())} }",
+    ]
+
+    [log.record(i) for i in info]
+
+    return flags
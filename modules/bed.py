@@ -0,0 +1,154 @@
+#!/usr/bin/env python3
+
+
+"""
+A module to represent and read BED12 projections record by record.
+
+BED4 and BED6 lines (e.g. TOGA exon tracks) are read as well, with
+graceful degradation through bed_fields: a missing strand is '.', a
+missing score is 0, the record is non-coding (thickStart = thickEnd =
+start) and its single block spans the whole interval, so they convert
+to exon-only models.
+"""
+
+
+from modules.fileio import open_text
+from modules.errors import PostogaValueError
+
+
+__author__ = "Alejandro Gonzales-Irribarren"
+__email__ = "jose.gonzalesdezavala1@unmsm.edu.pe"
+__github__ = "https://github.com/alejandrogzi"
+__version__ = "0.7.0-devel"
+
+
+def bed_fields(fields: list) -> list:
+    """
+    Pads a BED3-BED11 line to BED12 fields as a single non-coding block
+    (name and strand '.', score 0, itemRgb 0,0,0)
+
+    @type fields: list
+    @param fields: tab-separated fields of the line
+    """
+    if len(fields) >= 12:
+        return fields
+
+    start, end = int(fields[1]), int(fields[2])
+    defaults = [".", "0", ".", str(start), str(start), "0,0,0"]
+    fields = fields[:9] + defaults[len(fields) - 3 :]
+
+    return fields + ["1", f"{end - start},", "0,"]
+
+
+class BedRecord:
+    """A class to represent a single BED12 (or BED4/BED6) projection."""
+
+    def __init__(self, fields: list) -> None:
+        """
+        Constructs all the necessary attributes for the BedRecord object.
+
+        @type fields: list
+        @param fields: the 4, 6 or 12 tab-separated fields of a BED line
+        """
+
+        if len(fields) not in (4, 6) and len(fields) < 12:
+            raise ValueError(f"expected 4, 6 or 12 columns, got {len(fields)}")
+
+        self.columns = min(len(fields), 12)
+        fields = bed_fields(fields)
+        self.chrom = fields[0]
+        self.start = int(fields[1])
+        self.end = int(fields[2])
+        self.name = fields[3]
+        self.score = fields[4]
+        self.strand = fields[5]
+        self.thick_start = int(fields[6])
+        self.thick_end = int(fields[7])
+        self.rgb = fields[8]
+        self.sizes = [int(x) for x in fields[10].strip(",").split(",")]
+        self.offsets = [int(x) for x in fields[11].strip(",").split(",")]
+
+    @classmethod
+    def from_line(cls, line: str) -> "BedRecord":
+        return cls(line.rstrip("\n").split("\t"))
+
+    def exons(self) -> list:
+        """
+        Returns the exon blocks as (start, end) 0-based half-open
+        genomic intervals, sorted by position.
+        """
+        return [
+            (self.start + offset, self.start + offset + size)
+            for offset, size in zip(self.offsets, self.sizes)
+        ]
+
+    def cds(self) -> list:
+        """
+        Returns the exon blocks clipped to thickStart/thickEnd,
+        an empty list if the projection is non-coding.
+        """
+        if self.thick_start >= self.thick_end:
+            return []
+
+        return [
+            (max(start, self.thick_start), min(end, self.thick_end))
+            for start, end in self.exons()
+            if end > self.thick_start and start < self.thick_end
+        ]
+
+    def cds_position(self, offset: int) -> int:
+        """
+        Returns the 0-based genomic position of a CDS offset counted
+        in transcript orientation (0 is the first coding base)
+
+        @type offset: int
+        @param offset: offset within the spliced CDS
+        """
+        blocks = self.cds() if self.strand == "+" else self.cds()[::-1]
+
+        for start, end in blocks:
+            if offset < end - start:
+                return start + offset if self.strand == "+" else end - 1 - offset
+            offset -= end - start
+
+        raise IndexError(f"offset out of the CDS of {self.name}")
+
+    def to_line(self) -> str:
+        return "\t".join(
+            [
+                self.chrom,
+                str(self.start),
+                str(self.end),
+                self.name,
+                self.score,
+                self.strand,
+                str(self.thick_start),
+                str(self.thick_end),
+                self.rgb,
+                str(len(self.sizes)),
+                ",".join(str(x) for x in self.sizes) + ",",
+                ",".join(str(x) for x in self.offsets) + ",",
+            ]
+        )
+
+
+def read_bed(bed: str):
+    """
+    Yields BedRecord objects from a .bed file (optionally gzipped or
+    bgzipped) one line at a time, skipping empty, comment and track
+    lines, so large annotations are never held in memory as a whole
+
+    @type bed: str
+    @param bed: path to .bed file
+    """
+    with open_text(bed) as f:
+        for number, line in enumerate(f, 1):
+            if not line.strip() or line.startswith(("#", "track", "browser")):
+                continue
+            try:
+                record = BedRecord.from_line(line)
+            except (IndexError, ValueError) as e:
+                raise PostogaValueError(
+                    f"malformed BED line {number} of {bed}: {e}", f"{bed}:{number}"
+                ) from e
+            yield record
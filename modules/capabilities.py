@@ -0,0 +1,83 @@
+#!/usr/bin/env python3
+
+
+"""
+A module to report which external tools and optional features are available.
+
+postoga relies on external binaries (bed2gtf, bed2gff, UCSC tools, ...)
+and optional python packages that may be missing or outdated on a given
+system. Modules ask `supports()` before using them and degrade
+gracefully (e.g. skip a .bw or fall back to gzip) instead of failing
+midway through a run.
+"""
+
+
+import shutil
+from functools import lru_cache
+from importlib.util import find_spec
+from constants import Constants
+from modules.utils import shell
+
+
+__author__ = "Alejandro Gonzales-Irribarren"
+__email__ = "jose.gonzalesdezavala1@unmsm.edu.pe"
+__github__ = "https://github.com/alejandrogzi"
+__version__ = "0.7.0-devel"
+
+
+TOOLS = [
+    Constants.ToolNames.BED2GTF,
+    Constants.ToolNames.BED2GFF,
+    Constants.ToolNames.BEDGRAPHTOBIGWIG,
+    Constants.ToolNames.BEDTOBIGBED,
+    Constants.ToolNames.BGZIP,
+]
+PACKAGES = {"zstd": "zstandard", "xz": "lzma"}
+
+
+def tool_version(tool: str) -> str:
+    """
+    Returns the version string of an external tool, None if it is not
+    in PATH and an empty string if it does not report a version
+
+    @type tool: str
+    @param tool: binary name
+    """
+    if shutil.which(tool) is None:
+        return None
+
+    return shell(f"{tool} --version 2>&1 | head -n 1")
+
+
+@lru_cache(maxsize=None)
+def capabilities() -> dict:
+    """
+    Returns the postoga version, the external tools found (with their
+    versions) and the compression formats available on this system
+    """
+    tools = {tool: tool_version(tool) for tool in TOOLS}
+    compression = ["gz"]
+
+    if tools[Constants.ToolNames.BGZIP] is not None:
+        compression.append("bgzf")
+    for name, package in PACKAGES.items():
+        if find_spec(package) is not None:
+            compression.append(name)
+
+    return {
+        "version": Constants.VERSION,
+        "tools": tools,
+        "compression": compression,
+    }
+
+
+def supports(feature: str) -> bool:
+    """
+    Checks whether an external tool or compression format is available
+
+    @type feature: str
+    @param feature: a tool name (e.g. bed2gtf) or a compression format (e.g. bgzf)
+    """
+    caps = capabilities()
+
+    return caps["tools"].get(feature) is not None or feature in caps["compression"]
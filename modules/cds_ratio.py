@@ -0,0 +1,72 @@
+#!/usr/bin/env python3
+
+
+"""
+A module to compare the CDS length of each projection to its reference.
+
+The ratio is the query CDS length (spliced thickStart-thickEnd of the
+.bed) over the reference CDS length, taken from the reference protein
+in TOGA's protein .fasta (3 bases per residue plus the stop codon, as
+the .bed CDS includes it). Values near 1 are full-length models; the
+ratio is the usual first triage of Partially Intact projections.
+"""
+
+
+import os
+from constants import Constants
+from logger import Log
+from modules.bed import read_bed
+from modules.orf_rescue import reference_lengths
+from modules.rank_projections import reference_transcript
+
+
+__author__ = "Alejandro Gonzales-Irribarren"
+__email__ = "jose.gonzalesdezavala1@unmsm.edu.pe"
+__github__ = "https://github.com/alejandrogzi"
+__version__ = "0.7.0-devel"
+
+
+def cds_length(record) -> int:
+    """Returns the spliced CDS length of a projection, 0 if non-coding"""
+    return sum(end - start for start, end in record.cds())
+
+
+def cds_ratios(path: str, bed: str, decimals: int = None) -> dict:
+    """
+    Returns projection -> query CDS length / reference CDS length for
+    the coding projections of a .bed file whose reference protein is known
+
+    @type path: str
+    @param path: path to the results directory
+    @type bed: str
+    @param bed: path to .bed file
+    @type decimals: int
+    @param decimals: round the ratios to this many decimals
+    """
+
+    log = Log.connect(path, Constants.FileNames.LOG)
+
+    protein = os.path.join(path, Constants.FileNames.PROTEIN)
+    lengths = reference_lengths(protein)
+
+    ratios, unknown = {}, 0
+    for record in read_bed(bed):
+        length = cds_length(record)
+        reference = lengths.get(reference_transcript(record.name))
+        if not length:
+            continue
+        if not reference:
+            unknown += 1
+            continue
+        ratio = length / (3 * reference + 3)
+        ratios[record.name] = round(ratio, decimals) if decimals is not None else ratio
+
+    info = [
+        f"CDS length ratio computed for {len(ratios)} coding projections from {protein}",
+        f"{unknown} coding projections without a reference protein",
+        f"{sum(x < 0.5 for x in ratios.values())} projections below half the reference CDS length",
+    ]
+
+    [log.record(i) for i in info]
+
+    return ratios
@@ -0,0 +1,151 @@
+#!/usr/bin/env python3
+
+
+"""
+A module to process and write annotations with bounded memory.
+
+Lines are spilled to one temporary file per chromosome as they are
+produced; they are then read back one chromosome at a time (ChromSpill)
+or sorted on their own and appended to the output in chromosome order
+(ChromSpillWriter), so memory peaks at the largest chromosome instead
+of the whole genome:
+
+``` python
+from modules.chrom_spill import ChromSpill, ChromSpillWriter
+
+with ChromSpill("temp/") as spill:
+    spill.spill("chr2", "chr2\\t...\\n")
+    for chrom, lines in spill.chromosomes():
+        ...
+
+with ChromSpillWriter("out.saf", "temp/", header="GeneID\\tChr\\n") as out:
+    out.write("chr2", 100, "G1\\tchr2\\n")
+```
+"""
+
+
+import os
+import shutil
+import tempfile
+
+
+__author__ = "Alejandro Gonzales-Irribarren"
+__email__ = "jose.gonzalesdezavala1@unmsm.edu.pe"
+__github__ = "https://github.com/alejandrogzi"
+__version__ = "0.7.0-devel"
+
+
+class ChromSpill:
+    """A class to group lines by chromosome through per-chromosome spill files."""
+
+    # Spill files kept open at once, fragmented assemblies have thousands
+    MAX_OPEN = 64
+
+    def __init__(self, tmp: str) -> None:
+        """
+        Constructs all the necessary attributes for the ChromSpill object.
+
+        @type tmp: str
+        @param tmp: directory for the spill files
+        """
+
+        os.makedirs(tmp, exist_ok=True)
+        self.tmp = tempfile.mkdtemp(dir=tmp, prefix="spill.")
+        self.spills = {}
+        self.handles = {}
+        self.lines = 0
+
+    def __enter__(self) -> "ChromSpill":
+        return self
+
+    def __exit__(self, *args) -> None:
+        [f.close() for f in self.handles.values()]
+        shutil.rmtree(self.tmp, ignore_errors=True)
+
+    def spill(self, chrom: str, line: str) -> None:
+        """
+        Spills a line (with its trailing newline) to its chromosome
+
+        @type chrom: str
+        @param chrom: chromosome of the line
+        @type line: str
+        @param line: the line itself
+        """
+        if chrom not in self.handles:
+            if len(self.handles) >= self.MAX_OPEN:
+                self.handles.pop(next(iter(self.handles))).close()
+            name = self.spills.setdefault(
+                chrom, os.path.join(self.tmp, f"{len(self.spills)}.spill")
+            )
+            self.handles[chrom] = open(name, "a")
+        self.handles[chrom].write(line)
+        self.lines += 1
+
+    def chromosomes(self, key=None):
+        """
+        Yields (chrom, lines) one chromosome at a time, chromosomes sorted
+        by key and lines in spill order
+
+        @type key: callable
+        @param key: sort key of the chromosome names, plain order if None
+        """
+        [f.close() for f in self.handles.values()]
+        self.handles = {}
+
+        for chrom in sorted(self.spills, key=key):
+            with open(self.spills[chrom], "r") as spill:
+                yield chrom, spill.readlines()
+
+
+class ChromSpillWriter(ChromSpill):
+    """A class to sort lines by (chrom, start) through per-chromosome spill files."""
+
+    def __init__(self, output: str, tmp: str, header: str = "") -> None:
+        """
+        Constructs all the necessary attributes for the ChromSpillWriter object.
+
+        @type output: str
+        @param output: path to the sorted output file
+        @type tmp: str
+        @param tmp: directory for the spill files
+        @type header: str
+        @param header: text written before the sorted lines
+        """
+
+        super().__init__(tmp)
+        self.output = output
+        self.header = header
+
+    def __enter__(self) -> "ChromSpillWriter":
+        return self
+
+    def __exit__(self, kind, *args) -> None:
+        try:
+            if kind is None:
+                self.close()
+        finally:
+            super().__exit__(kind, *args)
+
+    def write(self, chrom: str, start: int, line: str) -> None:
+        """
+        Spills a line (with its trailing newline) to its chromosome
+
+        @type chrom: str
+        @param chrom: chromosome of the line
+        @type start: int
+        @param start: sort position of the line
+        @type line: str
+        @param line: the line itself
+        """
+        self.spill(chrom, f"{start}\t{line}")
+
+    def close(self) -> str:
+        """Sorts every chromosome and writes them to the output"""
+        with open(self.output, "w") as out:
+            out.write(self.header)
+            for _, lines in self.chromosomes():
+                rows = [x.split("\t", 1) for x in lines]
+                rows.sort(key=lambda x: int(x[0]))
+                out.writelines(x[1] for x in rows)
+
+        return self.output
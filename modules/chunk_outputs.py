@@ -0,0 +1,212 @@
+#!/usr/bin/env python3
+
+
+"""
+A module to split the outputs of a run into chunks for array jobs.
+
+Genes are balanced over N chunks by their exon count (largest first,
+each into the lightest chunk), so the chunks are roughly the same size
+and a gene never straddles two of them. The converted annotation and
+every extracted .fasta are split with the same gene -> chunk assignment,
+so chunk_003.gtf and chunk_003.<fasta> describe the same genes:
+
+    chunks/chunk_000.gtf
+    chunks/chunk_000.query_annotation.nucleotide.fa
+    ...
+"""
+
+
+import heapq
+import os
+import re
+from collections import defaultdict
+from constants import Constants
+from logger import Log
+from modules.bed import read_bed
+from modules.fasta import read_fasta, write_fasta
+from modules.gxf import GxfFeature, gxf_format
+from modules.isoform_map import IsoformMap
+
+
+__author__ = "Alejandro Gonzales-Irribarren"
+__email__ = "jose.gonzalesdezavala1@unmsm.edu.pe"
+__github__ = "https://github.com/alejandrogzi"
+__version__ = "0.7.0-devel"
+
+
+def assign_chunks(bed: str, genes: IsoformMap, n: int) -> dict:
+    """
+    Returns a gene -> chunk index dictionary balancing exon counts
+
+    @type bed: str
+    @param bed: path to .bed file
+    @type genes: IsoformMap
+    @param genes: gene <-> transcript map
+    @type n: int
+    @param n: number of chunks
+    """
+    weights = defaultdict(int)
+    for record in read_bed(bed):
+        weights[genes.get(record.name, record.name)] += len(record.sizes)
+
+    heap = [(0, i) for i in range(n)]
+    chunks = {}
+    for gene, weight in sorted(weights.items(), key=lambda x: (-x[1], x[0])):
+        load, i = heapq.heappop(heap)
+        chunks[gene] = i
+        heapq.heappush(heap, (load + weight, i))
+
+    return chunks
+
+
+def feature_gene(feature: GxfFeature, genes: IsoformMap) -> str:
+    """Returns the gene a .gtf/.gff feature belongs to"""
+    if feature.fmt == "gtf":
+        return feature.attributes.get("gene_id")
+    if feature.feature == "gene":
+        return feature.attributes.get("ID")
+
+    transcript = feature.transcript_id
+    return genes.get(transcript) or genes.get(
+        feature.attributes.get("Parent"), transcript
+    )
+
+
+def header_gene(header: str, genes: IsoformMap) -> str:
+    """
+    Returns the gene of a .fasta record, looking for a projection among
+    the header fields (templated headers keep the name somewhere)
+    """
+    fields = [x for x in re.split(r"[\s|;]+", header) if x]
+    for field in fields:
+        gene = genes.get(field)
+        if gene is not None:
+            return gene
+
+    return fields[0] if fields else header
+
+
+def chunk_name(path: str, i: int, suffix: str) -> str:
+    return os.path.join(path, Constants.FileNames.CHUNKS, f"chunk_{i:03d}.{suffix}")
+
+
+def split_annotation(
+    path: str, annotation: str, chunks: dict, genes: IsoformMap, n: int
+) -> list:
+    """
+    Splits a .gtf/.gff file by the gene -> chunk assignment, copying
+    lines as they are and the header pragmas into every chunk
+
+    @type path: str
+    @param path: path to the results directory
+    @type annotation: str
+    @param annotation: path to .gtf/.gff file
+    @type chunks: dict
+    @param chunks: gene -> chunk index
+    @type genes: IsoformMap
+    @param genes: gene <-> transcript map
+    @type n: int
+    @param n: number of chunks
+    """
+    fmt = gxf_format(annotation)
+    ext = os.path.splitext(annotation)[1].lstrip(".")
+    files = [chunk_name(path, i, ext) for i in range(n)]
+    handles = [open(f, "w", newline="") for f in files]
+
+    try:
+        with open(annotation, "r", newline="") as f:
+            for line in f:
+                if line.startswith("#"):
+                    [h.write(line) for h in handles]
+                    continue
+                if not line.strip():
+                    continue
+                gene = feature_gene(GxfFeature.from_line(line, fmt), genes)
+                handles[chunks.get(gene, 0)].write(line)
+    finally:
+        [h.close() for h in handles]
+
+    return files
+
+
+def split_fasta(
+    path: str, fasta: str, chunks: dict, genes: IsoformMap, n: int
+) -> list:
+    """
+    Splits a .fasta file by the gene -> chunk assignment
+
+    @type path: str
+    @param path: path to the results directory
+    @type fasta: str
+    @param fasta: path to .fasta file
+    @type chunks: dict
+    @param chunks: gene -> chunk index
+    @type genes: IsoformMap
+    @param genes: gene <-> transcript map
+    @type n: int
+    @param n: number of chunks
+    """
+    files = [chunk_name(path, i, os.path.basename(fasta)) for i in range(n)]
+    handles = [open(f, "w") for f in files]
+
+    try:
+        for header, seq in read_fasta(fasta):
+            write_fasta(handles[chunks.get(header_gene(header, genes), 0)], header, seq)
+    finally:
+        [h.close() for h in handles]
+
+    return files
+
+
+def chunk_outputs(
+    path: str,
+    bed: str,
+    isoforms: str,
+    n: int,
+    annotation: str = None,
+    fastas: list = None,
+) -> dict:
+    """
+    Splits the converted annotation and the extracted .fasta files into
+    n chunks that never split a gene
+
+    @type path: str
+    @param path: path to the results directory
+    @type bed: str
+    @param bed: path to .bed file the outputs were built from
+    @type isoforms: str
+    @param isoforms: path to the isoforms file
+    @type n: int
+    @param n: number of chunks
+    @type annotation: str
+    @param annotation: path to .gtf/.gff file, if any
+    @type fastas: list
+    @param fastas: paths to .fasta files
+    @rtype: dict
+    @return: input file -> chunk files
+    """
+
+    log = Log.connect(path, Constants.FileNames.LOG)
+
+    os.makedirs(os.path.join(path, Constants.FileNames.CHUNKS), exist_ok=True)
+    genes = IsoformMap.load(isoforms)
+    chunks = assign_chunks(bed, genes, n)
+
+    files = {}
+    if annotation:
+        files[annotation] = split_annotation(path, annotation, chunks, genes, n)
+    for fasta in fastas or []:
+        files[fasta] = split_fasta(path, fasta, chunks, genes, n)
+
+    sizes = [0] * n
+    for i in chunks.values():
+        sizes[i] += 1
+
+    info = [
+        f"split {len(files)} outputs into {n} chunks of {min(sizes)}-{max(sizes)} genes",
+        f"chunks written to {os.path.join(path, Constants.FileNames.CHUNKS)}",
+    ]
+
+    [log.record(i) for i in info]
+
+    return files
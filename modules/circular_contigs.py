@@ -0,0 +1,101 @@
+#!/usr/bin/env python3
+
+
+"""
+A module to handle projections on circular contigs (MT, plasmids).
+
+Projections spanning the origin of a circular contig may come with
+end < start. They are rewritten with coordinates running past the end
+of the contig (end > contig length), the GFF3 convention for circular
+sequences, so conversion and sequence extraction handle them linearly.
+"""
+
+
+from constants import Constants
+from logger import Log
+from modules.bed import read_bed
+from modules.errors import PostogaValueError
+from modules.fasta import is_mitochondrial
+from modules.run_warnings import RunWarnings
+from modules.utils import chrom_sizes_reader, output_stem
+
+
+__author__ = "Alejandro Gonzales-Irribarren"
+__email__ = "jose.gonzalesdezavala1@unmsm.edu.pe"
+__github__ = "https://github.com/alejandrogzi"
+__version__ = "0.7.0-devel"
+
+
+def unwrap(record, length: int):
+    """
+    Moves the coordinates after the origin of a wrapping projection
+    past the contig end. Block offsets are relative to the start and
+    stay valid; only end and thickEnd need to move
+
+    @type record: BedRecord
+    @param record: projection with end < start
+    @type length: int
+    @param length: contig length
+    """
+    record.end += length
+    if record.thick_end < record.thick_start:
+        record.thick_end += length
+    elif record.thick_start < min(record.start, record.thick_end):
+        record.thick_start += length
+        record.thick_end += length
+
+    return record
+
+
+def normalize_circular(
+    path: str, bed: str, chrom_sizes: str, circular: set = None
+) -> str:
+    """
+    Writes a copy of a .bed file where projections wrapping around the
+    origin of a circular contig end past the contig length
+
+    @type path: str
+    @param path: path to the results directory
+    @type bed: str
+    @param bed: path to .bed file
+    @type chrom_sizes: str
+    @param chrom_sizes: path to a chrom.sizes file
+    @type circular: set
+    @param circular: circular contigs, on top of the mitochondrial ones
+    """
+
+    log = Log.connect(path, Constants.FileNames.LOG)
+
+    sizes = chrom_sizes_reader(chrom_sizes)
+    records, wrapped = list(read_bed(bed)), []
+
+    for record in records:
+        if record.end >= record.start:
+            continue
+        if not is_mitochondrial(record.chrom, circular) or record.chrom not in sizes:
+            raise PostogaValueError(
+                f"{record.name} has end < start on {record.chrom}, "
+                "which is not a known circular contig",
+                record.name,
+            )
+        unwrap(record, sizes[record.chrom])
+        wrapped.append(record.name)
+
+    if not wrapped:
+        log.record("no origin-spanning projections found")
+        return bed
+
+    f = f"{output_stem(bed)}.{Constants.FileNames.CIRCULAR_BED}"
+    with open(f, "w") as out:
+        for record in records:
+            out.write(record.to_line() + "\n")
+
+    RunWarnings.connect(path).add(
+        "origin_spanning",
+        wrapped,
+        "projection spans the origin of a circular contig, written with end > contig length",
+    )
+
+    log.record(f"{len(wrapped)} origin-spanning projections normalized in {f}")
+
+    return f
@@ -0,0 +1,150 @@
+#!/usr/bin/env python3
+
+
+"""
+A module to build a gene x species loss class matrix from several TOGA runs.
+
+Each run contributes the gene-level class of its loss summary. The
+matrix can be written wide (one column per species, for heatmaps) or
+long (gene, species, class rows, for R/tidyverse). With symbol tables,
+genes are joined on normalized symbols instead of reference IDs.
+"""
+
+
+import os
+from constants import Constants
+from logger import Log
+from modules.gene_symbols import read_aliases, read_symbols, rekey_classes
+from modules.errors import PostogaValueError
+from modules.recode import recode
+from modules.table_writer import write_table
+
+
+__author__ = "Alejandro Gonzales-Irribarren"
+__email__ = "jose.gonzalesdezavala1@unmsm.edu.pe"
+__github__ = "https://github.com/alejandrogzi"
+__version__ = "0.7.0-devel"
+
+
+def gene_classes(path: str, mapping: dict = None) -> dict:
+    """
+    Reads the gene -> class pairs of a TOGA run (GENE rows of the loss summary)
+
+    @type path: str
+    @param path: path to a TOGA results directory
+    @type mapping: dict
+    @param mapping: optional class recode mapping
+    """
+    classes = {}
+
+    with open(os.path.join(path, Constants.FileNames.CLASS), "r") as f:
+        for line in f:
+            fields = line.rstrip("\n").split("\t")
+            if len(fields) >= 3 and fields[0] == "GENE":
+                classes[fields[1]] = recode(fields[2], mapping)
+
+    return classes
+
+
+def to_long(matrix: dict, species: list) -> list:
+    """
+    Converts a gene -> {species: class} matrix to (gene, species, class) rows
+
+    @type matrix: dict
+    @param matrix: gene -> {species: class}
+    @type species: list
+    @param species: species order
+    """
+    return [
+        [gene, name, classes[name]]
+        for gene, classes in matrix.items()
+        for name in species
+        if name in classes
+    ]
+
+
+def to_wide(matrix: dict, species: list) -> list:
+    """
+    Converts a gene -> {species: class} matrix to one row per gene with a
+    column per species (NA where a species lacks the gene)
+
+    @type matrix: dict
+    @param matrix: gene -> {species: class}
+    @type species: list
+    @param species: species order
+    """
+    return [
+        [gene] + [classes.get(name) for name in species]
+        for gene, classes in matrix.items()
+    ]
+
+
+def class_matrix(
+    paths: list,
+    names: list = None,
+    shape: str = "wide",
+    output: str = None,
+    mapping: dict = None,
+    symbols: list = None,
+    aliases: list = None,
+) -> str:
+    """
+    Writes the gene x species class matrix of several TOGA runs
+
+    @type paths: list
+    @param paths: paths to TOGA results directories
+    @type names: list
+    @param names: species names, in the order of paths (default: directory names)
+    @type shape: str
+    @param shape: wide or long
+    @type output: str
+    @param output: path to the output .tsv (default: in the first run)
+    @type mapping: dict
+    @param mapping: optional class recode mapping
+    @type symbols: list
+    @param symbols: gene ID -> symbol tables, one per run or one for all runs
+    @type aliases: list
+    @param aliases: HGNC/MGI-style alias tables to resolve symbols
+    """
+
+    log = Log.connect(paths[0], Constants.FileNames.LOG)
+
+    names = names or [os.path.basename(os.path.normpath(x)) for x in paths]
+    if len(names) != len(paths):
+        raise PostogaValueError(
+            f"{len(names)} names given for {len(paths)} runs", ",".join(names)
+        )
+
+    if symbols and len(symbols) not in (1, len(paths)):
+        raise PostogaValueError(
+            f"{len(symbols)} symbol tables given for {len(paths)} runs",
+            ",".join(symbols),
+        )
+    if symbols:
+        tables = [read_symbols(x) for x in symbols]
+        tables = tables * len(paths) if len(tables) == 1 else tables
+        aliases = read_aliases(aliases)
+
+    matrix = {}
+    for i, (name, path) in enumerate(zip(names, paths)):
+        classes = gene_classes(path, mapping)
+        if symbols:
+            classes, collisions = rekey_classes(classes, tables[i], aliases)
+            for key, genes in collisions.items():
+                log.record(f"{name}: {','.join(genes)} collapse onto {key}")
+        for gene, cls in classes.items():
+            matrix.setdefault(gene, {})[name] = cls
+
+    f = output or os.path.join(
+        paths[0], Constants.FileNames.CLASS_MATRIX.format(shape)
+    )
+    if shape == "long":
+        write_table(to_long(matrix, names), f, ["gene", "species", "class"])
+    else:
+        write_table(to_wide(matrix, names), f, ["gene"] + names)
+
+    log.record(
+        f"{shape} class matrix of {len(matrix)} genes across {len(names)} runs written to {f}"
+    )
+
+    return f
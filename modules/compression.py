@@ -0,0 +1,69 @@
+#!/usr/bin/env python3
+
+
+"""
+A module to compress finished outputs as gz, bgzf, zstd or xz.
+
+Plain and compressed files are opened through fileio, which detects
+the compression from the magic bytes. bgzf is written with bgzip so
+files stay indexable by tabix; it falls back to gz when bgzip is missing.
+"""
+
+
+import os
+import shutil
+import subprocess
+from constants import Constants
+from modules.capabilities import supports
+from modules.errors import PostogaToolError
+from modules.fileio import open_binary
+
+
+__author__ = "Alejandro Gonzales-Irribarren"
+__email__ = "jose.gonzalesdezavala1@unmsm.edu.pe"
+__github__ = "https://github.com/alejandrogzi"
+__version__ = "0.7.0-devel"
+
+
+def compress_file(f: str, compress: str, output: str = None) -> tuple:
+    """
+    Compresses a file as gz, bgzf, zstd or xz, removing the plain file
+    only once the compressed copy is written
+
+    @type f: str
+    @param f: path to the plain file
+    @type compress: str
+    @param compress: gz, bgzf, zstd or xz
+    @type output: str
+    @param output: path to the compressed file (default: f + extension)
+    @rtype: tuple
+    @return: (path to the compressed file, compression used)
+    """
+    if compress == "bgzf" and not supports(Constants.ToolNames.BGZIP):
+        compress = "gz"
+
+    output = output or f"{f}{Constants.COMPRESSION_EXTENSIONS[compress]}"
+    if compress == "bgzf":
+        try:
+            with open(output, "wb") as dst:
+                subprocess.run(
+                    [Constants.ToolNames.BGZIP, "-c", f],
+                    stdout=dst,
+                    stderr=subprocess.PIPE,
+                    check=True,
+                )
+        except (OSError, subprocess.CalledProcessError) as e:
+            if os.path.exists(output):
+                os.remove(output)
+            detail = e.stderr.decode().strip() if getattr(e, "stderr", None) else e
+            raise PostogaToolError(
+                f"{Constants.ToolNames.BGZIP} failed on {f}, kept it uncompressed: "
+                f"{detail}",
+                f,
+            ) from e
+    else:
+        with open(f, "rb") as src, open_binary(output, "wb", compress) as dst:
+            shutil.copyfileobj(src, dst)
+    os.remove(f)
+
+    return output, compress
@@ -0,0 +1,298 @@
+#!/usr/bin/env python3
+
+
+"""
+A module to call basic variant consequences against the query annotation.
+
+Given a .vcf, the query annotation and the query genome, classifies
+each variant per overlapping projection (synonymous, missense,
+stop gained/lost, start lost, frameshift, in-frame indel, splice,
+UTR, intronic). It is not meant to replace VEP, just to give
+population-genomics users quick consequence calls.
+"""
+
+
+import os
+from collections import defaultdict
+from constants import Constants
+from logger import Log
+from modules.bed import read_bed
+from modules.fasta import (
+    codon_table,
+    fasta_to_dict,
+    genetic_code,
+    is_mitochondrial,
+    reverse_complement,
+    splice,
+)
+from modules.isoform_map import IsoformMap
+
+
+__author__ = "Alejandro Gonzales-Irribarren"
+__email__ = "jose.gonzalesdezavala1@unmsm.edu.pe"
+__github__ = "https://github.com/alejandrogzi"
+__version__ = "0.7.0-devel"
+
+
+BIN_SIZE = 100000
+SPLICE_SITE = 2
+SPLICE_REGION = 8
+
+
+def read_vcf(vcf: str):
+    """
+    Yields (chrom, pos, ref, alt) tuples from a .vcf file, with pos
+    0-based and one tuple per alternative allele. Symbolic alleles are skipped
+
+    @type vcf: str
+    @param vcf: path to .vcf file
+    """
+    with open(vcf, "r") as f:
+        for line in f:
+            if line.startswith("#") or not line.strip():
+                continue
+            fields = line.rstrip("\n").split("\t")
+            for alt in fields[4].split(","):
+                if alt.startswith("<") or alt in ("*", "."):
+                    continue
+                yield fields[0], int(fields[1]) - 1, fields[3].upper(), alt.upper()
+
+
+def index_records(records: list) -> dict:
+    """
+    Bins projections by chromosome and BIN_SIZE windows for overlap queries
+
+    @type records: list
+    @param records: list of BedRecord objects
+    """
+    bins = defaultdict(list)
+
+    for record in records:
+        for i in range(record.start // BIN_SIZE, (record.end - 1) // BIN_SIZE + 1):
+            bins[(record.chrom, i)].append(record)
+
+    return bins
+
+
+def cds_offset(record, pos: int) -> int:
+    """
+    Returns the offset of a genomic position within the CDS, in transcript
+    orientation, None if the position is not coding
+
+    @type record: BedRecord
+    @param record: projection
+    @type pos: int
+    @param pos: 0-based genomic position
+    """
+    offset, hit = 0, None
+
+    for start, end in record.cds():
+        if start <= pos < end:
+            hit = offset + pos - start
+        offset += end - start
+
+    if hit is None:
+        return None
+
+    return hit if record.strand == "+" else offset - 1 - hit
+
+
+def intronic_consequence(record, pos: int) -> str:
+    """
+    Classifies an intronic position by its distance to the closest exon
+
+    @type record: BedRecord
+    @param record: projection
+    @type pos: int
+    @param pos: 0-based genomic position
+    """
+    exons = record.exons()
+
+    for (_, left), (right, _) in zip(exons, exons[1:]):
+        if left <= pos < right:
+            donor, acceptor = pos - left, right - 1 - pos
+            if record.strand == "-":
+                donor, acceptor = acceptor, donor
+            if donor < SPLICE_SITE:
+                return "splice_donor_variant"
+            if acceptor < SPLICE_SITE:
+                return "splice_acceptor_variant"
+            if min(donor, acceptor) < SPLICE_REGION:
+                return "splice_region_variant"
+            return "intron_variant"
+
+    return None
+
+
+def coding_consequence(
+    record, seq: str, pos: int, ref: str, alt: str, table: dict, starts: list
+) -> tuple:
+    """
+    Classifies a variant overlapping the CDS of a projection
+
+    @type record: BedRecord
+    @param record: projection
+    @type seq: str
+    @param seq: spliced CDS sequence of the projection
+    @type pos: int
+    @param pos: 0-based genomic position
+    @type ref: str
+    @param ref: reference allele
+    @type alt: str
+    @param alt: alternative allele
+    @type table: dict
+    @param table: codon -> amino acid dictionary
+    @type starts: list
+    @param starts: start codons of the genetic code
+    @rtype: tuple
+    @return: (consequence, codons, amino acids)
+    """
+    if len(ref) != len(alt):
+        diff = len(alt) - len(ref)
+        if diff % 3:
+            return "frameshift_variant", ".", "."
+        return ("inframe_insertion" if diff > 0 else "inframe_deletion"), ".", "."
+
+    offset = cds_offset(record, pos)
+    if len(ref) > 1 or offset is None:
+        return "coding_sequence_variant", ".", "."
+
+    base = alt if record.strand == "+" else reverse_complement(alt)
+    frame = offset % 3
+    codon = seq[offset - frame : offset - frame + 3].upper()
+
+    if len(codon) < 3:
+        return "incomplete_terminal_codon_variant", ".", "."
+
+    mutated = codon[:frame] + base + codon[frame + 1 :]
+    before, after = table.get(codon, "X"), table.get(mutated, "X")
+    changes = f"{codon}/{mutated}", f"{before}/{after}"
+
+    if offset < 3 and after != before and codon in starts:
+        return "start_lost", *changes
+    if before == after:
+        return "synonymous_variant", *changes
+    if after == "*":
+        return "stop_gained", *changes
+    if before == "*":
+        return "stop_lost", *changes
+
+    return "missense_variant", *changes
+
+
+def classify(
+    record, seq: str, pos: int, ref: str, alt: str, code: str, mt: bool = False
+) -> tuple:
+    """
+    Classifies a variant against a single projection
+
+    @type record: BedRecord
+    @param record: projection
+    @type seq: str
+    @param seq: spliced CDS sequence of the projection
+    @type code: str
+    @param code: name of the genetic code
+    @type mt: bool
+    @param mt: the projection lies on a mitochondrial contig (no splicing)
+    @rtype: tuple
+    @return: (consequence, codons, amino acids)
+    """
+    for start, end in record.exons():
+        if start <= pos < end:
+            if record.thick_start < record.thick_end:
+                if record.thick_start <= pos < record.thick_end:
+                    return coding_consequence(
+                        record,
+                        seq,
+                        pos,
+                        ref,
+                        alt,
+                        codon_table(code),
+                        Constants.START_CODONS[code],
+                    )
+                upstream = pos < record.thick_start
+                if record.strand == "-":
+                    upstream = not upstream
+                utr = "5_prime_UTR_variant" if upstream else "3_prime_UTR_variant"
+                return utr, ".", "."
+            return "non_coding_transcript_exon_variant", ".", "."
+
+    if mt:
+        return "intron_variant", ".", "."
+
+    return intronic_consequence(record, pos), ".", "."
+
+
+def call_consequences(
+    path: str,
+    vcf: str,
+    annotation: str,
+    genome: str,
+    isoforms: str,
+    mt_contigs: set = None,
+) -> str:
+    """
+    Writes a table with the consequence of each variant per overlapping projection
+
+    @type path: str
+    @param path: path to the results directory
+    @type vcf: str
+    @param vcf: path to .vcf file
+    @type annotation: str
+    @param annotation: path to .bed file (query_annotation/filtered)
+    @type genome: str
+    @param genome: path to the query genome .fasta
+    @type isoforms: str
+    @param isoforms: path to the isoforms file
+    @type mt_contigs: set
+    @param mt_contigs: extra contig names to treat as mitochondrial
+    """
+
+    log = Log.connect(path, Constants.FileNames.LOG)
+
+    records = list(read_bed(annotation))
+    bins = index_records(records)
+    genes = IsoformMap.load(isoforms)
+    seqs = fasta_to_dict(genome, keep={record.chrom for record in records})
+
+    cds, counts, variants = {}, defaultdict(int), 0
+    f = os.path.join(path, Constants.FileNames.CONSEQUENCES)
+
+    with open(f, "w") as out:
+        out.write(
+            "chrom\tpos\tref\talt\ttranscript\tgene\tconsequence\tcodons\tamino_acids\n"
+        )
+        for chrom, pos, ref, alt in read_vcf(vcf):
+            variants += 1
+            for record in bins.get((chrom, pos // BIN_SIZE), []):
+                if not record.start <= pos < record.end or chrom not in seqs:
+                    continue
+                if record.name not in cds:
+                    cds[record.name] = splice(
+                        seqs, chrom, record.cds(), record.strand
+                    )
+
+                consequence, codons, aa = classify(
+                    record,
+                    cds[record.name],
+                    pos,
+                    ref,
+                    alt,
+                    genetic_code(chrom, mt_contigs),
+                    is_mitochondrial(chrom, mt_contigs),
+                )
+                counts[consequence] += 1
+                gene = genes.get(record.name, record.name)
+                out.write(
+                    f"{chrom}\t{pos + 1}\t{ref}\t{alt}\t{record.name}\t{gene}\t{consequence}\t{codons}\t{aa}\n"
+                )
+
+    info = [
+        f"classified {sum(counts.values())} variant-projection pairs from {variants} alleles",
+        f"consequence stats: {dict(counts)}",
+        f"variant consequences written to {f}",
+    ]
+
+    [log.record(i) for i in info]
+
+    return f
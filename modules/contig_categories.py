@@ -0,0 +1,149 @@
+#!/usr/bin/env python3
+
+
+"""
+A module to break the summary statistics down by contig category.
+
+Contigs are tagged as autosome, sex, unplaced or MT, from a two-column
+(contig, category) table when given and from their names otherwise
+(chrX/Y/Z/W, chrM/MT, chrUn/_random/scaffold). Unplaced scaffolds carry
+most fragmented projections, so a loss spread over them is read as an
+assembly artifact rather than a biological loss.
+"""
+
+
+import os
+import re
+import pandas as pd
+from constants import Constants
+from logger import Log
+from modules.bed import read_bed
+from modules.errors import PostogaValueError
+from modules.fasta import is_mitochondrial
+from modules.table_writer import write_table
+from modules.utils import ancestral_reader
+
+
+__author__ = "Alejandro Gonzales-Irribarren"
+__email__ = "jose.gonzalesdezavala1@unmsm.edu.pe"
+__github__ = "https://github.com/alejandrogzi"
+__version__ = "0.7.0-devel"
+
+
+SEX = re.compile(r"^(chr)?[XYZW]$", re.IGNORECASE)
+UNPLACED = re.compile(r"(^chrUn|_random$|_alt$|scaffold|contig|^Un)", re.IGNORECASE)
+
+
+def infer_category(chrom: str, mt_contigs: set = None) -> str:
+    """
+    Returns the category of a contig from its name
+
+    @type chrom: str
+    @param chrom: contig name
+    @type mt_contigs: set
+    @param mt_contigs: extra contig names to treat as mitochondrial
+    """
+    if is_mitochondrial(chrom, mt_contigs):
+        return "MT"
+    if SEX.match(chrom):
+        return "sex"
+    if UNPLACED.search(chrom):
+        return "unplaced"
+
+    return "autosome"
+
+
+def read_categories(categories: str) -> dict:
+    """
+    Reads a (contig, category) table, categories in
+    Constants.CONTIG_CATEGORIES
+
+    @type categories: str
+    @param categories: path to the tab-separated table
+    """
+    mapping = {}
+
+    with open(categories, "r") as f:
+        for line in f:
+            fields = line.rstrip("\n").split("\t")
+            if len(fields) < 2 or line.startswith("#"):
+                continue
+            if fields[1] not in Constants.CONTIG_CATEGORIES:
+                raise PostogaValueError(
+                    f"unknown contig category {fields[1]!r} for {fields[0]}, expected one of {', '.join(Constants.CONTIG_CATEGORIES)}",
+                    categories,
+                )
+            mapping[fields[0]] = fields[1]
+
+    return mapping
+
+
+def category_stats(
+    path: str,
+    bed: str,
+    table: pd.DataFrame,
+    categories: str = None,
+    ancestral: str = None,
+    mt_contigs: set = None,
+) -> pd.DataFrame:
+    """
+    Adds a `contig_category` column to the query table and writes the
+    projection, gene, class and ancestral completeness counts by category
+
+    @type path: str
+    @param path: path to the results directory
+    @type bed: str
+    @param bed: path to .bed file
+    @type table: pd.DataFrame
+    @param table: query table
+    @type categories: str
+    @param categories: (contig, category) table, contig names used if None
+    @type ancestral: str
+    @param ancestral: path to the ancestral genes file, for completeness
+    @type mt_contigs: set
+    @param mt_contigs: extra contig names to treat as mitochondrial
+    """
+
+    log = Log.connect(path, Constants.FileNames.LOG)
+
+    mapping = read_categories(categories) if categories else {}
+    category = {
+        x.name: mapping.get(x.chrom) or infer_category(x.chrom, mt_contigs)
+        for x in read_bed(bed)
+    }
+    table["contig_category"] = table["transcripts"].map(category)
+
+    projected = table.dropna(subset=["contig_category"])
+    # Each gene is counted in the category of its best projection
+    genes = projected.sort_values(
+        by="class", key=lambda x: x.map(Constants.ORDER)
+    ).drop_duplicates("t_gene", keep="first")
+    reference = set(ancestral_reader(ancestral)) if ancestral else set()
+
+    rows = []
+    for name in Constants.CONTIG_CATEGORIES:
+        members = genes[genes["contig_category"] == name]
+        classes = members["class"].value_counts().to_dict()
+        row = {
+            "category": name,
+            "projections": int((projected["contig_category"] == name).sum()),
+            "genes": len(members),
+            **{x: classes.get(x, 0) for x in Constants.ORDER},
+        }
+        if reference:
+            row["ancestral_genes"] = int(members["t_gene"].isin(reference).sum())
+            row["ancestral_fraction"] = row["ancestral_genes"] / len(reference)
+        rows.append(row)
+
+    f = os.path.join(path, Constants.FileNames.CONTIG_CATEGORIES)
+    write_table(rows, f)
+
+    info = [
+        f"{x['category']}: {x['projections']} projections, {x['genes']} genes"
+        for x in rows
+    ]
+    info.append(f"stats by contig category written to {f}")
+
+    [log.record(i) for i in info]
+
+    return table
@@ -0,0 +1,105 @@
+#!/usr/bin/env python3
+
+
+"""
+A module to flag projections truncated by contig ends.
+
+Projections whose span reaches (or nearly reaches) the end of a
+contig are often partial models of a gene split across contigs by
+a fragmented assembly. Counting them by class tells how much of the
+loss signal may be driven by fragmentation.
+"""
+
+
+from collections import Counter
+from constants import Constants
+from logger import Log
+from modules.bed import read_bed
+from modules.run_warnings import RunWarnings
+from modules.utils import chrom_sizes_reader
+
+
+__author__ = "Alejandro Gonzales-Irribarren"
+__email__ = "jose.gonzalesdezavala1@unmsm.edu.pe"
+__github__ = "https://github.com/alejandrogzi"
+__version__ = "0.7.0-devel"
+
+
+def contig_end(record, length: int, margin: int) -> str:
+    """
+    Returns which contig end a projection abuts (start, end or both),
+    None if it is farther than margin bp from both
+
+    @type record: BedRecord
+    @param record: projection
+    @type length: int
+    @param length: contig length
+    @type margin: int
+    @param margin: distance in bp
+    """
+    left, right = record.start <= margin, record.end >= length - margin
+
+    if left and right:
+        return "both"
+    if left or right:
+        return "start" if left else "end"
+
+    return None
+
+
+def flag_contig_ends(
+    path: str,
+    bed: str,
+    chrom_sizes: str,
+    table=None,
+    margin: int = Constants.CONTIG_END_MARGIN,
+) -> dict:
+    """
+    Flags projections within margin bp of a contig end
+
+    @type path: str
+    @param path: path to the results directory
+    @type bed: str
+    @param bed: path to .bed file
+    @type chrom_sizes: str
+    @param chrom_sizes: path to a chrom.sizes file
+    @type table: pd.DataFrame
+    @param table: optional query table, for counts by class
+    @type margin: int
+    @param margin: distance in bp to a contig end
+    @rtype: dict
+    @return: projection -> start / end / both
+    """
+
+    log = Log.connect(path, Constants.FileNames.LOG)
+
+    sizes = chrom_sizes_reader(chrom_sizes)
+    flags, total = {}, 0
+
+    for record in read_bed(bed):
+        total += 1
+        if record.chrom in sizes:
+            flag = contig_end(record, sizes[record.chrom], margin)
+            if flag:
+                flags[record.name] = flag
+
+    RunWarnings.connect(path).add(
+        "contig_end",
+        list(flags),
+        f"projection lies within {margin} bp of a contig end, it may be a fragment",
+    )
+
+    info = [
+        f"{len(flags)} of {total} projections within {margin} bp of a contig end ({100 * len(flags) / max(total, 1):.2f}%)",
+        f"contig end stats: {dict(Counter(flags.values()))}",
+    ]
+
+    if table is not None:
+        truncated = table[table["transcripts"].isin(flags.keys())]
+        info.append(
+            f"contig end class stats: {truncated['class'].value_counts().to_dict()}"
+        )
+
+    [log.record(i) for i in info]
+
+    return flags
@@ -0,0 +1,143 @@
+#!/usr/bin/env python3
+
+
+"""
+A module to summarize the annotation quality of each contig/scaffold.
+
+Combines per-contig metrics (gene density, fraction of flagged
+projections, mean orthology score and assembly gap burden) into a
+single table, so users can decide which scaffolds to trust or leave
+out of comparative analyses.
+"""
+
+
+import os
+from collections import defaultdict
+import pandas as pd
+from constants import Constants
+from logger import Log
+from modules.assembly_gaps import read_gaps
+from modules.bed import read_bed
+from modules.table_writer import write_table
+from modules.utils import chrom_sizes_reader
+
+
+__author__ = "Alejandro Gonzales-Irribarren"
+__email__ = "jose.gonzalesdezavala1@unmsm.edu.pe"
+__github__ = "https://github.com/alejandrogzi"
+__version__ = "0.7.0-devel"
+
+
+COLUMNS = [
+    "contig",
+    "length",
+    "projections",
+    "genes",
+    "genes_per_mb",
+    "flagged_fraction",
+    "mean_score",
+    "gap_bp",
+    "gap_fraction",
+    "quality",
+]
+
+
+def quality_score(mean_score: float, flagged: float, gap_fraction: float) -> float:
+    """
+    Combines the contig metrics into a 0-1 score: the mean of the
+    orthology score, the unflagged fraction and the gap-free fraction.
+    Missing metrics are left out of the mean
+
+    @type mean_score: float
+    @param mean_score: mean orthology score of the projections
+    @type flagged: float
+    @param flagged: fraction of flagged projections
+    @type gap_fraction: float
+    @param gap_fraction: fraction of the contig in assembly gaps
+    """
+    gap_free = None if gap_fraction is None else 1 - gap_fraction
+    metrics = [x for x in (mean_score, 1 - flagged, gap_free) if x is not None]
+
+    return sum(metrics) / len(metrics)
+
+
+def contig_quality(
+    path: str,
+    bed: str,
+    table,
+    chrom_sizes: str = None,
+    gaps: str = None,
+    flags: list = None,
+    select: list = None,
+    decimals: int = 4,
+) -> str:
+    """
+    Writes a per-contig quality table
+
+    @type path: str
+    @param path: path to the results directory
+    @type bed: str
+    @param bed: path to .bed file
+    @type table: pd.DataFrame
+    @param table: query table (scores and genes)
+    @type chrom_sizes: str
+    @param chrom_sizes: optional chrom.sizes file, for density and gap burden
+    @type gaps: str
+    @param gaps: optional assembly gaps .bed file
+    @type flags: list
+    @param flags: projection -> flag dictionaries (gaps, contig ends, ...)
+    @type select: list
+    @param select: columns to write and their order (see write_table)
+    @type decimals: int
+    @param decimals: decimals for floats
+    """
+
+    log = Log.connect(path, Constants.FileNames.LOG)
+
+    scores = dict(zip(table["transcripts"], table["pred"]))
+    genes = dict(zip(table["transcripts"], table["t_gene"]))
+    flagged = set().union(*(x.keys() for x in flags or []))
+
+    sizes = chrom_sizes_reader(chrom_sizes) if chrom_sizes else {}
+    gap_bp = {
+        chrom: sum(end - start for start, end in blocks)
+        for chrom, blocks in (read_gaps(gaps) if gaps else {}).items()
+    }
+
+    contigs = defaultdict(list)
+    for record in read_bed(bed):
+        contigs[record.chrom].append(record.name)
+
+    rows = []
+    for contig in sorted(contigs, key=lambda x: -len(contigs[x])):
+        names = contigs[contig]
+        length = sizes.get(contig)
+        n_genes = len({genes.get(x, x) for x in names})
+        # missing and NaN scores (projections without a prediction) are skipped
+        hits = [scores[x] for x in names if pd.notna(scores.get(x))]
+        mean_score = sum(hits) / len(hits) if hits else None
+        flagged_fraction = sum(x in flagged for x in names) / len(names)
+        gaps_here = gap_bp.get(contig, 0) if gaps else None
+        gap_fraction = gaps_here / length if length and gaps else None
+
+        rows.append(
+            [
+                contig,
+                length,
+                len(names),
+                n_genes,
+                n_genes / length * 1e6 if length else None,
+                flagged_fraction,
+                mean_score,
+                gaps_here,
+                gap_fraction,
+                quality_score(mean_score, flagged_fraction, gap_fraction),
+            ]
+        )
+
+    f = os.path.join(path, Constants.FileNames.CONTIG_QUALITY)
+    write_table(rows, f, COLUMNS, select, decimals)
+
+    log.record(f"quality of {len(contigs)} contigs written to {f}")
+
+    return f
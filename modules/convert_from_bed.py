@@ -1,12 +1,24 @@
 #!/usr/bin/env python3
 
 
-""" A module to convert .bed files to .gtf and .gff files. """
+""" A module to convert .bed files to .gtf, .gff and .saf files. """
 
 
+import os
+import shutil
+import sys
 from constants import Constants
 from logger import Log
-from modules.utils import shell
+from modules.bed import read_bed
+from modules.capabilities import supports
+from modules.chrom_spill import ChromSpillWriter
+from modules.compression import compress_file as compress_output
+from modules.errors import PostogaToolError, PostogaValueError
+from modules.fileio import detect_compression, extension_compression, open_binary
+from modules.run_warnings import RunWarnings
+from modules.union_model import build_union_models
+from modules.utils import output_stem, shell
+from modules.isoform_map import IsoformMap
 
 
 __author__ = "Alejandro Gonzales-Irribarren"
@@ -15,9 +27,69 @@ __github__ = "https://github.com/alejandrogzi"
 __version__ = "0.6.0-devel"
 
 
+def plain_input(path: str, f: str) -> str:
+    """
+    Returns a path the converters can read: the file itself, or for a
+    compressed input (gz/bgzf, zstd, xz) a decompressed copy in the temp
+    directory
+
+    @type path: str
+    @param path: path to the results directory
+    @type f: str
+    @param f: path to the input file
+    """
+    if not detect_compression(f):
+        return f
+
+    name = os.path.splitext(os.path.basename(f))[0]
+    plain = os.path.join(path, Constants.TEMP, name)
+    os.makedirs(os.path.dirname(plain), exist_ok=True)
+    with open_binary(f) as src, open(plain, "wb") as dst:
+        shutil.copyfileobj(src, dst)
+
+    return plain
+
+
+def bed12_input(path: str, bed: str) -> str:
+    """
+    Returns a BED12 file the converters can read: plain_input for BED12
+    files, or for files with any BED4/BED6 record a BED12 copy in the
+    temp directory where those records are single-exon and non-coding
+    (see BedRecord), so they convert to exon-only transcripts
+
+    @type path: str
+    @param path: path to the results directory
+    @type bed: str
+    @param bed: path to .bed file, optionally compressed
+    """
+    log = Log.connect(path, Constants.FileNames.LOG)
+
+    stem = os.path.basename(output_stem(bed))
+    full = os.path.join(path, Constants.TEMP, f"{stem}.bed12")
+    os.makedirs(os.path.dirname(full), exist_ok=True)
+
+    # Every record is checked, BED4/BED6 lines may follow BED12 ones
+    padded = 0
+    with open(full, "w") as out:
+        for record in read_bed(bed):
+            padded += record.columns < 12
+            out.write(record.to_line() + "\n")
+
+    if not padded:
+        os.remove(full)
+        return plain_input(path, bed)
+
+    log.warning(
+        f"{bed} has {padded} BED4/BED6 records: "
+        "converted as single-exon, non-coding transcripts"
+    )
+
+    return full
+
+
 def bed_to_gtf(path: str, bed: str, isoforms: str) -> str:
     """
-    Converts a .bed file to .gtf
+    Converts a .bed file (optionally compressed) to .gtf
 
     @type path: str
     @param path: path to the results directory
@@ -29,8 +101,16 @@ def bed_to_gtf(path: str, bed: str, isoforms: str) -> str:
 
     log = Log.connect(path, Constants.FileNames.LOG)
 
-    gtf = f"{bed.split('.bed')[0]}.gtf"
-    cmd = f"{Constants.ToolNames.BED2GTF} --bed {bed} --isoforms {isoforms} --output {gtf}"
+    if not supports(Constants.ToolNames.BED2GTF):
+        raise PostogaToolError(
+            f"{Constants.ToolNames.BED2GTF} not found in PATH, cannot convert to gtf (run ./configure.sh)"
+        )
+
+    gtf = f"{output_stem(bed)}.gtf"
+    cmd = (
+        f"{Constants.ToolNames.BED2GTF} --bed {bed12_input(path, bed)} "
+        f"--isoforms {plain_input(path, isoforms)} --output {gtf}"
+    )
     sh = shell(cmd)
 
     info = [
@@ -46,7 +126,7 @@ def bed_to_gtf(path: str, bed: str, isoforms: str) -> str:
 
 def bed_to_gff(path: str, bed: str, isoforms: str) -> str:
     """
-    Converts a .bed file to .gff
+    Converts a .bed file (optionally compressed) to .gff
 
     @type path: str
     @param path: path to the results directory
@@ -58,8 +138,16 @@ def bed_to_gff(path: str, bed: str, isoforms: str) -> str:
 
     log = Log.connect(path, Constants.FileNames.LOG)
 
-    gff = f"{bed.split('.bed')[0]}.gff"
-    cmd = f"{Constants.ToolNames.BED2GFF} --bed {bed} --isoforms {isoforms} --output {gff}"
+    if not supports(Constants.ToolNames.BED2GFF):
+        raise PostogaToolError(
+            f"{Constants.ToolNames.BED2GFF} not found in PATH, cannot convert to gff (run ./configure.sh)"
+        )
+
+    gff = f"{output_stem(bed)}.gff"
+    cmd = (
+        f"{Constants.ToolNames.BED2GFF} --bed {bed12_input(path, bed)} "
+        f"--isoforms {plain_input(path, isoforms)} --output {gff}"
+    )
     sh = shell(cmd)
 
     info = [
@@ -67,9 +155,279 @@ def bed_to_gff(path: str, bed: str, isoforms: str) -> str:
         f"running {cmd}",
         sh,
         f"gff file written to {gff}",
-
     ]
 
     [log.record(i) for i in info]
 
     return gff
+
+
+def bed_to_saf(path: str, bed: str, isoforms: str, union: bool = False) -> str:
+    """
+    Converts a .bed file to .saf (featureCounts simplified annotation format)
+
+    @type path: str
+    @param path: path to the results directory
+    @type bed: str
+    @param bed: path to .bed file
+    @type isoforms: str
+    @param isoforms: path to the isoforms file
+    @type union: bool
+    @param union: write union gene models instead of per-projection exons
+    """
+
+    log = Log.connect(path, Constants.FileNames.LOG)
+
+    saf = f"{output_stem(bed)}.saf"
+    genes = IsoformMap.load(isoforms)
+
+    if union:
+        rows = (
+            (gene, chrom, start, end, strand)
+            for (gene, chrom, strand), exons in build_union_models(bed, genes).items()
+            for start, end in exons
+        )
+    else:
+        rows = (
+            (
+                genes.get(record.name, record.name),
+                record.chrom,
+                start,
+                end,
+                record.strand,
+            )
+            for record in read_bed(bed)
+            for start, end in record.exons()
+        )
+
+    # SAF coordinates are 1-based and inclusive; rows are spilled per
+    # chromosome so memory stays bounded on whole-genome annotations
+    names = set()
+    with ChromSpillWriter(
+        saf, os.path.join(path, Constants.TEMP), "GeneID\tChr\tStart\tEnd\tStrand\n"
+    ) as out:
+        for gene, chrom, start, end, strand in rows:
+            names.add(gene)
+            out.write(chrom, start, f"{gene}\t{chrom}\t{start + 1}\t{end}\t{strand}\n")
+        total = out.lines
+
+    info = [
+        f"wrote {total} {'union ' if union else ''}exons from {len(names)} genes to saf",
+        f"saf file written to {saf}",
+    ]
+
+    [log.record(i) for i in info]
+
+    return saf
+
+
+def output_format(output: str) -> tuple:
+    """
+    Infers the (format, compression) of an output from its file name,
+    None for what the name does not tell (e.g. '-' or temporary files)
+
+    @type output: str
+    @param output: path to the output file
+    """
+    name = os.path.basename(output).lower()
+    compress = extension_compression(name)
+    if compress:
+        name = os.path.splitext(name)[0]
+
+    fmt = next(
+        (
+            fmt
+            for ext, fmt in ((".gtf", "gtf"), (".gff3", "gff3"), (".gff", "gff3"))
+            if name.endswith(ext)
+        ),
+        None,
+    )
+
+    return fmt, compress
+
+
+def compress_file(path: str, f: str, compress: str, output: str = None) -> str:
+    """
+    Compresses a file as gz, bgzf (with bgzip, gz if it is missing),
+    zstd or xz, removing the plain file (see compression.compress_file)
+
+    @type path: str
+    @param path: path to the results directory
+    @type f: str
+    @param f: path to the plain file
+    @type compress: str
+    @param compress: gz, bgzf, zstd or xz
+    @type output: str
+    @param output: path to the compressed file (default: f + extension)
+    """
+
+    log = Log.connect(path, Constants.FileNames.LOG)
+
+    requested = compress
+    output, compress = compress_output(f, compress, output)
+    if compress != requested:
+        log.warning(f"{Constants.ToolNames.BGZIP} not found in PATH, wrote gz instead")
+
+    log.record(f"{f} compressed ({compress}) to {output}")
+
+    return output
+
+
+def read_chrom_map(chrom_map: str) -> dict:
+    """
+    Reads a two-column (name, alias) table, tab- or space-separated,
+    skipping blank and '#' lines
+
+    @type chrom_map: str
+    @param chrom_map: path to the alias table
+    """
+    aliases = {}
+
+    with open(chrom_map, "r") as f:
+        for line in f:
+            fields = line.split()
+            if not fields or line.startswith("#"):
+                continue
+            if len(fields) < 2:
+                raise PostogaValueError(
+                    f"expected (name, alias) columns in {chrom_map}, got: {line.strip()}",
+                    chrom_map,
+                )
+            if aliases.get(fields[0], fields[1]) != fields[1]:
+                raise PostogaValueError(
+                    f"{fields[0]} has two aliases in {chrom_map}: {aliases[fields[0]]}, {fields[1]}",
+                    chrom_map,
+                )
+            aliases[fields[0]] = fields[1]
+
+    return aliases
+
+
+def rename_chroms(path: str, f: str, aliases: dict, unmapped: str = "error") -> str:
+    """
+    Renames the chromosome of every line of a .gtf/.gff file in place
+    (features and ##sequence-region pragmas). Lines on a chromosome
+    without alias raise or, with unmapped='drop', are dropped
+
+    @type path: str
+    @param path: path to the results directory
+    @type f: str
+    @param f: path to the plain .gtf/.gff file
+    @type aliases: dict
+    @param aliases: name -> alias (see read_chrom_map)
+    @type unmapped: str
+    @param unmapped: error or drop
+    """
+
+    log = Log.connect(path, Constants.FileNames.LOG)
+
+    missing, renamed, dropped = set(), 0, 0
+    tmp = f"{f}.renamed"
+
+    with open(f, "r") as src, open(tmp, "w") as dst:
+        for line in src:
+            pragma = line.startswith("##sequence-region")
+            if line.startswith("#") and not pragma:
+                dst.write(line)
+                continue
+            fields = line.split("\t") if not pragma else line.split(" ")
+            i = 1 if pragma else 0
+            if len(fields) <= i or not fields[i].strip():
+                dst.write(line)
+                continue
+            alias = aliases.get(fields[i])
+            if alias is None:
+                missing.add(fields[i])
+                dropped += 1
+                continue
+            fields[i] = alias
+            dst.write(("\t" if not pragma else " ").join(fields))
+            renamed += 1
+
+    if missing and unmapped == "error":
+        os.remove(tmp)
+        raise PostogaValueError(
+            f"{len(missing)} chromosomes of {f} have no alias: {', '.join(sorted(missing)[:10])}; drop them with unmapped='drop'",
+            f,
+        )
+    os.replace(tmp, f)
+
+    RunWarnings.connect(path).add(
+        "unmapped_chrom",
+        sorted(missing),
+        "chromosome without alias, its lines were dropped from the annotation",
+    )
+
+    info = [
+        f"renamed the chromosomes of {renamed} lines of {f} ({len(aliases)} aliases)",
+        f"dropped {dropped} lines on {len(missing)} chromosomes without alias",
+    ]
+
+    [log.record(i) for i in info]
+
+    return f
+
+
+def convert(
+    path: str,
+    bed: str,
+    isoforms: str,
+    output: str = None,
+    fmt: str = None,
+    compress: str = None,
+    chrom_map: str = None,
+    unmapped: str = "error",
+) -> str:
+    """
+    Converts a .bed file to .gtf or .gff3 at a given output. The format
+    and compression parameters take precedence; the output name is only
+    used to infer what they leave out, so '-' (stdout) and temporary
+    files work when both are given
+
+    @type path: str
+    @param path: path to the results directory
+    @type bed: str
+    @param bed: path to .bed file
+    @type isoforms: str
+    @param isoforms: path to the isoforms file
+    @type output: str
+    @param output: path to the output file, '-' for stdout (default: next to the .bed)
+    @type fmt: str
+    @param fmt: gtf or gff3
+    @type compress: str
+    @param compress: none, gz, bgzf, zstd or xz
+    @type chrom_map: str
+    @param chrom_map: (name, alias) table to rename chromosomes with
+    @type unmapped: str
+    @param unmapped: error or drop, for lines on chromosomes without alias
+    @rtype: str
+    @return: path to the output file, '-' if written to stdout
+    """
+    inferred = output_format(output) if output and output != "-" else (None, None)
+    fmt = fmt or inferred[0]
+    compress = compress or inferred[1] or "none"
+    if fmt not in ("gtf", "gff3", "gff"):
+        raise PostogaValueError(
+            f"cannot tell the output format of {output or bed}, pass fmt='gtf' or 'gff3'",
+            output,
+        )
+
+    to_fmt = bed_to_gtf if fmt == "gtf" else bed_to_gff
+    converted = to_fmt(path, bed, isoforms)
+    if chrom_map:
+        rename_chroms(path, converted, read_chrom_map(chrom_map), unmapped)
+
+    if compress != "none":
+        target = None if output in (None, "-") else output
+        converted = compress_file(path, converted, compress, target)
+    elif output not in (None, "-"):
+        shutil.move(converted, output)
+        converted = output
+
+    if output == "-":
+        with open(converted, "rb") as f:
+            shutil.copyfileobj(f, sys.stdout.buffer)
+        os.remove(converted)
+        return output
+
+    return converted
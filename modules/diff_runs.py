@@ -0,0 +1,113 @@
+#!/usr/bin/env python3
+
+
+"""
+A module to compare two postoga runs.
+
+Reports genes whose class changed, projections added or removed
+and projections whose coordinates changed between two TOGA results
+directories (e.g. two postoga versions or parameter sets), so
+annotation upgrades are auditable.
+"""
+
+
+import os
+import pandas as pd
+from constants import Constants
+from logger import Log
+from modules.annotation_set import AnnotationSet
+from modules.make_query_table import query_table
+
+
+__author__ = "Alejandro Gonzales-Irribarren"
+__email__ = "jose.gonzalesdezavala1@unmsm.edu.pe"
+__github__ = "https://github.com/alejandrogzi"
+__version__ = "0.7.0-devel"
+
+
+def run_annotation(path: str) -> str:
+    """
+    Returns the annotation of a run, the filtered .bed if postoga wrote one
+
+    @type path: str
+    @param path: path to the results directory
+    """
+    filtered = os.path.join(path, Constants.FileNames.FILTERED_BED)
+
+    if os.path.exists(filtered):
+        return filtered
+
+    return os.path.join(path, Constants.FileNames.BED)
+
+
+def locus(tx) -> str:
+    return f"{tx.chrom}:{tx.start}-{tx.end}"
+
+
+def coordinates(tx) -> tuple:
+    return (tx.chrom, tx.strand, tx.thick, tuple(tx.exons))
+
+
+def gene_classes(table: pd.DataFrame) -> dict:
+    """
+    Returns the best class of each gene following Constants.ORDER
+
+    @type table: pd.DataFrame
+    @param table: query table
+    """
+    genes = table.sort_values(by="class", key=lambda x: x.map(Constants.ORDER))
+
+    return genes.drop_duplicates("t_gene").set_index("t_gene")["class"].to_dict()
+
+
+def diff_runs(run_a: str, run_b: str) -> pd.DataFrame:
+    """
+    Compares two postoga runs and writes the differences to run_b
+
+    @type run_a: str
+    @param run_a: path to the old results directory
+    @type run_b: str
+    @param run_b: path to the new results directory
+    @rtype: pd.DataFrame
+    @return: a table with the columns [change, id, before, after]
+    """
+
+    log = Log.connect(run_b, Constants.FileNames.LOG)
+
+    rows = []
+
+    before = gene_classes(query_table(run_a))
+    after = gene_classes(query_table(run_b))
+    for gene in sorted(before.keys() | after.keys()):
+        a, b = before.get(gene, "NF"), after.get(gene, "NF")
+        if a != b:
+            rows.append(("class_changed", gene, a, b))
+
+    old = AnnotationSet.from_file(run_annotation(run_a)).transcripts
+    new = AnnotationSet.from_file(run_annotation(run_b)).transcripts
+
+    for name in sorted(new.keys() - old.keys()):
+        rows.append(("added", name, ".", locus(new[name])))
+    for name in sorted(old.keys() - new.keys()):
+        rows.append(("removed", name, locus(old[name]), "."))
+    for name in sorted(old.keys() & new.keys()):
+        # Any change in span, strand, CDS or blocks is reported with both lines
+        if coordinates(old[name]) != coordinates(new[name]):
+            before, after = old[name].to_bed(), new[name].to_bed()
+            rows.append(
+                ("coordinates_changed", name, before.to_line(), after.to_line())
+            )
+
+    diff = pd.DataFrame(rows, columns=["change", "id", "before", "after"])
+    f = os.path.join(run_b, Constants.FileNames.DIFF)
+    diff.to_csv(f, sep="\t", index=False)
+
+    info = [
+        f"compared {run_a} (before) against {run_b} (after)",
+        f"diff stats: {diff['change'].value_counts().to_dict()}",
+        f"diff table written to {f}",
+    ]
+
+    [log.record(i) for i in info]
+
+    return diff
@@ -0,0 +1,63 @@
+#!/usr/bin/env python3
+
+
+"""
+A module with the exceptions postoga raises on bad inputs.
+
+All of them derive from PostogaError and from the matching built-in
+exception, so callers can catch every postoga problem at once or keep
+catching ValueError/KeyError/OSError as before. The command line
+reports them as a one-line error instead of a traceback:
+
+``` python
+from modules.errors import PostogaError
+
+try:
+    records = list(read_bed("query_annotation.bed"))
+except PostogaError as e:
+    print(f"skipping run: {e}")
+```
+"""
+
+
+__author__ = "Alejandro Gonzales-Irribarren"
+__email__ = "jose.gonzalesdezavala1@unmsm.edu.pe"
+__github__ = "https://github.com/alejandrogzi"
+__version__ = "0.7.0-devel"
+
+
+class PostogaError(Exception):
+    """Base class of the errors caused by postoga inputs."""
+
+    def __init__(self, message: str, record: str = None) -> None:
+        """
+        Constructs all the necessary attributes for the PostogaError object.
+
+        @type message: str
+        @param message: human-readable explanation
+        @type record: str
+        @param record: offending record (projection, line, ...) if any
+        """
+
+        super().__init__(message)
+        self.message = message
+        self.record = record
+
+    def __str__(self) -> str:
+        return self.message
+
+
+class PostogaValueError(PostogaError, ValueError):
+    """Malformed or inconsistent input (e.g. a broken .bed line)."""
+
+
+class PostogaKeyError(PostogaError, KeyError):
+    """A record missing from an input (e.g. a transcript without a gene)."""
+
+
+class PostogaIOError(PostogaError, OSError):
+    """An input or output location that cannot be used."""
+
+
+class PostogaToolError(PostogaError, RuntimeError):
+    """An external tool that is missing or failed (e.g. bed2gtf)."""
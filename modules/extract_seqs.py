@@ -0,0 +1,806 @@
+#!/usr/bin/env python3
+
+
+"""
+A module to extract the sequences of the query annotation from TOGA .fasta files.
+
+TOGA writes nucleotide, codon and protein .fasta files with both the
+reference and the query sequence of each projection. This module
+keeps the query sequences of the projections present in a (filtered)
+.bed file, and can cross-check them against the converted annotation.
+"""
+
+
+import os
+from collections import defaultdict
+from concurrent.futures import ThreadPoolExecutor
+from constants import Constants
+from logger import Log
+from modules.bed import read_bed
+from modules.errors import PostogaValueError
+from modules.fasta import (
+    add_record,
+    cached_fasta,
+    format_fasta,
+    read_fasta,
+)
+from modules.fileio import detect_compression
+from modules.gxf import read_gxf
+from modules.run_warnings import RunWarnings
+from modules.seq_digest import header_digests
+from modules.seq_store import FaiReader
+from modules.utils import natural_key, output_stem
+
+
+__author__ = "Alejandro Gonzales-Irribarren"
+__email__ = "jose.gonzalesdezavala1@unmsm.edu.pe"
+__github__ = "https://github.com/alejandrogzi"
+__version__ = "0.7.0-devel"
+
+
+PROTEIN_ONLY = set("EFILPQZJO*")
+FLAVOR_TOKENS = {"PROT": "protein", "PROTEIN": "protein", "CODON": "codon"}
+
+
+def fasta_flavor(fasta: str, peek: int = 20) -> str:
+    """
+    Detects which TOGA .fasta flavor (nucleotide, codon or protein) a file
+    is, from the header tags (PROT, CODON) or, if there are none, from
+    the alphabet of its first records
+
+    @type fasta: str
+    @param fasta: path to a TOGA .fasta file
+    @type peek: int
+    @param peek: number of records to look at
+    """
+    letters = set()
+
+    for i, (header, seq) in enumerate(read_fasta(fasta)):
+        for field in header.split("|")[1:]:
+            if field.strip().upper() in FLAVOR_TOKENS:
+                return FLAVOR_TOKENS[field.strip().upper()]
+        letters |= set(seq.upper())
+        if i + 1 >= peek:
+            break
+
+    return "protein" if letters & PROTEIN_ONLY else "nucleotide"
+
+
+def parse_toga_header(header: str) -> tuple:
+    """
+    Splits a TOGA .fasta header (e.g. 'ENST00000355624.10 | CODON | QUERY')
+    into the projection name and its source (QUERY, REFERENCE or None)
+
+    @type header: str
+    @param header: header without the leading '>'
+    """
+    fields = [x.strip() for x in header.split("|")]
+    name = fields[0].split()[0] if fields[0] else ""
+    source = next((x for x in fields[1:] if x in ("QUERY", "REFERENCE")), None)
+
+    return name, source
+
+
+class HeaderFields(dict):
+    """Header template fields, missing keys are written as NA."""
+
+    def __missing__(self, key: str) -> str:
+        return "NA"
+
+
+def table_metadata(table, species: str = None) -> dict:
+    """
+    Builds transcript -> header fields from the query table
+
+    @type table: pd.DataFrame
+    @param table: query table
+    @type species: str
+    @param species: query species name, available as {species}
+    """
+    table = table.rename(columns={"t_gene": "gene"}).set_index("transcripts")
+    metadata = table.drop(columns=["helper"]).to_dict("index")
+
+    for fields in metadata.values():
+        fields["species"] = species or "NA"
+
+    return metadata
+
+
+def read_metadata(metadata: str) -> dict:
+    """
+    Reads a tab-separated metadata table with a header line, keyed
+    by its first column (the projection name)
+
+    @type metadata: str
+    @param metadata: path to the metadata table
+    """
+    with open(metadata, "r") as f:
+        columns = f.readline().rstrip("\n").split("\t")
+        rows = [line.rstrip("\n").split("\t") for line in f if line.strip()]
+
+    return {row[0]: dict(zip(columns[1:], row[1:])) for row in rows}
+
+
+def format_header(template: str, name: str, metadata: dict = None, **extra) -> str:
+    """
+    Fills a header template (e.g. '{tx}|{gene}|{class}|{species}') for a
+    projection, returns the plain name if there is no template. Sequence
+    digests are passed as extra fields ({md5}, {sq}, see seq_digest)
+
+    @type template: str
+    @param template: header template with {field} placeholders
+    @type name: str
+    @param name: projection name, available as {tx}
+    @type metadata: dict
+    @param metadata: transcript -> fields dictionary
+    """
+    if not template:
+        return name
+
+    fields = HeaderFields((metadata or {}).get(name, {}))
+    fields.update(extra, tx=name)
+
+    return template.format_map(fields)
+
+
+def read_query_seqs(
+    fasta: str, names: set, duplicates: str = "keep_first", records=None
+) -> tuple:
+    """
+    Returns the query sequences of a set of projections. The .fasta
+    file is parsed once and shared by later calls (see cached_fasta)
+
+    @type fasta: str
+    @param fasta: path to a TOGA .fasta file
+    @type names: set
+    @param names: projection names to keep
+    @type duplicates: str
+    @param duplicates: duplicate policy (error, keep_first, keep_longest)
+    @type records: iterable
+    @param records: (header, sequence) pairs to use instead of the cache
+    @rtype: tuple
+    @return: (name -> sequence, duplicated names)
+    """
+    seqs, dups = {}, set()
+
+    for header, seq in records if records is not None else cached_fasta(fasta):
+        name, source = parse_toga_header(header)
+        if name and name in names and source != "REFERENCE":
+            if add_record(seqs, name, seq, duplicates):
+                dups.add(name)
+
+    return seqs, sorted(dups)
+
+
+def indexed_records(path: str, fasta: str, names: set, threads: int = 1):
+    """
+    Returns the (header, sequence) pairs of a set of projections read
+    through the .fai index of a TOGA .fasta file (written if missing or
+    older than the file), so only their records are read from disk;
+    None for compressed files, which cannot be indexed
+
+    @type path: str
+    @param path: path to the results directory
+    @type fasta: str
+    @param fasta: path to a TOGA .fasta file
+    @type names: set
+    @param names: projection names to fetch
+    @type threads: int
+    @param threads: number of processes indexing the file (see write_fai)
+    """
+
+    log = Log.connect(path, Constants.FileNames.LOG)
+
+    if detect_compression(fasta):
+        log.warning(f"{fasta} is compressed and cannot be indexed, reading it whole")
+        return None
+
+    reader = FaiReader(fasta, threads)
+    records = list(reader.records(names))
+    reader.close()
+
+    log.record(f"fetched {len(records)} records from {fasta} through {fasta}.fai")
+
+    return records
+
+
+def duplicate_warning(path: str, dups: list, fasta: str, policy: str) -> None:
+    """
+    Records projections with more than one query sequence in a .fasta file
+
+    @type path: str
+    @param path: path to the results directory
+    @type dups: list
+    @param dups: duplicated projection names
+    @type fasta: str
+    @param fasta: path to the .fasta file
+    @type policy: str
+    @param policy: duplicate policy applied
+    """
+    RunWarnings.connect(path).add(
+        "duplicate_sequence",
+        dups,
+        f"projection has more than one query sequence in {fasta} ({policy})",
+    )
+
+
+def check_missing(
+    path: str,
+    names: list,
+    seqs: dict,
+    fasta: str,
+    on_missing: str = "skip",
+    label: str = None,
+) -> list:
+    """
+    Reports the projections of a .bed file absent from a .fasta file.
+    With on_missing="error" the run stops; "skip" leaves them out of
+    the output, "warn" does the same and prints a warning with their
+    count, and "emit_empty" writes them with an empty sequence
+
+    @type path: str
+    @param path: path to the results directory
+    @type names: list
+    @param names: projection names in the .bed file
+    @type seqs: dict
+    @param seqs: name -> sequence dictionary
+    @type fasta: str
+    @param fasta: path to the .fasta file
+    @type on_missing: str
+    @param on_missing: one of Constants.MISSING_POLICIES
+    @type label: str
+    @param label: optional label of the .bed file
+    """
+    missing = [name for name in names if name not in seqs]
+    where = f" ({label})" if label else ""
+
+    RunWarnings.connect(path).add(
+        "missing_sequence", missing, f"projection{where} not found in {fasta}"
+    )
+
+    if missing and on_missing == "error":
+        raise PostogaValueError(
+            f"{len(missing)} projections{where} not found in {fasta}, "
+            f"e.g. {missing[0]}",
+            missing[0],
+        )
+
+    if missing and on_missing == "warn":
+        Log.connect(path, Constants.FileNames.LOG).warning(
+            f"skipped {len(missing)} projections{where} not found in {fasta}, "
+            f"e.g. {', '.join(missing[:3])}"
+        )
+
+    return missing
+
+
+def sequence_issue(seq: str, flavor: str, filters: dict) -> str:
+    """
+    Returns why a sequence fails the extraction filters, None if it passes.
+    Supported filters: min_len (gaps excluded), max_ambiguous_frac (N in
+    nucleotides, X in proteins) and require_start_stop
+
+    @type seq: str
+    @param seq: sequence
+    @type flavor: str
+    @param flavor: nucleotide, codon or protein
+    @type filters: dict
+    @param filters: filter name -> threshold
+    """
+    seq = seq.replace("-", "").upper()
+    protein = flavor == "protein"
+
+    if len(seq) < (filters.get("min_len") or 0):
+        return f"shorter than {filters['min_len']}"
+
+    max_ambiguous = filters.get("max_ambiguous_frac")
+    if max_ambiguous is not None and seq:
+        ambiguous = seq.count("X") if protein else sum(c not in "ACGT" for c in seq)
+        if ambiguous / len(seq) > max_ambiguous:
+            return f"more than {max_ambiguous} ambiguous positions"
+
+    if filters.get("require_start_stop"):
+        if protein:
+            start, stop = seq[:1] == "M", seq[-1:] == "*"
+        else:
+            start = seq[:3] in Constants.START_CODONS["standard"]
+            stop = seq[-3:] in ("TAA", "TAG", "TGA")
+        if not (start and stop):
+            return "missing start or stop codon"
+
+    return None
+
+
+def filter_seqs(path: str, seqs: dict, flavor: str, filters: dict) -> set:
+    """
+    Drops the sequences failing the extraction filters and reports them
+
+    @type path: str
+    @param path: path to the results directory
+    @type seqs: dict
+    @param seqs: name -> sequence dictionary, modified in place
+    @type flavor: str
+    @param flavor: nucleotide, codon or protein
+    @type filters: dict
+    @param filters: filter name -> threshold (see sequence_issue)
+    @rtype: set
+    @return: names of the dropped sequences
+    """
+    if not filters:
+        return set()
+
+    issues = defaultdict(list)
+    for name, seq in seqs.items():
+        issue = sequence_issue(seq, flavor, filters)
+        if issue:
+            issues[issue].append(name)
+
+    for issue, names in issues.items():
+        RunWarnings.connect(path).add(
+            "filtered_sequence", names, f"sequence excluded from extraction: {issue}"
+        )
+        for name in names:
+            del seqs[name]
+
+    return {name for names in issues.values() for name in names}
+
+
+def write_records(
+    f: str, records: list, order: str = "bed", threads: int = 1
+) -> str:
+    """
+    Writes (name, header, sequence) records to a .fasta file in a
+    deterministic order: as given (.bed order) or natural-sorted by name.
+    Records are formatted by a pool of threads, the order is kept
+
+    @type f: str
+    @param f: path to the output .fasta file
+    @type records: list
+    @param records: (name, header, sequence) tuples in .bed order
+    @type order: str
+    @param order: bed or natural
+    @type threads: int
+    @param threads: number of formatting threads
+    """
+    if order == "natural":
+        records = sorted(records, key=lambda record: natural_key(record[0]))
+
+    with ThreadPoolExecutor(max_workers=max(threads, 1)) as pool:
+        texts = pool.map(lambda record: format_fasta(*record[1:]), records)
+        with open(f, "w") as out:
+            out.writelines(texts)
+
+    return f
+
+
+def write_split(
+    directory: str,
+    records: list,
+    groups,
+    suffix: str,
+    order: str = "bed",
+    threads: int = 1,
+) -> str:
+    """
+    Writes (name, header, sequence) records to one .fasta file per group
+    (e.g. gene or orthology class) in a directory; projections without a
+    group go to 'unassigned'
+
+    @type directory: str
+    @param directory: output directory, created if missing
+    @type records: list
+    @param records: (name, header, sequence) tuples in .bed order
+    @type groups: dict
+    @param groups: projection -> group (anything with .get, e.g. IsoformMap)
+    @type suffix: str
+    @param suffix: file name after the group (e.g. prot.fasta)
+    @type order: str
+    @param order: bed or natural (see write_records)
+    @type threads: int
+    @param threads: number of formatting threads
+    """
+    os.makedirs(directory, exist_ok=True)
+
+    split = defaultdict(list)
+    for record in records:
+        group = str(groups.get(record[0]) or "unassigned")
+        split[group.replace(os.sep, "_").replace(" ", "_")].append(record)
+
+    for group, members in split.items():
+        write_records(
+            os.path.join(directory, f"{group}.{suffix}"), members, order, threads
+        )
+
+    return directory
+
+
+def extract_seqs(
+    path: str,
+    bed: str,
+    fasta: str,
+    header_template: str = None,
+    metadata: dict = None,
+    duplicates: str = "keep_first",
+    hint: str = None,
+    on_missing: str = "skip",
+    order: str = "bed",
+    threads: int = 1,
+    filters: dict = None,
+    indexed: bool = False,
+    groups=None,
+) -> tuple:
+    """
+    Writes the query sequences of the projections in a .bed file.
+    The .fasta flavor is detected from its content; a hint that does
+    not match it is reported instead of failing. With groups, one
+    .fasta per group is also written to a *.split directory next to the
+    .fasta (see write_split)
+
+    @type path: str
+    @param path: path to the results directory
+    @type bed: str
+    @param bed: path to .bed file (query_annotation/filtered)
+    @type fasta: str
+    @param fasta: path to a TOGA .fasta file (nucleotide, codon or protein)
+    @type header_template: str
+    @param header_template: optional header template (see format_header)
+    @type metadata: dict
+    @param metadata: transcript -> fields dictionary for the template
+    @type duplicates: str
+    @param duplicates: duplicate header policy (error, keep_first, keep_longest)
+    @type hint: str
+    @param hint: expected flavor (nucleotide, codon or protein)
+    @type on_missing: str
+    @param on_missing: projections absent from the .fasta (see check_missing)
+    @type order: str
+    @param order: output order, bed or natural (see write_records)
+    @type threads: int
+    @param threads: number of formatting threads (and indexing processes)
+    @type filters: dict
+    @param filters: quality/length filters (see sequence_issue)
+    @type indexed: bool
+    @param indexed: fetch the sequences through a .fai index (see indexed_records)
+    @type groups: dict
+    @param groups: projection -> gene or class to split the output by
+    @rtype: tuple
+    @return: (path to the extracted .fasta file, missing projections,
+        written header -> projection name)
+    """
+
+    log = Log.connect(path, Constants.FileNames.LOG)
+
+    flavor = fasta_flavor(fasta)
+    if hint and hint != flavor:
+        log.warning(f"{fasta} was given as {hint} but looks like a {flavor} .fasta")
+    log.record(f"extracting from {fasta} ({flavor} .fasta)")
+
+    names = [record.name for record in read_bed(bed)]
+    records = indexed_records(path, fasta, set(names), threads) if indexed else None
+    seqs, dups = read_query_seqs(fasta, set(names), duplicates, records)
+    duplicate_warning(path, dups, fasta, duplicates)
+
+    missing = check_missing(path, names, seqs, fasta, on_missing)
+    failed = filter_seqs(path, seqs, flavor, filters)
+
+    stem = os.path.basename(output_stem(bed))
+    f = os.path.join(path, f"{stem}.{os.path.basename(fasta)}")
+
+    records = [
+        (
+            name,
+            format_header(
+                header_template,
+                name,
+                metadata,
+                **header_digests(header_template, seqs.get(name, "")),
+            ),
+            seqs.get(name, ""),
+        )
+        for name in dict.fromkeys(names)
+        if name not in failed and (name in seqs or on_missing == "emit_empty")
+    ]
+    write_records(f, records, order, threads)
+    if groups is not None:
+        write_split(
+            f"{f.rsplit('.', 1)[0]}.split",
+            records,
+            groups,
+            os.path.basename(fasta),
+            order,
+            threads,
+        )
+
+    log.record(f"{len(seqs)} query sequences extracted from {fasta} to {f}")
+
+    return f, missing, {header: name for name, header, _ in records}
+
+
+def extract_seqs_batch(
+    path: str,
+    beds: dict,
+    fasta: str,
+    header_template: str = None,
+    metadata: dict = None,
+    duplicates: str = "keep_first",
+    on_missing: str = "skip",
+    order: str = "bed",
+    threads: int = 1,
+    filters: dict = None,
+    indexed: bool = False,
+    groups=None,
+) -> tuple:
+    """
+    Writes the query sequences of several .bed files (e.g. one per loss
+    class) in a single pass over a TOGA .fasta file. Headers are
+    annotated with the label of their .bed file ('name | label'),
+    or filled from header_template where the label is {label}. With
+    groups, each label is also split as in extract_seqs
+
+    @type path: str
+    @param path: path to the results directory
+    @type beds: dict
+    @param beds: label -> path to .bed file
+    @type fasta: str
+    @param fasta: path to a TOGA .fasta file (nucleotide, codon or protein)
+    @type header_template: str
+    @param header_template: optional header template (see format_header)
+    @type metadata: dict
+    @param metadata: transcript -> fields dictionary for the template
+    @type duplicates: str
+    @param duplicates: duplicate header policy (error, keep_first, keep_longest)
+    @type on_missing: str
+    @param on_missing: projections absent from the .fasta (see check_missing)
+    @type order: str
+    @param order: output order, bed or natural (see write_records)
+    @type threads: int
+    @param threads: number of formatting threads (and indexing processes)
+    @type filters: dict
+    @param filters: quality/length filters (see sequence_issue)
+    @type indexed: bool
+    @param indexed: fetch the sequences through a .fai index (see indexed_records)
+    @type groups: dict
+    @param groups: projection -> gene or class to split the output by
+    @rtype: tuple
+    @return: (label -> path to the extracted .fasta file,
+        label -> missing projections)
+    """
+
+    log = Log.connect(path, Constants.FileNames.LOG)
+
+    names = {label: [r.name for r in read_bed(bed)] for label, bed in beds.items()}
+    wanted = set().union(*names.values())
+    records = indexed_records(path, fasta, wanted, threads) if indexed else None
+    seqs, dups = read_query_seqs(
+        fasta, wanted, duplicates, records if records is not None else read_fasta(fasta)
+    )
+    duplicate_warning(path, dups, fasta, duplicates)
+
+    missing = {
+        label: check_missing(path, names[label], seqs, fasta, on_missing, label)
+        for label in beds
+    }
+    failed = filter_seqs(path, seqs, fasta_flavor(fasta), filters)
+
+    files = {}
+    for label, bed in beds.items():
+        stem = os.path.basename(output_stem(bed))
+        f = os.path.join(path, f"{stem}.{label}.{os.path.basename(fasta)}")
+        template = header_template or "{tx} | {label}"
+        records = [
+            (
+                name,
+                format_header(
+                    template,
+                    name,
+                    metadata,
+                    label=label,
+                    **header_digests(template, seqs.get(name, "")),
+                ),
+                seqs.get(name, ""),
+            )
+            for name in dict.fromkeys(names[label])
+            if name not in failed and (name in seqs or on_missing == "emit_empty")
+        ]
+        files[label] = write_records(f, records, order, threads)
+        if groups is not None:
+            write_split(
+                f"{f.rsplit('.', 1)[0]}.split",
+                records,
+                groups,
+                os.path.basename(fasta),
+                order,
+                threads,
+            )
+
+    log.record(
+        f"{len(seqs)} query sequences extracted from {fasta} for {', '.join(beds)}"
+    )
+
+    return files, missing
+
+
+def verify_cds_lengths(
+    path: str, gxf: str, fasta: str, headers: dict = None
+) -> str:
+    """
+    Cross-checks the CDS length of each transcript in a .gtf/.gff file
+    against the length of its extracted sequence (gaps removed),
+    allowing a difference of one stop codon. Protein sequences are
+    counted as three bases per residue. Sequences are matched to
+    transcripts through the headers written by extract_seqs, so
+    templated headers are checked too; finding no transcript of the
+    annotation in the .fasta is an error
+
+    @type path: str
+    @param path: path to the results directory
+    @type gxf: str
+    @param gxf: path to the converted .gtf/.gff file
+    @type fasta: str
+    @param fasta: path to the extracted nucleotide/codon/protein .fasta file
+    @type headers: dict
+    @param headers: written header -> projection name (see extract_seqs),
+        the first word of each header if None
+    """
+
+    log = Log.connect(path, Constants.FileNames.LOG)
+
+    lengths = defaultdict(int)
+    for feature in read_gxf(gxf):
+        if feature.feature == "CDS":
+            lengths[feature.transcript_id] += feature.end - feature.start + 1
+
+    scale = 3 if fasta_flavor(fasta) == "protein" else 1
+    seqs = {}
+    for header, seq in read_fasta(fasta):
+        name = header.split()[0] if headers is None else headers.get(header)
+        if name is not None:
+            seqs[name] = len(seq.replace("-", "")) * scale
+
+    checked = sorted(lengths.keys() & seqs.keys())
+    if lengths and seqs and not checked:
+        raise PostogaValueError(
+            f"no transcript of {gxf} matches a sequence in {fasta}, "
+            "cannot verify CDS lengths",
+            fasta,
+        )
+
+    f = os.path.join(path, Constants.FileNames.CDS_CHECK)
+    mismatches = []
+    with open(f, "w") as out:
+        out.write("transcript\tannotation_cds\tsequence_length\tstatus\n")
+        for transcript in checked:
+            diff = seqs[transcript] - lengths[transcript]
+            status = "ok" if diff in (0, 3, -3) else "mismatch"
+            if status == "mismatch":
+                mismatches.append(transcript)
+            out.write(
+                f"{transcript}\t{lengths[transcript]}\t{seqs[transcript]}\t{status}\n"
+            )
+
+    RunWarnings.connect(path).add(
+        "cds_length_mismatch",
+        mismatches,
+        f"CDS length in {gxf} differs from the extracted sequence in {fasta}",
+    )
+
+    log.record(
+        f"checked {len(checked)} CDS lengths against {fasta}, "
+        f"{len(mismatches)} mismatches written to {f}"
+    )
+
+    return f
+
+
+def format_pair(name: str, reference: str, query: str, width: int = 60) -> str:
+    """
+    Formats a reference/query pair as a text alignment block: REF and
+    QRY lines with a match line ('|' identical, '.' different, ' ' gap)
+    in between when both sequences are aligned (same length), and the
+    two sequences alone otherwise
+
+    @type name: str
+    @param name: projection name
+    @type reference: str
+    @param reference: reference sequence
+    @type query: str
+    @param query: query sequence
+    @type width: int
+    @param width: columns per line
+    """
+    if len(reference) != len(query):
+        lines = [f"# {name} not aligned ({len(reference)} vs {len(query)} residues)"]
+        lines += [
+            f"REF  {reference[i : i + width]}" for i in range(0, len(reference), width)
+        ]
+        lines += [f"QRY  {query[i : i + width]}" for i in range(0, len(query), width)]
+        return "\n".join(lines) + "\n\n"
+
+    matches = "".join(
+        " " if "-" in (r, q) else "|" if r == q else "."
+        for r, q in zip(reference.upper(), query.upper())
+    )
+    compared = len(matches) - matches.count(" ")
+    identity = matches.count("|") / compared if compared else 0
+
+    lines = [f"# {name} aligned, {len(matches)} columns, {identity:.1%} identity"]
+    for i in range(0, len(matches), width):
+        lines += [
+            f"REF  {reference[i : i + width]}",
+            f"     {matches[i : i + width]}",
+            f"QRY  {query[i : i + width]}",
+        ]
+
+    return "\n".join(lines) + "\n\n"
+
+
+def write_paired(
+    path: str, bed: str, fasta: str, layout: str = "adjacent", order: str = "bed"
+) -> str:
+    """
+    Writes the reference and query sequences of each projection in a
+    .bed file next to each other, for curation: as adjacent .fasta
+    records (REFERENCE then QUERY) or as text alignment blocks (see
+    format_pair). Projections without both sequences are reported
+
+    @type path: str
+    @param path: path to the results directory
+    @type bed: str
+    @param bed: path to .bed file (query_annotation/filtered)
+    @type fasta: str
+    @param fasta: path to a TOGA .fasta file (nucleotide, codon or protein)
+    @type layout: str
+    @param layout: adjacent or aligned
+    @type order: str
+    @param order: output order, bed or natural (see write_records)
+    @rtype: str
+    @return: path to the paired .fasta (adjacent) or .aln (aligned) file
+    """
+
+    log = Log.connect(path, Constants.FileNames.LOG)
+
+    names = list(dict.fromkeys(record.name for record in read_bed(bed)))
+    if order == "natural":
+        names.sort(key=natural_key)
+    wanted = set(names)
+
+    pairs = {"QUERY": {}, "REFERENCE": {}}
+    for header, seq in cached_fasta(fasta):
+        name, source = parse_toga_header(header)
+        if name in wanted:
+            pairs.get(source or "QUERY", {}).setdefault(name, seq)
+    reference, query = pairs["REFERENCE"], pairs["QUERY"]
+
+    stem = os.path.basename(output_stem(bed))
+    f = os.path.join(path, f"{stem}.paired.{os.path.basename(fasta)}")
+    if layout == "aligned":
+        f = f"{f.rsplit('.fasta', 1)[0]}.aln"
+
+    written, aligned = 0, 0
+    with open(f, "w") as out:
+        for name in names:
+            if name not in reference or name not in query:
+                continue
+            if layout == "aligned":
+                out.write(format_pair(name, reference[name], query[name]))
+                aligned += len(reference[name]) == len(query[name])
+            else:
+                out.write(format_fasta(f"{name} | REFERENCE", reference[name]))
+                out.write(format_fasta(f"{name} | QUERY", query[name]))
+            written += 1
+
+    unpaired = [x for x in names if x not in reference or x not in query]
+    RunWarnings.connect(path).add(
+        "unpaired_sequence",
+        unpaired,
+        f"projection without both a reference and a query sequence in {fasta}",
+    )
+
+    info = [f"{written} reference/query pairs from {fasta} written to {f}"]
+    if layout == "aligned":
+        info.append(
+            f"{aligned} pairs aligned, {written - aligned} of different lengths"
+        )
+
+    [log.record(i) for i in info]
+
+    return f
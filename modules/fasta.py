@@ -0,0 +1,358 @@
+#!/usr/bin/env python3
+
+
+""" A module with postoga .fasta reading/writing utility functions. """
+
+
+import os
+import threading
+from functools import lru_cache
+from itertools import product
+from constants import Constants
+from modules.fileio import open_text
+from modules.errors import PostogaValueError
+
+
+__author__ = "Alejandro Gonzales-Irribarren"
+__email__ = "jose.gonzalesdezavala1@unmsm.edu.pe"
+__github__ = "https://github.com/alejandrogzi"
+__version__ = "0.7.0-devel"
+
+
+COMPLEMENT = str.maketrans("ACGTNacgtnRYKMrykmBDHVbdhv", "TGCANtgcanYRMKyrmkVHDBvhdb")
+CODONS = ["".join(codon) for codon in product(Constants.CODON_BASES, repeat=3)]
+
+# Parsed .fasta files shared by all extraction calls of the process,
+# keyed by real path and invalidated when the file changes on disk
+FASTA_CACHE = {}
+FASTA_LOCK = threading.Lock()
+
+
+def read_fasta(fasta: str):
+    """
+    Yields (header, sequence) pairs from a .fasta file. The header
+    is returned without the leading '>'
+
+    @type fasta: str
+    @param fasta: path to .fasta file, optionally compressed
+    """
+    header, chunks = None, []
+
+    with open_text(fasta) as f:
+        for line in f:
+            line = line.rstrip("\r\n")
+            if line.startswith(">"):
+                if header is not None:
+                    yield header, "".join(chunks)
+                header, chunks = line[1:], []
+            elif line:
+                chunks.append(line)
+
+    if header is not None:
+        yield header, "".join(chunks)
+
+
+def cached_fasta(fasta: str) -> list:
+    """
+    Returns the (header, sequence) pairs of a .fasta file, reading it
+    only once per process as long as its size and mtime do not change.
+    Safe to call from several threads
+
+    @type fasta: str
+    @param fasta: path to .fasta file
+    """
+    path = os.path.realpath(fasta)
+    stat = os.stat(path)
+    stamp = (stat.st_mtime_ns, stat.st_size)
+
+    with FASTA_LOCK:
+        cached = FASTA_CACHE.get(path)
+        if cached is None or cached[0] != stamp:
+            cached = FASTA_CACHE[path] = (stamp, list(read_fasta(path)))
+
+    return cached[1]
+
+
+def clear_fasta_cache() -> None:
+    with FASTA_LOCK:
+        FASTA_CACHE.clear()
+
+
+def add_record(seqs: dict, name: str, seq: str, policy: str = "keep_first") -> bool:
+    """
+    Adds a sequence to a name -> sequence dictionary following a
+    duplicate policy (error, keep_first, keep_longest)
+
+    @type seqs: dict
+    @param seqs: name -> sequence dictionary
+    @type name: str
+    @param name: record name
+    @type seq: str
+    @param seq: record sequence
+    @type policy: str
+    @param policy: one of Constants.DUPLICATE_POLICIES
+    @rtype: bool
+    @return: True if the name was already present
+    """
+    if name not in seqs:
+        seqs[name] = seq
+        return False
+    if policy == "error":
+        raise PostogaValueError(f"duplicate .fasta header: {name}", name)
+    if policy == "keep_longest" and len(seq) > len(seqs[name]):
+        seqs[name] = seq
+
+    return True
+
+
+def fasta_to_dict(fasta: str, keep: set = None, duplicates: str = "keep_first") -> dict:
+    """
+    Reads a .fasta file into a name -> sequence dictionary, where
+    name is the first word of the header. Records with an empty
+    header are skipped
+
+    @type fasta: str
+    @param fasta: path to .fasta file
+    @type keep: set
+    @param keep: optional set of names to keep, everything else is skipped
+    @type duplicates: str
+    @param duplicates: duplicate policy (see add_record)
+    """
+    seqs = {}
+
+    for header, seq in read_fasta(fasta):
+        name = header.split()[0] if header.strip() else ""
+        if name and (keep is None or name in keep):
+            add_record(seqs, name, seq, duplicates)
+
+    return seqs
+
+
+def write_chrom_sizes(fasta: str, output: str) -> str:
+    """
+    Writes a chrom.sizes file (name, length) from a .fasta file
+
+    @type fasta: str
+    @param fasta: path to .fasta file
+    @type output: str
+    @param output: path to the chrom.sizes file
+    """
+    with open(output, "w") as f:
+        for header, seq in read_fasta(fasta):
+            f.write(f"{header.split()[0]}\t{len(seq)}\n")
+
+    return output
+
+
+def format_fasta(header: str, seq: str, width: int = 60) -> str:
+    """
+    Formats a single record as .fasta text
+
+    @type header: str
+    @param header: header without the leading '>'
+    @type seq: str
+    @param seq: sequence
+    @type width: int
+    @param width: line width, 0 to write the sequence in a single line
+    """
+    if width <= 0:
+        return f">{header}\n{seq}\n"
+
+    lines = [seq[i : i + width] for i in range(0, len(seq), width)]
+
+    return f">{header}\n" + "".join(f"{line}\n" for line in lines)
+
+
+def write_fasta(handle, header: str, seq: str, width: int = 60) -> None:
+    """
+    Writes a single record to an open file handle
+
+    @type handle: file
+    @param handle: writable file handle
+    @type header: str
+    @param header: header without the leading '>'
+    @type seq: str
+    @param seq: sequence
+    @type width: int
+    @param width: line width, 0 to write the sequence in a single line
+    """
+    handle.write(format_fasta(header, seq, width))
+
+
+def reformat_fasta(
+    fasta: str,
+    output: str,
+    wrap: int = 60,
+    upper: bool = False,
+    dedup: str = None,
+) -> dict:
+    """
+    Normalizes a third-party .fasta file: sequences are rewrapped with
+    whitespace and CR stripped, optionally upper-cased (soft-masking
+    lost), and records sharing a name (first header word) deduplicated.
+    Streams the input, with a first pass for keep_longest, so the output
+    cannot be the input itself
+
+    @type fasta: str
+    @param fasta: path to .fasta file, optionally compressed
+    @type output: str
+    @param output: path to the normalized .fasta (compressed by extension)
+    @type wrap: int
+    @param wrap: line width, 0 to write each sequence in a single line
+    @type upper: bool
+    @param upper: upper-case every sequence
+    @type dedup: str
+    @param dedup: one of Constants.REFORMAT_DUPLICATE_POLICIES, duplicates
+        are kept as they are if None; rename appends the first unused _2,
+        _3... to the name
+    @rtype: dict
+    @return: counts of records read, written, empty and duplicated
+    """
+    if os.path.exists(output) and os.path.samefile(fasta, output):
+        raise PostogaValueError(
+            f"{output} is the .fasta being reformatted, write it to another path",
+            output,
+        )
+
+    longest = {}
+    if dedup == "keep_longest":
+        for i, (header, seq) in enumerate(read_fasta(fasta)):
+            name = header.split()[0] if header.strip() else ""
+            size = len("".join(seq.split()))
+            if size > longest.get(name, (-1, None))[0]:
+                longest[name] = (size, i)
+
+    counts = {"records": 0, "written": 0, "empty": 0, "duplicates": 0}
+    seen = {}
+
+    with open_text(output, "w") as out:
+        for i, (header, seq) in enumerate(read_fasta(fasta)):
+            counts["records"] += 1
+            seq = "".join(seq.split())
+            seq = seq.upper() if upper else seq
+            counts["empty"] += not seq
+
+            header = header.strip()
+            name = header.split()[0] if header else ""
+            seen[name] = seen.get(name, 0) + 1
+            if seen[name] > 1:
+                counts["duplicates"] += 1
+                if dedup == "error":
+                    raise PostogaValueError(f"duplicate .fasta header: {name}", fasta)
+                if dedup == "keep_first":
+                    continue
+                if dedup == "rename":
+                    # Skip suffixes taken by other records (e.g. a real x_2)
+                    suffix = seen[name]
+                    while f"{name}_{suffix}" in seen:
+                        suffix += 1
+                    seen[f"{name}_{suffix}"] = 1
+                    header = f"{name}_{suffix}{header[len(name):]}"
+            if dedup == "keep_longest" and longest[name][1] != i:
+                continue
+
+            write_fasta(out, header, seq, wrap)
+            counts["written"] += 1
+
+    return counts
+
+
+def reverse_complement(seq: str) -> str:
+    return seq.translate(COMPLEMENT)[::-1]
+
+
+def splice(genome: dict, chrom: str, blocks: list, strand: str) -> str:
+    """
+    Concatenates the sequence of (start, end) 0-based blocks, in
+    transcript orientation. Blocks running past the end of the contig
+    (circular contigs) continue from its origin
+
+    @type genome: dict
+    @param genome: chrom -> sequence dictionary
+    @type chrom: str
+    @param chrom: chromosome name
+    @type blocks: list
+    @param blocks: sorted list of (start, end) tuples
+    @type strand: str
+    @param strand: + or -
+    """
+    contig = genome[chrom]
+    seq = "".join(
+        contig[start:end] if end <= len(contig) else circular_slice(contig, start, end)
+        for start, end in blocks
+    )
+
+    return reverse_complement(seq) if strand == "-" else seq
+
+
+def circular_slice(contig: str, start: int, end: int) -> str:
+    """
+    Slices a circular contig with coordinates that may run past its end
+
+    @type contig: str
+    @param contig: contig sequence
+    @type start: int
+    @param start: 0-based start
+    @type end: int
+    @param end: 0-based exclusive end, may be > len(contig)
+    """
+    length = len(contig)
+    start, end = start % length, start % length + (end - start)
+
+    return (contig * (end // length + 1))[start:end]
+
+
+@lru_cache(maxsize=None)
+def codon_table(code: str = "standard") -> dict:
+    """
+    Returns a codon -> amino acid dictionary for a genetic code
+
+    @type code: str
+    @param code: name of the genetic code in Constants.GENETIC_CODES
+    """
+    return dict(zip(CODONS, Constants.GENETIC_CODES[code]))
+
+
+def is_mitochondrial(chrom: str, mt_contigs: set = None) -> bool:
+    """
+    Checks whether a contig is mitochondrial, by name or user list
+
+    @type chrom: str
+    @param chrom: contig name
+    @type mt_contigs: set
+    @param mt_contigs: extra contig names to treat as mitochondrial
+    """
+    return chrom in Constants.MT_CONTIGS or chrom in (mt_contigs or set())
+
+
+def genetic_code(chrom: str, mt_contigs: set = None) -> str:
+    """
+    Returns the name of the genetic code to use on a contig
+
+    @type chrom: str
+    @param chrom: contig name
+    @type mt_contigs: set
+    @param mt_contigs: extra contig names to treat as mitochondrial
+    """
+    if is_mitochondrial(chrom, mt_contigs):
+        return "vertebrate_mitochondrial"
+
+    return "standard"
+
+
+def translate(seq: str, table: dict = None) -> str:
+    """
+    Translates a nucleotide sequence codon by codon, codons with
+    ambiguous bases become 'X' and trailing incomplete codons are ignored
+
+    @type seq: str
+    @param seq: nucleotide sequence in frame
+    @type table: dict
+    @param table: codon -> amino acid dictionary (default: standard code)
+    """
+    table = table or codon_table()
+    seq = seq.upper()
+
+    return "".join(
+        table.get(seq[i : i + 3], "X") for i in range(0, len(seq) - len(seq) % 3, 3)
+    )
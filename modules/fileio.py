@@ -0,0 +1,121 @@
+#!/usr/bin/env python3
+
+
+"""
+A module to open plain and compressed files, with no dependencies
+beyond the standard library (zstandard is imported on demand).
+
+Inputs are told apart by their magic bytes, never their extension:
+
+    gz / bgzf   1f 8b
+    zstd        28 b5 2f fd   (needs the zstandard package)
+    xz          fd 37 7a 58 5a 00
+
+Outputs take an explicit compression or infer it from the extension
+(.gz, .bgz, .zst, .xz). Readers such as read_bed stay lightweight by
+importing from here instead of compression.
+"""
+
+
+import gzip
+import io
+import lzma
+import os
+from importlib import import_module
+from modules.errors import PostogaIOError
+
+
+__author__ = "Alejandro Gonzales-Irribarren"
+__email__ = "jose.gonzalesdezavala1@unmsm.edu.pe"
+__github__ = "https://github.com/alejandrogzi"
+__version__ = "0.7.0-devel"
+
+
+MAGIC = {
+    "gz": b"\x1f\x8b",
+    "zstd": b"\x28\xb5\x2f\xfd",
+    "xz": b"\xfd7zXZ\x00",
+}
+EXTENSIONS = {".gz": "gz", ".bgz": "bgzf", ".zst": "zstd", ".zstd": "zstd", ".xz": "xz"}
+
+
+def detect_compression(path: str) -> str:
+    """
+    Returns the compression of a file from its magic bytes (gz, zstd or
+    xz; bgzf reads as gz), None for plain files
+
+    @type path: str
+    @param path: path to the file
+    """
+    with open(path, "rb") as f:
+        head = f.read(6)
+
+    return next((name for name, magic in MAGIC.items() if head.startswith(magic)), None)
+
+
+def extension_compression(path: str) -> str:
+    """
+    Returns the compression implied by the extension of a file name,
+    None if it has no compression extension
+
+    @type path: str
+    @param path: path to the file
+    """
+    name = os.path.basename(path).lower()
+
+    return next((kind for ext, kind in EXTENSIONS.items() if name.endswith(ext)), None)
+
+
+def zstandard():
+    """Returns the zstandard module, raising if it is not installed"""
+    try:
+        return import_module("zstandard")
+    except ImportError:
+        raise PostogaIOError(
+            "zstd files need the zstandard package (pip install zstandard)"
+        )
+
+
+def open_binary(path: str, mode: str = "rb", compress: str = None):
+    """
+    Opens a plain or compressed file in binary mode. Reading detects the
+    compression; writing uses compress or the extension of the path
+
+    @type path: str
+    @param path: path to the file
+    @type mode: str
+    @param mode: rb, wb or ab
+    @type compress: str
+    @param compress: none, gz, zstd or xz (writing only)
+    """
+    if "r" in mode:
+        compress = detect_compression(path)
+    elif compress is None:
+        compress = extension_compression(path)
+
+    if compress in ("gz", "bgzf"):
+        return gzip.open(path, mode)
+    if compress == "xz":
+        return lzma.open(path, mode)
+    if compress == "zstd":
+        return zstandard().open(path, mode)
+
+    return open(path, mode)
+
+
+def open_text(path: str, mode: str = "r", compress: str = None):
+    """
+    Opens a plain, gz/bgzf, zstd or xz file as text (see open_binary)
+
+    @type path: str
+    @param path: path to the file
+    @type mode: str
+    @param mode: r, w or a
+    @type compress: str
+    @param compress: none, gz, zstd or xz (writing only)
+    """
+    handle = open_binary(path, mode.replace("t", "") + "b", compress)
+
+    return io.TextIOWrapper(
+        handle, encoding="utf-8", newline=None if "r" in mode else ""
+    )
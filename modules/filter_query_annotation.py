@@ -8,6 +8,8 @@ import pandas as pd
 import os
 from constants import Constants
 from logger import Log
+from modules.fileio import open_text
+from modules.errors import PostogaValueError
 from modules.utils import bed_reader
 
 
@@ -17,6 +19,55 @@ __github__ = "https://github.com/alejandrogzi"
 __version__ = "0.6.0-devel"
 
 
+def check_whitelist(values: str, known, kind: str) -> list:
+    """
+    Splits a comma-separated whitelist, rejecting unknown values that
+    would silently filter every projection out
+
+    @type values: str
+    @param values: comma-separated values
+    @type known: iterable
+    @param known: accepted values
+    @type kind: str
+    @param kind: what the values are (for the error message)
+    """
+    values = values.split(",")
+    unknown = [x for x in values if x not in known]
+    if unknown:
+        raise PostogaValueError(
+            f"unknown {kind} {', '.join(unknown)}, expected any of {', '.join(known)}",
+            ",".join(unknown),
+        )
+
+    return values
+
+
+def select_bed(bed: str, names: set, output: str) -> list:
+    """
+    Writes the lines of a .bed file whose name is in a set, streaming the
+    file and splitting only the name field of each line
+
+    @type bed: str
+    @param bed: path to .bed file, optionally gzipped
+    @type names: set
+    @param names: projection names to keep
+    @type output: str
+    @param output: path to the filtered .bed file
+    @rtype: list
+    @return: names of the lines kept
+    """
+    kept = []
+
+    with open_text(bed) as f, open(output, "w") as out:
+        for line in f:
+            fields = line.split("\t", 4)
+            if len(fields) > 3 and fields[3] in names:
+                out.write(line)
+                kept.append(fields[3])
+
+    return kept
+
+
 def filter_bed(
     path: str, table: pd.DataFrame, by_class: list, by_rel: list, threshold: str
 ) -> str:
@@ -46,37 +97,34 @@ def filter_bed(
 
     if by_class:
         edge = len(table)
-        table = table[table["class"].isin(by_class.split(","))]
+        classes = check_whitelist(by_class, list(Constants.ORDER), "classes")
+        table = table[table["class"].isin(classes)]
         log.record(
             f"discarded {edge - len(table)} projections with classes other than {by_class}"
         )
 
     if by_rel:
         edge = len(table)
-        table = table[table["relation"].isin(by_rel.split(","))]
+        relations = check_whitelist(
+            by_rel, list(Constants.ORTHOLOGY_TYPE.values()), "relations"
+        )
+        table = table[table["relation"].isin(relations)]
         log.record(
             f"discarded {edge - len(table)} projections with relationships other than {by_rel}"
         )
 
-    # Read the original .bed file and filter it based on the transcripts table
-    bed = pd.read_csv(
-        os.path.join(path, Constants.FileNames.BED), sep="\t", header=None
-    )
-    bed = bed[bed[3].isin(table["transcripts"])]
-    custom_table = table[table["transcripts"].isin(bed[3])]
-
-    # Write the filtered .bed file
+    # Stream the original .bed file, keeping the projections left in the table
     f = os.path.join(path, Constants.FileNames.FILTERED_BED)
-    bed.to_csv(
+    kept = select_bed(
+        os.path.join(path, Constants.FileNames.BED),
+        set(table["transcripts"].dropna()),
         f,
-        sep="\t",
-        header=None,
-        index=False,
     )
+    custom_table = table[table["transcripts"].isin(kept)]
 
     info = [
-        f"kept {len(bed)} projections after filters, discarded {initial - len(bed)}.",
-        f"{len(bed)} projections are coming from {len(custom_table['helper'].unique())} unique transcripts and {len(custom_table['t_gene'].unique())} genes",
+        f"kept {len(kept)} projections after filters, discarded {initial - len(kept)}.",
+        f"{len(kept)} projections are coming from {len(custom_table['helper'].unique())} unique transcripts and {len(custom_table['t_gene'].unique())} genes",
         f"class stats of new bed: {custom_table['class'].value_counts().to_dict()}",
         f"relation stats of new bed: {custom_table['relation'].value_counts().to_dict()}",
         f"confidence stats of new bed: {custom_table['confidence_level'].value_counts().to_dict()}",
@@ -0,0 +1,180 @@
+#!/usr/bin/env python3
+
+
+"""
+A module to detect and open annotation files regardless of extension.
+
+The format is sniffed from the first data line (BED, GTF, GFF3 or
+genePred, with or without the leading bin column) and falls back to the
+extension only for files without data lines. Gzipped and bgzipped
+files are recognized by their magic bytes, not by their name:
+
+``` python
+from modules.formats import detect_format, open_annotation
+
+fmt = detect_format("query_annotation.gtf.gz")  # gtf
+for record in open_annotation("query_annotation.gtf.gz"):
+    ...
+```
+"""
+
+
+import re
+from modules.bed import BedRecord, bed_fields
+from modules.errors import PostogaValueError
+from modules.fileio import open_text
+from modules.gxf import GxfFeature
+
+
+__author__ = "Alejandro Gonzales-Irribarren"
+__email__ = "jose.gonzalesdezavala1@unmsm.edu.pe"
+__github__ = "https://github.com/alejandrogzi"
+__version__ = "0.7.0-devel"
+
+
+FORMATS = ["bed", "gtf", "gff", "genepred"]
+GTF_ATTRIBUTE = re.compile(r'^\s*[\w.\-]+\s+"?[^=]*;')
+EXTENSIONS = {
+    ".bed": "bed",
+    ".gtf": "gtf",
+    ".gff": "gff",
+    ".gff3": "gff",
+    ".gp": "genepred",
+    ".genepred": "genepred",
+}
+
+
+def data_lines(handle):
+    """Yields the stripped non-empty, non-comment, non-track lines of a file"""
+    for line in handle:
+        if not line.strip() or line.startswith(("#", "track", "browser")):
+            continue
+        yield line.rstrip("\r\n")
+
+
+def is_int(value: str) -> bool:
+    return value.lstrip("-").isdigit()
+
+
+def sniff_line(fields: list) -> str:
+    """
+    Returns the format of a data line split on tabs, None if unknown
+
+    @type fields: list
+    @param fields: tab-separated fields of the line
+    """
+    if len(fields) == 9 and is_int(fields[3]) and is_int(fields[4]):
+        return "gtf" if GTF_ATTRIBUTE.match(fields[8]) else "gff"
+
+    # genePred (name chrom strand ...), optionally preceded by a bin column
+    for offset in (0, 1):
+        if (
+            len(fields) >= 10 + offset
+            and fields[2 + offset] in ("+", "-")
+            and all(is_int(x) for x in fields[3 + offset : 8 + offset])
+            and (offset == 0 or is_int(fields[0]))
+        ):
+            return "genepred"
+
+    if len(fields) >= 3 and is_int(fields[1]) and is_int(fields[2]):
+        return "bed"
+
+    return None
+
+
+def extension_format(path: str) -> str:
+    """
+    Returns the format implied by the extension, ignoring .gz/.bgz
+
+    @type path: str
+    @param path: path to the file
+    """
+    name = re.sub(r"\.(gz|bgz)$", "", path.lower())
+
+    for ext, fmt in EXTENSIONS.items():
+        if name.endswith(ext):
+            return fmt
+
+    return None
+
+
+def detect_format(path: str) -> str:
+    """
+    Returns the format of an annotation file (see FORMATS), sniffing its
+    first data line and falling back to the extension for empty files
+
+    @type path: str
+    @param path: path to a .bed/.gtf/.gff/genePred file, optionally gzipped
+    """
+    with open_text(path) as f:
+        for line in f:
+            if line.startswith("##gff-version"):
+                return "gff"
+            if not line.strip() or line.startswith(("#", "track", "browser")):
+                continue
+            fmt = sniff_line(line.rstrip("\r\n").split("\t"))
+            if fmt:
+                return fmt
+            break
+
+    fmt = extension_format(path)
+    if fmt is None:
+        raise PostogaValueError(
+            f"could not detect the annotation format of {path}", path
+        )
+
+    return fmt
+
+
+def genepred_fields(fields: list) -> list:
+    """
+    Converts a genePred line (with or without bin column) to BED12 fields
+
+    @type fields: list
+    @param fields: tab-separated fields of the line
+    """
+    if fields[3] in ("+", "-"):
+        fields = fields[1:]
+
+    name, chrom, strand = fields[:3]
+    start, end, thick_start, thick_end = fields[3:7]
+    starts = [int(x) for x in fields[8].strip(",").split(",")]
+    ends = [int(x) for x in fields[9].strip(",").split(",")]
+
+    return [
+        chrom,
+        start,
+        end,
+        name,
+        ".",
+        strand,
+        thick_start,
+        thick_end,
+        "0,0,0",
+        str(len(starts)),
+        ",".join(str(e - s) for s, e in zip(starts, ends)) + ",",
+        ",".join(str(s - int(start)) for s in starts) + ",",
+    ]
+
+
+def open_annotation(path: str, fmt: str = None):
+    """
+    Yields the records of an annotation file with the right parser:
+    BedRecord objects for BED and genePred, GxfFeature objects for
+    .gtf/.gff. Short BED lines are read as single-block records.
+
+    @type path: str
+    @param path: path to a .bed/.gtf/.gff/genePred file, optionally gzipped
+    @type fmt: str
+    @param fmt: format to use instead of detecting it (see FORMATS)
+    """
+    fmt = fmt or detect_format(path)
+
+    with open_text(path) as f:
+        for line in data_lines(f):
+            if fmt in ("gtf", "gff"):
+                yield GxfFeature(line.split("\t", 8), fmt)
+            elif fmt == "genepred":
+                yield BedRecord(genepred_fields(line.split("\t")))
+            else:
+                yield BedRecord(bed_fields(line.split("\t")))
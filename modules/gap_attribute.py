@@ -0,0 +1,147 @@
+#!/usr/bin/env python3
+
+
+"""
+A module to describe CDS indels as GFF3 Gap/Target attributes.
+
+TOGA writes the codon alignment of each projection against its
+reference transcript (codon.fasta, REFERENCE and QUERY records). Walking
+that alignment along the CDS features of a projection gives, for each
+CDS, the reference stretch it aligns to (Target) and where insertions
+and deletions landed (Gap, in bases, feature orientation):
+
+    Gap=M45 I3 M102    3 reference bases missing in the query genome
+    Gap=M60 D1 M30     1 extra query base (e.g. a frameshift)
+"""
+
+
+from collections import defaultdict
+from modules.extract_seqs import parse_toga_header
+from modules.fasta import cached_fasta
+
+
+__author__ = "Alejandro Gonzales-Irribarren"
+__email__ = "jose.gonzalesdezavala1@unmsm.edu.pe"
+__github__ = "https://github.com/alejandrogzi"
+__version__ = "0.7.0-devel"
+
+
+GAP = "-"
+
+
+def read_alignments(fasta: str) -> dict:
+    """
+    Returns the reference/query codon alignment of each projection
+
+    @type fasta: str
+    @param fasta: path to TOGA's codon.fasta
+    @rtype: dict
+    @return: projection -> (reference, query) aligned sequences
+    """
+    pairs = defaultdict(dict)
+
+    for header, seq in cached_fasta(fasta):
+        name, source = parse_toga_header(header)
+        if name and source:
+            pairs[name].setdefault(source, seq.replace(" ", ""))
+
+    return {
+        name: (x["REFERENCE"], x["QUERY"])
+        for name, x in pairs.items()
+        if "REFERENCE" in x and "QUERY" in x and len(x["REFERENCE"]) == len(x["QUERY"])
+    }
+
+
+def gap_string(ops: list) -> str:
+    """
+    Run-length encodes alignment operations (M, I, D) as a Gap value
+
+    @type ops: list
+    @param ops: one operation per alignment column
+    """
+    runs = []
+
+    for op in ops:
+        if runs and runs[-1][0] == op:
+            runs[-1][1] += 1
+        else:
+            runs.append([op, 1])
+
+    return " ".join(f"{op}{n}" for op, n in runs)
+
+
+def split_alignment(reference: str, query: str, lengths: list) -> list:
+    """
+    Splits an alignment into the segments of consecutive CDS features.
+    Columns gapped in the query stay with the CDS before them
+
+    @type reference: str
+    @param reference: aligned reference sequence
+    @type query: str
+    @param query: aligned query sequence
+    @type lengths: list
+    @param lengths: CDS feature lengths in transcript order
+    @rtype: list
+    @return: (ops, reference start, reference end) per CDS, 1-based, None
+        if the alignment does not cover the CDS exactly
+    """
+    segments, ops, ref_pos, ref_start, used = [], [], 0, 1, 0
+    remaining = list(lengths)
+
+    for r, q in zip(reference, query):
+        if r == GAP and q == GAP:
+            continue
+        if q != GAP and remaining and used == remaining[0]:
+            segments.append((ops, ref_start, ref_pos))
+            ops, ref_start, used = [], ref_pos + 1, 0
+            remaining.pop(0)
+        if r == GAP:
+            ops.append("D")
+        elif q == GAP:
+            ops.append("I")
+        else:
+            ops.append("M")
+        ref_pos += r != GAP
+        used += q != GAP
+
+    if len(remaining) != 1 or used != remaining[0]:
+        return None
+
+    return segments + [(ops, ref_start, ref_pos)]
+
+
+def annotate_gaps(features: list, alignments: dict) -> tuple:
+    """
+    Adds Gap and Target attributes to the CDS features of projections
+    with a codon alignment
+
+    @type features: list
+    @param features: list of GxfFeature objects (.gff)
+    @type alignments: dict
+    @param alignments: projection -> (reference, query), see read_alignments
+    @rtype: tuple
+    @return: (annotated projections, projections whose CDS length does not
+        match their alignment)
+    """
+    cds = defaultdict(list)
+    for feature in features:
+        if feature.feature == "CDS" and feature.transcript_id in alignments:
+            cds[feature.transcript_id].append(feature)
+
+    annotated, mismatched = 0, []
+    for name, parts in cds.items():
+        parts = sorted(parts, key=lambda x: x.start, reverse=parts[0].strand == "-")
+        reference, query = alignments[name]
+        segments = split_alignment(
+            reference, query, [x.end - x.start + 1 for x in parts]
+        )
+        if segments is None:
+            mismatched.append(name)
+            continue
+        target = name.rsplit(".", 1)[0]
+        for feature, (ops, start, end) in zip(parts, segments):
+            feature.attributes["Target"] = f"{target} {start} {end} +"
+            feature.attributes["Gap"] = gap_string(ops)
+        annotated += 1
+
+    return annotated, sorted(mismatched)
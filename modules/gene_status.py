@@ -0,0 +1,156 @@
+#!/usr/bin/env python3
+
+
+"""
+A module to make minimum-evidence gene calls.
+
+Each reference gene gets a final status (annotated / putative / absent)
+from its best TOGA class plus the number of independent lines of
+support (expression, homology search) it has in the query. Rules are
+checked in the order of Constants.GENE_STATUS_RULES; the first match
+wins and genes matching none are absent.
+"""
+
+
+import os
+import pandas as pd
+from constants import Constants
+from logger import Log
+from modules.diff_runs import gene_classes
+from modules.homology_hits import hit_evidence, is_hit_table
+from modules.table_writer import write_table
+
+
+__author__ = "Alejandro Gonzales-Irribarren"
+__email__ = "jose.gonzalesdezavala1@unmsm.edu.pe"
+__github__ = "https://github.com/alejandrogzi"
+__version__ = "0.7.0-devel"
+
+
+def read_evidence(path: str) -> dict:
+    """
+    Reads a two-column (id, value) evidence table, skipping a header.
+    Ids can be reference genes or transcripts, projections or query genes
+
+    @type path: str
+    @param path: path to the evidence .tsv
+    """
+    evidence = {}
+
+    with open(path, "r") as f:
+        for line in f:
+            fields = line.rstrip("\n").split("\t")
+            if len(fields) < 2 or line.startswith("#"):
+                continue
+            try:
+                evidence[fields[0]] = float(fields[1])
+            except ValueError:
+                continue
+
+    return evidence
+
+
+def evidence_by_gene(table: pd.DataFrame, evidence: dict, passes) -> set:
+    """
+    Returns the reference genes with at least one passing evidence value
+    on any of their ids (gene, transcript, projection or query gene)
+
+    @type table: pd.DataFrame
+    @param table: query table
+    @type evidence: dict
+    @param evidence: id -> value
+    @type passes: callable
+    @param passes: value -> bool
+    """
+    genes = set()
+
+    for column in ("t_gene", "helper", "transcripts", "q_gene"):
+        for name, gene in zip(table[column], table["t_gene"]):
+            if name in evidence and passes(evidence[name]):
+                genes.add(gene)
+
+    return genes
+
+
+def call_status(cls: str, n_evidence: int) -> str:
+    """
+    Returns the status of the first rule matching a class and an
+    amount of supporting evidence (see Constants.GENE_STATUS_RULES)
+
+    @type cls: str
+    @param cls: best TOGA class of the gene
+    @type n_evidence: int
+    @param n_evidence: number of supporting evidence types
+    """
+    for status, classes, minimum in Constants.GENE_STATUS_RULES:
+        if cls in classes and n_evidence >= minimum:
+            return status
+
+    return "absent"
+
+
+def gene_status(
+    path: str,
+    table: pd.DataFrame,
+    expression: str = None,
+    homology: str = None,
+    min_expression: float = Constants.MIN_EXPRESSION,
+    max_evalue: float = Constants.MAX_EVALUE,
+    decimals: int = 4,
+) -> dict:
+    """
+    Writes the final status of every reference gene
+
+    @type path: str
+    @param path: path to the results directory
+    @type table: pd.DataFrame
+    @param table: query table
+    @type expression: str
+    @param expression: (id, TPM/count) table
+    @type homology: str
+    @param homology: (id, e-value) table or DIAMOND/BLAST/HMMER hit table
+    @type min_expression: float
+    @param min_expression: minimum expression to count as support
+    @type max_evalue: float
+    @param max_evalue: maximum e-value to count as support
+    @rtype: dict
+    @return: gene -> status
+    """
+
+    log = Log.connect(path, Constants.FileNames.LOG)
+
+    classes = gene_classes(table)
+    expressed = (
+        evidence_by_gene(
+            table, read_evidence(expression), lambda x: x >= min_expression
+        )
+        if expression
+        else set()
+    )
+    homologous = set()
+    if homology:
+        hits = hit_evidence(homology) if is_hit_table(homology) else None
+        homologous = evidence_by_gene(
+            table, hits or read_evidence(homology), lambda x: x <= max_evalue
+        )
+
+    rows, status = [], {}
+    for gene, cls in classes.items():
+        support = [gene in expressed, gene in homologous]
+        status[gene] = call_status(cls, sum(support))
+        rows.append([gene, cls] + support + [status[gene]])
+
+    f = os.path.join(path, Constants.FileNames.GENE_STATUS)
+    write_table(
+        rows, f, ["gene", "class", "expressed", "homology", "status"], decimals=decimals
+    )
+
+    counts = {x: list(status.values()).count(x) for x in set(status.values())}
+    info = [
+        f"gene status called with {sum(x is not None for x in (expression, homology))} evidence tables",
+        f"gene status counts: {counts}",
+        f"gene status table written to {f}",
+    ]
+    [log.record(i) for i in info]
+
+    return status
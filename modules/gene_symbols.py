@@ -0,0 +1,110 @@
+#!/usr/bin/env python3
+
+
+"""
+A module to normalize gene symbols across reference naming schemes.
+
+Runs against different references name the same gene differently
+(mouse Tp53 vs human TP53, or a previous symbol vs the approved one).
+Symbols are upper-cased and resolved through HGNC/MGI-style alias
+tables so multi-species matrices join on one gene key.
+"""
+
+
+from constants import Constants
+
+
+__author__ = "Alejandro Gonzales-Irribarren"
+__email__ = "jose.gonzalesdezavala1@unmsm.edu.pe"
+__github__ = "https://github.com/alejandrogzi"
+__version__ = "0.7.0-devel"
+
+
+def rows(path: str) -> list:
+    """Yields the tab-separated rows of a table, skipping comments and a header"""
+    with open(path, "r") as f:
+        for i, line in enumerate(f):
+            if not line.strip() or line.startswith("#"):
+                continue
+            fields = line.rstrip("\n").split("\t")
+            if i == 0 and any("symbol" in x.lower() for x in fields):
+                continue
+            yield fields
+
+
+def read_symbols(path: str) -> dict:
+    """
+    Reads a gene ID -> symbol table (e.g. an Ensembl BioMart export)
+
+    @type path: str
+    @param path: path to a two-column (gene ID, symbol) .tsv
+    """
+    return {x[0]: x[1] for x in rows(path) if len(x) > 1 and x[1]}
+
+
+def read_aliases(paths: list) -> dict:
+    """
+    Reads HGNC/MGI-style alias tables into a normalized alias -> approved
+    symbol dictionary. The first column is the approved symbol, every
+    other column holds comma- or pipe-separated alias/previous symbols
+
+    @type paths: list
+    @param paths: paths to alias tables
+    """
+    aliases = {}
+
+    for path in paths or []:
+        for fields in rows(path):
+            approved = fields[0].strip().upper()
+            for column in fields[1:]:
+                for alias in column.replace("|", ",").split(","):
+                    alias = alias.strip().upper()
+                    if alias and alias != approved:
+                        aliases.setdefault(alias, approved)
+
+    return aliases
+
+
+def normalize_symbol(symbol: str, aliases: dict = None) -> str:
+    """
+    Returns the normalized gene key of a symbol: upper-cased and
+    resolved to its approved symbol when it is a known alias
+
+    @type symbol: str
+    @param symbol: gene symbol
+    @type aliases: dict
+    @param aliases: alias -> approved symbol (see read_aliases)
+    """
+    symbol = symbol.strip().upper()
+
+    return (aliases or {}).get(symbol, symbol)
+
+
+def rekey_classes(classes: dict, symbols: dict, aliases: dict = None) -> tuple:
+    """
+    Re-keys gene -> class pairs by normalized symbol. Genes without a
+    symbol keep their ID; genes collapsing onto the same symbol keep the
+    most intact class (see Constants.ORDER)
+
+    @type classes: dict
+    @param classes: gene ID -> class
+    @type symbols: dict
+    @param symbols: gene ID -> symbol
+    @type aliases: dict
+    @param aliases: alias -> approved symbol
+    @rtype: tuple
+    @return: (symbol -> class, symbol -> colliding gene IDs)
+    """
+    rekeyed, members = {}, {}
+
+    for gene, cls in classes.items():
+        key = normalize_symbol(symbols[gene], aliases) if gene in symbols else gene
+        members.setdefault(key, []).append(gene)
+        if key not in rekeyed or Constants.ORDER.get(cls, 99) < Constants.ORDER.get(
+            rekeyed[key], 99
+        ):
+            rekeyed[key] = cls
+
+    collisions = {k: v for k, v in members.items() if len(v) > 1}
+
+    return rekeyed, collisions
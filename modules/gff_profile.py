@@ -0,0 +1,134 @@
+#!/usr/bin/env python3
+
+
+"""
+A module with GFF3 attribute profiles for submission tooling.
+
+The refseq profile follows the NCBI RefSeq/AgBioData attribute
+conventions expected by NCBI's validation tooling:
+
+    gene         gbkey=Gene; gene=<symbol>; gene_biotype; description
+    mRNA/transcript  gbkey=mRNA (misc_RNA if non-coding); gene; product
+    exon         gbkey as its transcript; gene; product
+    CDS          gbkey=CDS; gene; product; protein_id
+    all          Dbxref to the reference gene (Ensembl:, GeneID:)
+
+Symbols, products and biotypes come from the query table enrichment
+(see reference_metadata); the reference gene ID stands in when a
+projection has none.
+"""
+
+
+import pandas as pd
+from modules.gxf import TRANSCRIPT
+
+
+__author__ = "Alejandro Gonzales-Irribarren"
+__email__ = "jose.gonzalesdezavala1@unmsm.edu.pe"
+__github__ = "https://github.com/alejandrogzi"
+__version__ = "0.7.0-devel"
+
+
+def dbxref(gene: str) -> str:
+    """
+    Returns the Dbxref of a reference gene, None if its database is unknown
+
+    @type gene: str
+    @param gene: reference gene ID
+    """
+    if gene.startswith("ENS"):
+        return f"Ensembl:{gene.split('.')[0]}"
+    if gene.isdigit():
+        return f"GeneID:{gene}"
+
+    return None
+
+
+def profile_info(table: pd.DataFrame) -> dict:
+    """
+    Returns projection -> {gene, symbol, product, biotype} from the
+    query table (ref_* columns if the reference metadata was added)
+
+    @type table: pd.DataFrame
+    @param table: query table
+    """
+    info = {}
+
+    for row in table.dropna(subset=["transcripts"]).to_dict("records"):
+        gene = row.get("t_gene")
+        gene = None if pd.isna(gene) else str(gene)
+        fields = {
+            key: row.get(f"ref_{key}")
+            for key in ("symbol", "description", "biotype")
+        }
+        fields = {k: v for k, v in fields.items() if isinstance(v, str) and v}
+        info[row["transcripts"]] = {"gene": gene, **fields}
+
+    return info
+
+
+def refseq_profile(features: list, info: dict) -> int:
+    """
+    Adds the refseq profile attributes to .gff features in place
+
+    @type features: list
+    @param features: list of GxfFeature objects (.gff, gene -> transcript tree)
+    @type info: dict
+    @param info: projection -> fields (see profile_info)
+    @rtype: int
+    @return: number of features annotated
+    """
+    transcripts = {
+        x.attributes.get("ID"): x for x in features if x.feature in TRANSCRIPT
+    }
+    coding = {
+        parent
+        for x in features
+        if x.feature == "CDS"
+        for parent in x.attributes.get("Parent", "").split(",")
+    }
+    genes = {}
+    for tx, feature in transcripts.items():
+        genes.setdefault(feature.attributes.get("Parent"), info.get(tx, {}))
+
+    annotated = 0
+    for feature in features:
+        if feature.feature == "gene":
+            fields = genes.get(feature.attributes.get("ID"), {})
+            tx = None
+        elif feature.feature in TRANSCRIPT:
+            tx = feature.attributes.get("ID")
+            fields = info.get(tx, {})
+        else:
+            tx = feature.attributes.get("Parent", "").split(",")[0]
+            fields = info.get(tx, {})
+
+        gene = fields.get("gene")
+        symbol = fields.get("symbol") or gene
+        product = fields.get("description") or symbol
+        attributes = {}
+
+        if feature.feature == "gene":
+            attributes["gbkey"] = "Gene"
+            if fields.get("biotype"):
+                attributes["gene_biotype"] = fields["biotype"]
+            if fields.get("description"):
+                attributes["description"] = fields["description"]
+        elif feature.feature == "CDS":
+            attributes["gbkey"] = "CDS"
+            attributes["protein_id"] = f"{tx}.p"
+        else:
+            attributes["gbkey"] = "mRNA" if tx in coding else "misc_RNA"
+        if symbol:
+            attributes["gene"] = symbol
+        if feature.feature != "gene" and product:
+            attributes["product"] = product
+        if gene and dbxref(gene):
+            attributes["Dbxref"] = dbxref(gene)
+
+        feature.attributes.update(
+            {k: v for k, v in attributes.items() if k not in feature.attributes}
+        )
+        annotated += 1
+
+    return annotated
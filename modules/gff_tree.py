@@ -0,0 +1,161 @@
+#!/usr/bin/env python3
+
+
+"""
+A module to rebuild the gene -> transcript -> exon/CDS tree of a .gff file.
+
+bed2gff writes transcripts with an ad-hoc `Parent=<gene>` and gene lines
+synthesized on their own, which strict GFF3 consumers (AGAT, gffread)
+may reject. The tree built here guarantees that every transcript has a
+gene line and every sub-feature an existing transcript as Parent
+(missing parents are synthesized spanning their children), genes span
+their transcripts, features come out gene by gene, and the file gets
+`##sequence-region` pragmas after its `##gff-version 3` header.
+"""
+
+
+from collections import defaultdict
+from modules.gxf import CODING, TRANSCRIPT, GxfFeature
+
+
+__author__ = "Alejandro Gonzales-Irribarren"
+__email__ = "jose.gonzalesdezavala1@unmsm.edu.pe"
+__github__ = "https://github.com/alejandrogzi"
+__version__ = "0.7.0-devel"
+
+
+def parents(feature: GxfFeature) -> list:
+    """Returns the Parent IDs of a .gff feature (comma-separated)"""
+    return [x for x in feature.attributes.get("Parent", "").split(",") if x]
+
+
+def synthesize(kind: str, name: str, children: list, attributes: dict) -> GxfFeature:
+    """
+    Returns a gene/transcript feature spanning its children
+
+    @type kind: str
+    @param kind: feature type (gene, transcript, mRNA)
+    @type name: str
+    @param name: ID of the new feature
+    @type children: list
+    @param children: GxfFeature objects it spans
+    @type attributes: dict
+    @param attributes: extra attributes (e.g. Parent)
+    """
+    first = children[0]
+    feature = GxfFeature(
+        [
+            first.chrom,
+            first.source,
+            kind,
+            str(min(x.start for x in children)),
+            str(max(x.end for x in children)),
+            ".",
+            first.strand,
+            ".",
+            "",
+        ],
+        "gff",
+    )
+    feature.attributes = {"ID": name, **attributes}
+
+    return feature
+
+
+def gff_tree(features: list, isoforms=None, mrna: bool = False) -> tuple:
+    """
+    Rebuilds the gene -> transcript -> sub-feature tree of .gff features
+
+    @type features: list
+    @param features: list of GxfFeature objects (.gff)
+    @type isoforms: IsoformMap
+    @param isoforms: gene of transcripts without a valid Parent, if any
+    @type mrna: bool
+    @param mrna: emit coding transcripts as mRNA
+    @rtype: tuple
+    @return: (features in tree order, synthesized parents, orphan features)
+    """
+    genes, transcripts = {}, {}
+    children = defaultdict(list)
+    orphans = []
+
+    for feature in features:
+        if feature.feature == "gene":
+            genes[feature.attributes.get("ID")] = feature
+        elif feature.feature in TRANSCRIPT:
+            transcripts[feature.attributes.get("ID")] = feature
+
+    # Sub-features whose transcript is missing get one spanning them
+    synthesized = 0
+    for feature in features:
+        if feature.feature == "gene" or feature.feature in TRANSCRIPT:
+            continue
+        ids = parents(feature)
+        if not ids:
+            orphans.append(feature)
+            continue
+        for tx in ids:
+            children[tx].append(feature)
+
+    for tx, members in children.items():
+        if tx not in transcripts:
+            transcripts[tx] = synthesize("transcript", tx, members, {})
+            synthesized += 1
+
+    # Transcripts whose gene is missing get one spanning them
+    by_gene = defaultdict(list)
+    for tx, feature in transcripts.items():
+        gene = next(iter(parents(feature)), None)
+        if gene is None and isoforms is not None:
+            gene = isoforms.get(tx)
+        # GFF3 IDs are unique across features, a gene named after its
+        # only transcript gets a suffix
+        gene = gene or f"{tx}.gene"
+        feature.attributes["Parent"] = gene
+        by_gene[gene].append(feature)
+
+        if mrna and any(x.feature in CODING for x in children.get(tx, [])):
+            feature.feature = "mRNA"
+
+    for gene, members in by_gene.items():
+        if gene not in genes:
+            genes[gene] = synthesize("gene", gene, members, {})
+            synthesized += 1
+        else:
+            genes[gene].start = min([genes[gene].start] + [x.start for x in members])
+            genes[gene].end = max([genes[gene].end] + [x.end for x in members])
+
+    tree, emitted = [], set()
+    for gene_name, gene in sorted(
+        genes.items(), key=lambda x: (x[1].chrom, x[1].start, x[0] or "")
+    ):
+        tree.append(gene)
+        for tx in sorted(by_gene.get(gene_name, []), key=lambda x: x.start):
+            tree.append(tx)
+            for child in children.get(tx.attributes.get("ID"), []):
+                # Exons shared between isoforms are written once
+                if id(child) not in emitted:
+                    emitted.add(id(child))
+                    tree.append(child)
+
+    return tree, synthesized, orphans
+
+
+def sequence_regions(features: list, sizes: dict = None) -> list:
+    """
+    Returns `##sequence-region` pragmas for the sequences of a .gff,
+    spanning the chrom.sizes length if known or the last feature end
+
+    @type features: list
+    @param features: list of GxfFeature objects
+    @type sizes: dict
+    @param sizes: chrom -> length, if any
+    """
+    ends = defaultdict(int)
+    for feature in features:
+        ends[feature.chrom] = max(ends[feature.chrom], feature.end)
+
+    return [
+        f"##sequence-region {chrom} 1 {(sizes or {}).get(chrom, end)}"
+        for chrom, end in sorted(ends.items())
+    ]
@@ -0,0 +1,135 @@
+#!/usr/bin/env python3
+
+
+"""
+A module with hard limits against pathological records.
+
+Projections with inconsistent blocks are always skipped before
+conversion. Exon count, genomic span (e.g. chains gone wrong across a
+whole chromosome) and attribute length limits are opt-in through the
+--max_* options (Constants.LIMITS leaves them off): real long genes
+can span megabases. Every skipped projection is logged by name and
+recorded as a run warning; identifier attributes are never dropped.
+"""
+
+
+import os
+from collections import defaultdict
+from constants import Constants
+from logger import Log
+from modules.bed import read_bed
+from modules.run_warnings import RunWarnings
+from modules.utils import output_stem
+
+
+__author__ = "Alejandro Gonzales-Irribarren"
+__email__ = "jose.gonzalesdezavala1@unmsm.edu.pe"
+__github__ = "https://github.com/alejandrogzi"
+__version__ = "0.7.0-devel"
+
+
+# Attributes that tie features together, kept whatever their length
+KEY_ATTRIBUTES = {"transcript_id", "gene_id", "ID", "Parent"}
+
+
+def record_issue(record, limits: dict) -> str:
+    """
+    Returns the limit a BED12 record trips, None if it is within all
+
+    @type record: BedRecord
+    @param record: projection
+    @type limits: dict
+    @param limits: limit name -> value, None to skip it (see Constants.LIMITS)
+    """
+    if record.start < 0 or record.end < record.start:
+        return "invalid_coordinates"
+    if len(record.sizes) != len(record.offsets) or any(
+        s < 0 or record.start + o + s > record.end
+        for s, o in zip(record.sizes, record.offsets)
+    ):
+        return "invalid_blocks"
+    if limits["max_exons"] and len(record.sizes) > limits["max_exons"]:
+        return "max_exons"
+    if limits["max_span"] and record.end - record.start > limits["max_span"]:
+        return "max_span"
+
+    return None
+
+
+def guard_bed(path: str, bed: str, limits: dict = None) -> str:
+    """
+    Skips the projections tripping a limit, writing the rest to a new
+    .bed file. Returns the input .bed if nothing trips
+
+    @type path: str
+    @param path: path to the results directory
+    @type bed: str
+    @param bed: path to .bed file
+    @type limits: dict
+    @param limits: limit name -> value (default: Constants.LIMITS)
+    """
+
+    log = Log.connect(path, Constants.FileNames.LOG)
+
+    limits = {**Constants.LIMITS, **(limits or {})}
+    stem = os.path.basename(output_stem(bed))
+    f = os.path.join(path, f"{stem}.{Constants.FileNames.GUARDED_BED}")
+
+    # Kept records are written as they are read, the copy is dropped if
+    # nothing trips
+    skipped = defaultdict(list)
+    with open(f, "w") as out:
+        for record in read_bed(bed):
+            issue = record_issue(record, limits)
+            if issue:
+                skipped[issue].append(record.name)
+            else:
+                out.write(record.to_line() + "\n")
+
+    warnings = RunWarnings.connect(path)
+    for issue, names in skipped.items():
+        limit = limits.get(issue, "consistency check")
+        warnings.add(
+            f"limit_{issue}",
+            names,
+            f"projection skipped, it trips the {issue} guardrail ({limit})",
+        )
+        for name in names:
+            log.warning(f"{name} skipped by the {issue} guardrail ({limit})")
+
+    counts = {k: len(v) for k, v in skipped.items()}
+    log.record(
+        f"guardrails ({limits}): {sum(counts.values())} projections skipped {counts}"
+    )
+
+    if not skipped:
+        os.remove(f)
+        return bed
+
+    return f
+
+
+def guard_attributes(features: list, max_length: int) -> list:
+    """
+    Drops attribute values longer than max_length characters, except
+    the identifiers in KEY_ATTRIBUTES
+
+    @type features: list
+    @param features: list of GxfFeature objects
+    @type max_length: int
+    @param max_length: maximum length of an attribute value
+    @rtype: list
+    @return: transcripts that lost an attribute
+    """
+    trimmed = set()
+
+    for feature in features:
+        for key, value in list(feature.attributes.items()):
+            if key in KEY_ATTRIBUTES:
+                continue
+            values = value if isinstance(value, list) else [value]
+            if sum(len(str(x)) for x in values) > max_length:
+                del feature.attributes[key]
+                trimmed.add(feature.transcript_id or feature.attributes.get("ID"))
+
+    return sorted(x for x in trimmed if x)
@@ -0,0 +1,389 @@
+#!/usr/bin/env python3
+
+
+""" A module with helpers to read and format .gtf/.gff lines. """
+
+
+import os
+from urllib.parse import unquote
+from constants import Constants
+from modules.fileio import open_text
+from modules.utils import natural_key
+
+
+__author__ = "Alejandro Gonzales-Irribarren"
+__email__ = "jose.gonzalesdezavala1@unmsm.edu.pe"
+__github__ = "https://github.com/alejandrogzi"
+__version__ = "0.7.0-devel"
+
+
+CODING = ("CDS", "start_codon", "stop_codon")
+TRANSCRIPT = ("transcript", "mRNA")
+# Rank of each feature type within its transcript, others go last
+FEATURE_ORDER = {
+    "gene": 0,
+    "transcript": 1,
+    "mRNA": 1,
+    "exon": 2,
+    "CDS": 3,
+    "five_prime_utr": 4,
+    "three_prime_utr": 5,
+    "start_codon": 6,
+    "stop_codon": 7,
+}
+# Characters GFF3 reserves in attribute values, written percent-encoded
+GFF_ESCAPES = str.maketrans({x: f"%{ord(x):02X}" for x in "%;=&,\t\n\r"})
+# Attributes GFF3 allows several comma-separated values for
+GFF_MULTIPLE = {"Parent", "Alias", "Note", "Dbxref", "Ontology_term", "tag"}
+
+
+class GxfFeature:
+    """A class to represent a single .gtf/.gff feature line."""
+
+    def __init__(self, fields: list, fmt: str) -> None:
+        """
+        Constructs all the necessary attributes for the GxfFeature object.
+
+        @type fields: list
+        @param fields: the 9 tab-separated fields of a .gtf/.gff line
+        @type fmt: str
+        @param fmt: gtf or gff
+        """
+
+        self.chrom = fields[0]
+        self.source = fields[1]
+        self.feature = fields[2]
+        self.start = int(fields[3])
+        self.end = int(fields[4])
+        self.score = fields[5]
+        self.strand = fields[6]
+        self.phase = fields[7]
+        self.fmt = fmt
+        self.attributes = parse_attributes(fields[8], fmt)
+
+    @classmethod
+    def from_line(cls, line: str, fmt: str) -> "GxfFeature":
+        # maxsplit keeps tab-separated attributes in the 9th column
+        return cls(line.rstrip("\r\n").split("\t", 8), fmt)
+
+    @property
+    def transcript_id(self) -> str:
+        """The transcript a feature belongs to, None for gene lines"""
+        if self.feature == "gene":
+            return None
+        if self.fmt == "gtf":
+            return self.attributes.get("transcript_id")
+        if self.feature in TRANSCRIPT:
+            return self.attributes.get("ID")
+        return self.attributes.get("Parent")
+
+    def to_line(self, sep: str = None) -> str:
+        """
+        Formats the feature as a .gtf/.gff line
+
+        @type sep: str
+        @param sep: separator between attributes, the format default if None
+        """
+        if self.fmt == "gtf":
+            attributes = gtf_attributes(self.attributes, " " if sep is None else sep)
+        else:
+            attributes = gff_attributes(self.attributes, "" if sep is None else sep)
+
+        return "\t".join(
+            [
+                self.chrom,
+                self.source,
+                self.feature,
+                str(self.start),
+                str(self.end),
+                self.score,
+                self.strand,
+                self.phase,
+                attributes,
+            ]
+        )
+
+
+def gxf_format(gxf: str, fmt: str = None) -> str:
+    """
+    Returns gtf or gff based on the extension of the file name (dots in
+    directory names are ignored), unless a format is given
+
+    @type gxf: str
+    @param gxf: path to .gtf/.gff file
+    @type fmt: str
+    @param fmt: gtf or gff, bypasses the extension
+    """
+    if fmt:
+        return fmt
+
+    return "gtf" if ".gtf" in os.path.basename(gxf).lower() else "gff"
+
+
+def parse_attributes(column: str, fmt: str) -> dict:
+    """
+    Parses a .gtf (key "value";) or .gff (key=value;) attribute column,
+    decoding percent-encoded .gff values
+
+    @type column: str
+    @param column: the 9th column of a .gtf/.gff line
+    @type fmt: str
+    @param fmt: gtf or gff
+    """
+    attributes = {}
+
+    for field in column.strip().split(";"):
+        field = field.strip()
+        if not field:
+            continue
+        if fmt == "gtf":
+            key, _, value = field.partition(" ")
+            value = value.strip().strip('"')
+        else:
+            key, _, value = field.partition("=")
+            value = unquote(value)
+
+        # Repeated .gtf keys (e.g. tag) are kept as lists
+        if key in attributes:
+            previous = attributes[key]
+            attributes[key] = (
+                previous + [value] if isinstance(previous, list) else [previous, value]
+            )
+        else:
+            attributes[key] = value
+
+    return attributes
+
+
+def add_tag(feature, tag: str) -> None:
+    """
+    Adds an Ensembl-style tag (e.g. cds_end_NF) to a feature, written as
+    repeated `tag` keys in .gtf and as a comma-separated list in .gff
+
+    @type feature: GxfFeature
+    @param feature: feature to tag
+    @type tag: str
+    @param tag: tag name
+    """
+    tags = feature.attributes.get("tag", [])
+    tags = tags if isinstance(tags, list) else [tags]
+
+    if tag not in tags:
+        feature.attributes["tag"] = tags + [tag]
+
+
+def feature_genes(features: list) -> dict:
+    """
+    Returns transcript -> gene for .gtf (gene_id) or .gff (Parent)
+    transcripts
+
+    @type features: list
+    @param features: list of GxfFeature objects
+    """
+    genes = {}
+
+    for feature in features:
+        if feature.fmt == "gtf" and feature.feature != "gene":
+            genes.setdefault(feature.transcript_id, feature.attributes.get("gene_id"))
+        elif feature.feature in TRANSCRIPT:
+            genes[feature.attributes.get("ID")] = feature.attributes.get("Parent")
+
+    return genes
+
+
+def sort_features(features: list, mode: str = "position") -> list:
+    """
+    Sorts .gtf/.gff features in a stable, documented order that keeps
+    every gene with its transcripts and every transcript with its
+    sub-features (gene < transcript < exon < CDS < UTRs < codons, then
+    by start):
+
+        position    genes by contig (natural order) and start, their
+                    transcripts by start, ties by ID
+        transcript  genes by ID, their transcripts by ID
+        none        as given
+
+    @type features: list
+    @param features: list of GxfFeature objects
+    @type mode: str
+    @param mode: one of Constants.SORT_MODES
+    """
+    if mode == "none":
+        return features
+
+    genes = feature_genes(features)
+
+    def ids(feature) -> tuple:
+        if feature.feature == "gene":
+            key = "gene_id" if feature.fmt == "gtf" else "ID"
+            return feature.attributes.get(key) or "", ""
+        tx = (feature.transcript_id or "").split(",")[0]
+        return genes.get(tx) or "", tx
+
+    starts = {}
+    for feature in features:
+        for name in ids(feature):
+            starts[name] = min(starts.get(name, feature.start), feature.start)
+
+    def key(feature) -> tuple:
+        gene, tx = ids(feature)
+        rank = FEATURE_ORDER.get(feature.feature, len(FEATURE_ORDER))
+        if mode == "transcript":
+            return (natural_key(gene), natural_key(tx), rank, feature.start, feature.end)
+        return (
+            natural_key(feature.chrom),
+            starts[gene],
+            gene,
+            starts[tx] if tx else -1,
+            tx,
+            rank,
+            feature.start,
+            feature.end,
+        )
+
+    return sorted(features, key=key)
+
+
+def read_gxf(gxf: str, fmt: str = None):
+    """
+    Yields GxfFeature objects from a .gtf/.gff file, skipping comments
+
+    @type gxf: str
+    @param gxf: path to .gtf/.gff file
+    @type fmt: str
+    @param fmt: gtf or gff, instead of the extension
+    """
+    fmt = gxf_format(gxf, fmt)
+
+    with open_text(gxf) as f:
+        for line in f:
+            if not line.strip() or line.startswith("#"):
+                continue
+            yield GxfFeature.from_line(line, fmt)
+
+
+def write_gxf(
+    features: list,
+    gxf: str,
+    sep: str = None,
+    line_ending: str = "\n",
+    pragmas: list = None,
+    fmt: str = None,
+) -> str:
+    """
+    Writes GxfFeature objects to a .gtf/.gff file
+
+    @type features: list
+    @param features: list of GxfFeature objects
+    @type gxf: str
+    @param gxf: path to .gtf/.gff file
+    @type sep: str
+    @param sep: separator between attributes, the format default if None
+    @type line_ending: str
+    @param line_ending: line terminator (LF or CRLF)
+    @type pragmas: list
+    @param pragmas: `##` lines written after the .gff version header
+    @type fmt: str
+    @param fmt: gtf or gff, instead of the extension
+    """
+    with open(gxf, "w", newline="") as f:
+        if gxf_format(gxf, fmt) == "gff":
+            f.write("##gff-version 3" + line_ending)
+            for pragma in pragmas or []:
+                f.write(pragma + line_ending)
+        for feature in features:
+            f.write(feature.to_line(sep) + line_ending)
+
+    return gxf
+
+
+def gff_value(key: str, value) -> str:
+    """
+    Formats an attribute value for .gff, percent-encoding the characters
+    GFF3 reserves (see GFF_ESCAPES). Lists, and the commas of attributes
+    that take several values (see GFF_MULTIPLE), are written as
+    comma-separated values
+
+    @type key: str
+    @param key: attribute name
+    @type value: str | list
+    @param value: attribute value
+    """
+    if not isinstance(value, list):
+        value = value.split(",") if key in GFF_MULTIPLE else [value]
+
+    return ",".join(x.translate(GFF_ESCAPES) for x in value)
+
+
+def gff_attributes(attributes: dict, sep: str = "") -> str:
+    """
+    Formats a dictionary of attributes as a .gff attribute column
+
+    @type attributes: dict
+    @param attributes: ordered key -> value pairs
+    @type sep: str
+    @param sep: separator after each `;` between attributes
+    """
+    return (
+        f";{sep}".join(
+            f"{key}={gff_value(key, value)}" for key, value in attributes.items()
+        )
+        + ";"
+    )
+
+
+def gtf_attributes(attributes: dict, sep: str = " ") -> str:
+    """
+    Formats a dictionary of attributes as a .gtf attribute column
+
+    @type attributes: dict
+    @param attributes: ordered key -> value pairs
+    @type sep: str
+    @param sep: separator after each `;` between attributes
+    """
+    return sep.join(
+        f'{key} "{value}";'
+        for key, values in attributes.items()
+        for value in (values if isinstance(values, list) else [values])
+    )
+
+
+def gtf_line(
+    chrom: str,
+    feature: str,
+    start: int,
+    end: int,
+    strand: str,
+    attributes: dict,
+    score: str = ".",
+    phase: str = ".",
+) -> str:
+    """
+    Builds a .gtf line from 0-based half-open coordinates
+
+    @type chrom: str
+    @param chrom: chromosome name
+    @type feature: str
+    @param feature: feature type (gene, transcript, exon, ...)
+    @type start: int
+    @param start: 0-based start
+    @type end: int
+    @param end: 0-based exclusive end
+    @type strand: str
+    @param strand: + or -
+    @type attributes: dict
+    @param attributes: ordered key -> value pairs
+    """
+    return "\t".join(
+        [
+            chrom,
+            Constants.SOURCE,
+            feature,
+            str(start + 1),
+            str(end),
+            score,
+            strand,
+            phase,
+            gtf_attributes(attributes),
+        ]
+    )
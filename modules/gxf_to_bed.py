@@ -0,0 +1,166 @@
+#!/usr/bin/env python3
+
+
+"""
+A module to convert .gtf/.gff files back to BED12 and check round trips.
+
+BED12 -> .gtf/.gff -> BED12 is lossless for coordinates, blocks and
+strand. Score and itemRgb have no .gtf/.gff column, so postoga keeps
+them as `bed_score` and `item_rgb` transcript attributes when a round
+trip is requested; without them the canonical form uses 0 and 0,0,0.
+"""
+
+
+import os
+from constants import Constants
+from logger import Log
+from modules.annotation_set import AnnotationSet, Transcript
+from modules.bed import BedRecord, read_bed
+from modules.run_warnings import RunWarnings
+from modules.utils import output_stem
+
+
+__author__ = "Alejandro Gonzales-Irribarren"
+__email__ = "jose.gonzalesdezavala1@unmsm.edu.pe"
+__github__ = "https://github.com/alejandrogzi"
+__version__ = "0.7.0-devel"
+
+
+BED_FIELDS = [
+    "chrom",
+    "start",
+    "end",
+    "name",
+    "score",
+    "strand",
+    "thick",
+    "rgb",
+    "blocks",
+]
+
+
+def to_bed_record(name: str, features: list) -> BedRecord:
+    """
+    Builds a BED12 record from the features of one transcript (see
+    Transcript.from_features)
+
+    @type name: str
+    @param name: transcript name
+    @type features: list
+    @param features: GxfFeature objects of the transcript
+    """
+    return Transcript.from_features(name, features).to_bed()
+
+
+def gxf_to_bed(gxf: str, output: str) -> str:
+    """
+    Converts a .gtf/.gff file to BED12, one line per transcript in
+    order of appearance
+
+    @type gxf: str
+    @param gxf: path to .gtf/.gff file
+    @type output: str
+    @param output: path to the output .bed file
+    """
+    return AnnotationSet.from_file(gxf).write_bed(output)
+
+
+def convert_from_gxf(
+    gxf: str, output: str = None, isoforms: str = None, fmt: str = None
+) -> tuple:
+    """
+    Converts a (possibly edited) .gtf/.gff file, optionally gzipped, to
+    BED12 with blocks from its exons and thickStart/thickEnd from its
+    CDS, mirroring --to gtf/gff; the gene -> transcript pairs can be
+    written as an isoforms file to convert the .bed forward again
+
+    @type gxf: str
+    @param gxf: path to .gtf/.gff file
+    @type output: str
+    @param output: path to the output .bed file (default: <gxf stem>.bed)
+    @type isoforms: str
+    @param isoforms: path to an isoforms file to write, if any
+    @type fmt: str
+    @param fmt: gtf or gff, instead of detecting it
+    @rtype: tuple
+    @return: (path to the .bed file, path to the isoforms file or None)
+    """
+    output = output or f"{output_stem(gxf)}.bed"
+
+    annotation = AnnotationSet.from_file(gxf, fmt=fmt)
+    annotation.write_bed(output)
+
+    if isoforms:
+        with open(isoforms, "w") as f:
+            for transcript in annotation:
+                f.write(f"{transcript.gene}\t{transcript.name}\n")
+
+    return output, isoforms
+
+
+def bed_fields(record: BedRecord) -> dict:
+    """Splits a BedRecord in the fields compared by a round trip"""
+    return dict(
+        zip(
+            BED_FIELDS,
+            [
+                record.chrom,
+                record.start,
+                record.end,
+                record.name,
+                record.score,
+                record.strand,
+                (record.thick_start, record.thick_end),
+                record.rgb,
+                list(zip(record.sizes, record.offsets)),
+            ],
+        )
+    )
+
+
+def verify_round_trip(path: str, bed: str, gxf: str) -> dict:
+    """
+    Converts a .gtf/.gff back to BED12 and compares it with the source
+    .bed, reporting every projection that does not come back identical
+
+    @type path: str
+    @param path: path to the results directory
+    @type bed: str
+    @param bed: path to the source .bed file
+    @type gxf: str
+    @param gxf: path to the converted .gtf/.gff file
+    @rtype: dict
+    @return: projection -> list of lossy fields (missing if not in gxf)
+    """
+
+    log = Log.connect(path, Constants.FileNames.LOG)
+
+    stem = os.path.basename(output_stem(bed))
+    back = gxf_to_bed(
+        gxf, os.path.join(path, f"{stem}.{Constants.FileNames.ROUND_TRIP_BED}")
+    )
+    converted = {record.name: record for record in read_bed(back)}
+    lossy = {}
+
+    for record in read_bed(bed):
+        if record.name not in converted:
+            lossy[record.name] = ["missing"]
+            continue
+        before, after = bed_fields(record), bed_fields(converted[record.name])
+        fields = [x for x in BED_FIELDS if before[x] != after[x]]
+        if fields:
+            lossy[record.name] = fields
+
+    warnings = RunWarnings.connect(path)
+    for field in BED_FIELDS + ["missing"]:
+        warnings.add(
+            "lossy_round_trip",
+            [name for name, fields in lossy.items() if field in fields],
+            f"{field} changed in the BED -> {gxf} -> BED round trip",
+        )
+
+    log.record(
+        f"round trip of {bed} through {gxf}: {len(lossy)} projections not identical, written back to {back}"
+    )
+
+    return lossy
@@ -0,0 +1,158 @@
+#!/usr/bin/env python3
+
+
+"""
+A module to ingest HMMER/DIAMOND hit tables for loss verification.
+
+Hits of the query proteome against the reference are an independent
+check of TOGA loss calls: a Lost gene whose reference protein has a
+strong hit from a query protein projected elsewhere (another locus,
+another gene) may be a mis-projection and is flagged as a rescue
+candidate in the query table.
+"""
+
+
+import pandas as pd
+from constants import Constants
+from logger import Log
+from modules.diff_runs import gene_classes
+from modules.run_warnings import RunWarnings
+
+
+__author__ = "Alejandro Gonzales-Irribarren"
+__email__ = "jose.gonzalesdezavala1@unmsm.edu.pe"
+__github__ = "https://github.com/alejandrogzi"
+__version__ = "0.7.0-devel"
+
+
+BLAST_FIELDS = 12
+HMMER_FIELDS = 18
+
+
+def parse_hit(line: str) -> tuple:
+    """
+    Parses a hit line as (query, target, evalue, bitscore). DIAMOND/BLAST
+    tabular (-outfmt 6) and HMMER --tblout lines are recognized; other
+    lines return None
+
+    @type line: str
+    @param line: a line of a hit table
+    """
+    if not line.strip() or line.startswith("#"):
+        return None
+
+    fields = line.rstrip("\n").split("\t")
+    if len(fields) == BLAST_FIELDS:
+        return fields[0], fields[1], float(fields[10]), float(fields[11])
+
+    fields = line.split()
+    if len(fields) >= HMMER_FIELDS:
+        # HMMER: target name, accession, query name, accession, E-value, score
+        return fields[2], fields[0], float(fields[4]), float(fields[5])
+
+    return None
+
+
+def read_hits(path: str) -> list:
+    """
+    Reads a DIAMOND/BLAST tabular or HMMER --tblout hit table
+
+    @type path: str
+    @param path: path to the hit table
+    @rtype: list
+    @return: (query, target, evalue, bitscore) tuples
+    """
+    with open(path, "r") as f:
+        return [hit for hit in map(parse_hit, f) if hit]
+
+
+def is_hit_table(path: str) -> bool:
+    """Returns True if the file holds DIAMOND/BLAST or HMMER hits"""
+    with open(path, "r") as f:
+        for line in f:
+            if line.strip() and not line.startswith("#"):
+                try:
+                    return parse_hit(line) is not None
+                except ValueError:
+                    return False
+
+    return False
+
+
+def strong_hits(
+    hits: list,
+    max_evalue: float = Constants.MAX_EVALUE,
+    min_bitscore: float = Constants.MIN_BITSCORE,
+) -> list:
+    """Returns the hits passing the e-value and bitscore cutoffs"""
+    return [x for x in hits if x[2] <= max_evalue and x[3] >= min_bitscore]
+
+
+def hit_evidence(path: str) -> dict:
+    """
+    Returns the best e-value of every query and target id of a hit table,
+    to be used as homology evidence
+
+    @type path: str
+    @param path: path to the hit table
+    """
+    evidence = {}
+
+    for query, target, evalue, _ in read_hits(path):
+        for name in (query, target):
+            evidence[name] = min(evidence.get(name, evalue), evalue)
+
+    return evidence
+
+
+def rescue_candidates(
+    path: str,
+    table: pd.DataFrame,
+    hits: str,
+    max_evalue: float = Constants.MAX_EVALUE,
+    min_bitscore: float = Constants.MIN_BITSCORE,
+) -> pd.DataFrame:
+    """
+    Flags Lost genes with strong hits from query proteins projected from
+    other genes, adding a `rescue_candidate` column to the query table
+
+    @type path: str
+    @param path: path to the results directory
+    @type table: pd.DataFrame
+    @param table: query table
+    @type hits: str
+    @param hits: DIAMOND/BLAST tabular or HMMER --tblout file (query vs reference)
+    @type max_evalue: float
+    @param max_evalue: maximum e-value of a strong hit
+    @type min_bitscore: float
+    @param min_bitscore: minimum bitscore of a strong hit
+    """
+
+    log = Log.connect(path, Constants.FileNames.LOG)
+
+    # Reference transcripts/genes and query projections -> reference gene
+    to_gene = {}
+    for column in ("t_gene", "helper"):
+        to_gene.update(zip(table[column], table["t_gene"]))
+    projected_from = dict(zip(table["transcripts"], table["t_gene"]))
+
+    lost = {gene for gene, cls in gene_classes(table).items() if cls == "L"}
+
+    candidates = set()
+    for query, target, _, _ in strong_hits(read_hits(hits), max_evalue, min_bitscore):
+        gene = to_gene.get(target, to_gene.get(target.rsplit(".", 1)[0]))
+        if gene in lost and projected_from.get(query) != gene:
+            candidates.add(gene)
+
+    table["rescue_candidate"] = table["t_gene"].isin(candidates)
+
+    RunWarnings.connect(path).add(
+        "rescue_candidate",
+        sorted(candidates),
+        "Lost gene with a strong homology hit elsewhere in the assembly (possible mis-projection)",
+    )
+    log.record(
+        f"{len(candidates)} of {len(lost)} Lost genes flagged as rescue candidates from {hits}"
+    )
+
+    return table
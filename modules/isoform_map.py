@@ -0,0 +1,291 @@
+#!/usr/bin/env python3
+
+
+"""
+A module with a two-way gene <-> transcript map built from an isoforms file.
+
+The map is read once per isoforms file and shared by every postoga
+step (conversion, union models, quantification, consequences) instead
+of re-reading the file in each of them.
+"""
+
+
+import logging
+import os
+import re
+from collections import defaultdict
+from constants import Constants
+from modules.fileio import open_text
+from modules.errors import PostogaKeyError, PostogaValueError
+from modules.projection_index import ProjectionIndex
+
+
+__author__ = "Alejandro Gonzales-Irribarren"
+__email__ = "jose.gonzalesdezavala1@unmsm.edu.pe"
+__github__ = "https://github.com/alejandrogzi"
+__version__ = "0.7.0-devel"
+
+
+# Non-empty gene<TAB>transcript lines only, the last newline optional
+PLAIN = re.compile(r"(?:[^\t\n\r#]+\t[^\t\n\r#]+\n)*[^\t\n\r#]+\t[^\t\n\r#]+\n?")
+
+# Column names seen in the header of isoforms files
+HEADER_NAMES = {
+    "gene",
+    "gene_id",
+    "geneid",
+    "gene_name",
+    "t_gene",
+    "transcript",
+    "transcript_id",
+    "transcriptid",
+    "projection",
+    "projection_id",
+    "isoform",
+    "q_transcript",
+}
+
+
+def fragment_base(transcript: str) -> str:
+    """
+    Returns a transcript name without its fragment suffix (e.g.
+    ENST00000355624.10 for ENST00000355624.10.-1 or ENST00000355624.10-1),
+    the name itself if it has none
+
+    @type transcript: str
+    @param transcript: transcript name
+    """
+    if not transcript.endswith(Constants.FRAGMENT_SUFFIX):
+        return transcript
+
+    return transcript[: -len(Constants.FRAGMENT_SUFFIX)].rstrip(".")
+
+
+def is_header(row: list) -> bool:
+    """
+    Returns True if the fields of a line are column names, not a pair
+
+    @type row: list
+    @param row: (gene, transcript) fields of the line
+    """
+    return any(x.strip().lstrip("#").lower() in HEADER_NAMES for x in row)
+
+
+def plain_columns(text: str) -> tuple:
+    """
+    Splits a plain two-column file (one tab per line, no comments, blank
+    lines or carriage returns) into its gene and transcript columns in a
+    single pass over the whole text, None if the file is not plain and
+    needs to be parsed line by line
+
+    @type text: str
+    @param text: contents of the isoforms file
+    """
+    if not PLAIN.fullmatch(text):
+        return None
+
+    fields = text.rstrip("\n").replace("\n", "\t").split("\t")
+
+    return fields[0::2], fields[1::2]
+
+
+class IsoformMap:
+    """A class to query transcript -> gene and gene -> transcripts pairs."""
+
+    # Maps loaded from disk, keyed by real path and invalidated on change
+    LOADED = {}
+
+    def __init__(self, pairs: list = ()) -> None:
+        """
+        Constructs all the necessary attributes for the IsoformMap object.
+
+        @type pairs: list
+        @param pairs: (gene, transcript) pairs, a transcript listed under
+            two genes maps to its last gene but is only listed under its
+            first one in gene2tx
+        """
+
+        pairs = list(pairs)
+        self.columns = [x[0] for x in pairs], [x[1] for x in pairs]
+        self.tx2gene = dict(zip(self.columns[1], self.columns[0]))
+        self.by_gene = None
+        self.index = None
+
+    @classmethod
+    def from_columns(cls, genes: list, transcripts: list) -> "IsoformMap":
+        """
+        Builds a map from parallel gene and transcript columns in bulk
+
+        @type genes: list
+        @param genes: gene of each transcript
+        @type transcripts: list
+        @param transcripts: transcript names
+        """
+        isoforms = cls()
+        isoforms.columns = genes, transcripts
+        isoforms.tx2gene = dict(zip(transcripts, genes))
+
+        return isoforms
+
+    @property
+    def gene2tx(self) -> dict:
+        """
+        Returns the gene -> transcripts lists, built on first use from the
+        columns the map was read from. Transcripts listed twice stay under
+        their first gene
+        """
+        if self.by_gene is None:
+            genes, transcripts = self.columns
+            first = self.tx2gene
+            if len(transcripts) != len(first):
+                # Repeated transcripts: keep the first gene of each one
+                first = dict(zip(reversed(transcripts), reversed(genes)))
+                first = {tx: first[tx] for tx in dict.fromkeys(transcripts)}
+
+            self.by_gene = defaultdict(list)
+            for transcript, gene in first.items():
+                self.by_gene[gene].append(transcript)
+            self.columns = None
+
+        return self.by_gene
+
+    @classmethod
+    def from_file(cls, isoforms: str) -> "IsoformMap":
+        """
+        Reads a gene-to-projection isoforms file (gene, transcript),
+        optionally gzipped or bgzipped. A header line (e.g.
+        `gene_id<TAB>transcript_id`) or `#` comments are skipped with a
+        warning; a file without any pair is an error. Plain files are
+        split in one pass (see plain_columns), others line by line
+
+        @type isoforms: str
+        @param isoforms: path to the isoforms file
+        """
+        with open_text(isoforms) as f:
+            text = f.read()
+
+        columns = plain_columns(text)
+        if columns is not None:
+            genes, transcripts = columns
+            skipped = int(is_header([genes[0], transcripts[0]]))
+            genes, transcripts = genes[skipped:], transcripts[skipped:]
+            malformed = []
+        else:
+            rows = [line.split("\t")[:2] for line in text.splitlines() if line.strip()]
+            comments = {
+                i
+                for i, row in enumerate(rows)
+                if row[0].startswith("#") or (i == 0 and is_header(row))
+            }
+            skipped = len(comments)
+            pairs = [row for i, row in enumerate(rows) if i not in comments]
+            malformed = [row for row in pairs if len(row) < 2]
+            genes = [row[0] for row in pairs if len(row) == 2]
+            transcripts = [row[1] for row in pairs if len(row) == 2]
+
+        if skipped:
+            logging.warning(
+                f"skipped {skipped} header/comment lines in isoforms file {isoforms}"
+            )
+        if malformed:
+            logging.warning(
+                f"skipped {len(malformed)} lines without a gene-transcript pair "
+                f"in isoforms file {isoforms}"
+            )
+
+        if not transcripts:
+            logging.error(f"isoforms file {isoforms} has no gene-transcript pairs")
+            raise PostogaValueError(
+                f"isoforms file {isoforms} has no gene-transcript pairs", isoforms
+            )
+
+        return cls.from_columns(genes, transcripts)
+
+    @classmethod
+    def load(cls, isoforms) -> "IsoformMap":
+        """
+        Returns the map of an isoforms file, reading it only once per
+        process. An IsoformMap is returned as is
+
+        @type isoforms: str | IsoformMap
+        @param isoforms: path to the isoforms file or an IsoformMap
+        """
+        if isinstance(isoforms, cls):
+            return isoforms
+
+        path = os.path.realpath(isoforms)
+        stamp = os.stat(path).st_mtime_ns
+        cached = cls.LOADED.get(path)
+
+        if cached is None or cached[0] != stamp:
+            cached = cls.LOADED[path] = (stamp, cls.from_file(path))
+
+        return cached[1]
+
+    def __contains__(self, name: str) -> bool:
+        return name in self.tx2gene or name in self.gene2tx
+
+    def __len__(self) -> int:
+        return len(self.tx2gene)
+
+    def resolve(self, transcript: str) -> str:
+        """
+        Returns the gene of a transcript, None if it has none. Fragmented
+        transcripts (see Constants.FRAGMENT_SUFFIX) are matched without
+        the suffix, then by their reference transcript (any projection
+        sharing it)
+
+        @type transcript: str
+        @param transcript: transcript name
+        """
+        if transcript in self.tx2gene:
+            return self.tx2gene[transcript]
+
+        base = fragment_base(transcript)
+        if base == transcript:
+            return None
+        if base in self.tx2gene:
+            return self.tx2gene[base]
+
+        if self.index is None:
+            self.index = ProjectionIndex(self.tx2gene)
+
+        projections = self.index.projections_of(base)
+        return self.tx2gene[projections[0]] if projections else None
+
+    def get(self, transcript: str, default: str = None) -> str:
+        gene = self.resolve(transcript)
+        return default if gene is None else gene
+
+    def gene(self, transcript: str) -> str:
+        gene = self.resolve(transcript)
+        if gene is None:
+            raise PostogaKeyError(
+                f"{transcript} has no gene in the isoforms", transcript
+            )
+        return gene
+
+    def transcripts(self, gene: str) -> list:
+        return list(self.gene2tx.get(gene, []))
+
+    def genes(self) -> list:
+        return list(self.gene2tx)
+
+    def subset(self, transcripts=None, genes=None) -> "IsoformMap":
+        """
+        Returns a new map restricted to some transcripts and/or genes
+
+        @type transcripts: iterable
+        @param transcripts: transcripts to keep
+        @type genes: iterable
+        @param genes: genes to keep
+        """
+        transcripts = set(transcripts) if transcripts is not None else None
+        genes = set(genes) if genes is not None else None
+
+        return IsoformMap(
+            (gene, tx)
+            for tx, gene in self.tx2gene.items()
+            if (transcripts is None or tx in transcripts)
+            and (genes is None or gene in genes)
+        )
@@ -0,0 +1,227 @@
+#!/usr/bin/env python3
+
+
+"""
+A module to export junction and variant peptides for proteogenomics.
+
+Peptides spanning each exon-exon junction of the coding projections,
+and (with a .vcf) the stretches of protein changed by coding variants,
+are written to a .fasta that can be appended to a mass-spec search
+database. Junctions come from the query models and sequences from the
+query genome.
+"""
+
+
+import os
+from collections import defaultdict
+from constants import Constants
+from logger import Log
+from modules.bed import read_bed
+from modules.consequences import cds_offset, read_vcf
+from modules.fasta import (
+    codon_table,
+    genetic_code,
+    reverse_complement,
+    translate,
+    write_fasta,
+)
+from modules.isoform_map import IsoformMap
+from modules.seq_store import SeqStore
+
+
+__author__ = "Alejandro Gonzales-Irribarren"
+__email__ = "jose.gonzalesdezavala1@unmsm.edu.pe"
+__github__ = "https://github.com/alejandrogzi"
+__version__ = "0.7.0-devel"
+
+
+MIN_PEPTIDE = 7
+
+
+def junction_offsets(record) -> list:
+    """
+    Returns the CDS offsets (transcript orientation) of the first base
+    after each exon-exon junction
+
+    @type record: BedRecord
+    @param record: projection
+    """
+    sizes = [end - start for start, end in record.cds()]
+    sizes = sizes if record.strand == "+" else sizes[::-1]
+    offsets, total = [], 0
+
+    for size in sizes[:-1]:
+        total += size
+        offsets.append(total)
+
+    return offsets
+
+
+def window(protein: str, left: int, right: int, flank: int, to_stop: bool = False):
+    """
+    Returns the peptide from flank residues before left to flank residues
+    after right (or the first stop, with to_stop), never crossing a stop
+
+    @type protein: str
+    @param protein: translated sequence
+    @type left: int
+    @param left: first residue that must be included
+    @type right: int
+    @param right: last residue that must be included
+    @type flank: int
+    @param flank: residues added on each side
+    """
+    start = max(0, left - flank + 1)
+    end = len(protein) if to_stop else min(len(protein), right + flank)
+
+    stop = protein.rfind("*", start, left + 1)
+    start = stop + 1 if stop >= 0 else start
+    stop = protein.find("*", right, end)
+    end = stop if stop >= 0 else end
+
+    peptide = protein[start:end]
+
+    return peptide if len(peptide) >= MIN_PEPTIDE and end > right else None
+
+
+def junction_peptides(record, protein: str, flank: int) -> list:
+    """
+    Returns (label, peptide) pairs spanning the junctions of a projection
+
+    @type record: BedRecord
+    @param record: projection
+    @type protein: str
+    @param protein: translated CDS of the projection
+    @type flank: int
+    @param flank: residues on each side of the junction
+    """
+    peptides = []
+
+    for i, offset in enumerate(junction_offsets(record)):
+        peptide = window(protein, (offset - 1) // 3, offset // 3, flank)
+        if peptide:
+            peptides.append((f"junction_{i + 1}", peptide))
+
+    return peptides
+
+
+def variant_peptide(
+    record, cds: str, protein: str, variant: tuple, table: dict, flank: int
+) -> tuple:
+    """
+    Returns the (label, peptide) changed by a coding variant, running to
+    the next stop for frameshifts, None if the protein does not change
+
+    @type record: BedRecord
+    @param record: projection
+    @type cds: str
+    @param cds: spliced CDS of the projection
+    @type protein: str
+    @param protein: translated CDS
+    @type variant: tuple
+    @param variant: (chrom, pos, ref, alt), pos 0-based
+    @type table: dict
+    @param table: codon -> amino acid dictionary
+    """
+    chrom, pos, ref, alt = variant
+    first = cds_offset(record, pos)
+    last = cds_offset(record, pos + len(ref) - 1)
+    if first is None or last is None or abs(last - first) != len(ref) - 1:
+        return None
+
+    allele, expected = alt, ref
+    if record.strand == "-":
+        first, allele, expected = last, reverse_complement(alt), reverse_complement(ref)
+    if cds[first : first + len(ref)] != expected:
+        return None
+
+    mutated = translate(cds[:first] + allele + cds[first + len(ref) :], table)
+    diff = next(
+        (i for i, (a, b) in enumerate(zip(protein, mutated)) if a != b),
+        None,
+    )
+    if diff is None:
+        return None
+
+    frameshift = (len(alt) - len(ref)) % 3 != 0
+    right = diff + max(len(alt) // 3, 1) - 1
+    peptide = window(mutated, diff, right, flank, to_stop=frameshift)
+
+    return (f"{chrom}:{pos + 1}{ref}>{alt}", peptide) if peptide else None
+
+
+def export_peptides(
+    path: str,
+    bed: str,
+    genome: str,
+    isoforms: str,
+    flank: int = Constants.PEPTIDE_FLANK,
+    vcf: str = None,
+    mt_contigs: set = None,
+) -> str:
+    """
+    Writes junction (and variant) peptides of the coding projections
+
+    @type path: str
+    @param path: path to the results directory
+    @type bed: str
+    @param bed: path to .bed file
+    @type genome: str
+    @param genome: path to the query genome (.fasta or .2bit)
+    @type isoforms: str
+    @param isoforms: path to the isoforms file
+    @type flank: int
+    @param flank: residues on each side of a junction/variant
+    @type vcf: str
+    @param vcf: optional .vcf with variants to add variant peptides
+    @type mt_contigs: set
+    @param mt_contigs: extra contig names to treat as mitochondrial
+    """
+
+    log = Log.connect(path, Constants.FileNames.LOG)
+
+    genes = IsoformMap.load(isoforms)
+    records = [x for x in read_bed(bed) if x.thick_start < x.thick_end]
+
+    variants = defaultdict(list)
+    for variant in read_vcf(vcf) if vcf else []:
+        variants[variant[0]].append(variant)
+
+    seen, counts = set(), defaultdict(int)
+    f = os.path.join(path, Constants.FileNames.JUNCTION_PEPTIDES)
+
+    with SeqStore(genome, bed) as store, open(f, "w") as out:
+        for record in records:
+            if record.chrom not in store:
+                continue
+            table = codon_table(genetic_code(record.chrom, mt_contigs))
+            cds = store.get(record.name, cds=True).upper()
+            protein = translate(cds, table)
+
+            peptides = [
+                ("junction", x) for x in junction_peptides(record, protein, flank)
+            ]
+            for variant in variants.get(record.chrom, []):
+                if record.thick_start <= variant[1] < record.thick_end:
+                    peptide = variant_peptide(
+                        record, cds, protein, variant, table, flank
+                    )
+                    if peptide:
+                        peptides.append(("variant", peptide))
+
+            gene = genes.get(record.name, record.name)
+            for kind, (label, peptide) in peptides:
+                if peptide in seen:
+                    counts["duplicated"] += 1
+                    continue
+                seen.add(peptide)
+                counts[kind] += 1
+                write_fasta(out, f"{record.name}|{label}|{gene}", peptide)
+
+    info = [
+        f"peptides of {len(records)} coding projections (flank: {flank} aa): {dict(counts)}",
+        f"junction/variant peptides written to {f}",
+    ]
+    [log.record(i) for i in info]
+
+    return f
@@ -0,0 +1,156 @@
+#!/usr/bin/env python3
+
+
+"""
+A module to extract and score the Kozak context of each start codon.
+
+The context spans 6 bases upstream to 1 base downstream of the start
+codon (gccRccATGG). Upstream bases come from the 5' UTR and, when the
+model has none, from the genome upstream of the projection. The score
+is a weighted match to the consensus: -3 (purine) and +4 (G) weigh
+most. Rescued starts with weak contexts are listed as warnings.
+"""
+
+
+import os
+import pandas as pd
+from constants import Constants
+from logger import Log
+from modules.bed import read_bed
+from modules.run_warnings import RunWarnings
+from modules.seq_store import SeqStore
+
+
+__author__ = "Alejandro Gonzales-Irribarren"
+__email__ = "jose.gonzalesdezavala1@unmsm.edu.pe"
+__github__ = "https://github.com/alejandrogzi"
+__version__ = "0.7.0-devel"
+
+
+UPSTREAM = 6
+# Consensus bases (as allowed sets) and weights at -6..-1 and +4
+KOZAK = [("G", 1), ("C", 1), ("C", 1), ("AG", 3), ("C", 1), ("C", 1), ("G", 2)]
+
+
+def kozak_context(store: SeqStore, record) -> str:
+    """
+    Returns the -6..+4 context of the start codon of a coding projection
+    (10 bases, start codon included), in transcript orientation
+
+    @type store: SeqStore
+    @param store: opened query genome with the projections loaded
+    @type record: BedRecord
+    @param record: coding projection
+    """
+    exons = store.get(record.name).upper()
+    cds = store.get(record.name, cds=True).upper()
+
+    # 5' UTR length in transcript orientation
+    if record.strand == "+":
+        utr = sum(
+            min(e, record.thick_start) - s
+            for s, e in record.exons()
+            if s < record.thick_start
+        )
+    else:
+        utr = sum(
+            e - max(s, record.thick_end)
+            for s, e in record.exons()
+            if e > record.thick_end
+        )
+
+    missing = max(UPSTREAM - utr, 0)
+    extra = ""
+    if missing:
+        if record.strand == "+":
+            extra = store.get_region(record.chrom, record.start - missing, record.start)
+        else:
+            extra = store.get_region(
+                record.chrom, record.end, record.end + missing, "-"
+            )
+
+    upstream = (extra.upper() + exons[:utr])[-UPSTREAM:]
+
+    return upstream + cds[:4]
+
+
+def kozak_score(context: str) -> tuple:
+    """
+    Scores a -6..+4 context against the Kozak consensus
+
+    @type context: str
+    @param context: 10 bases, start codon at positions 6..8
+    @rtype: tuple
+    @return: (score in 0-1, strong/adequate/weak)
+    """
+    bases = context[:UPSTREAM].rjust(UPSTREAM, "N") + context[UPSTREAM + 3 :][:1]
+    total = sum(weight for _, weight in KOZAK)
+    score = sum(w for base, (allowed, w) in zip(bases, KOZAK) if base in allowed)
+
+    key = [bases[3] in "AG", bases[-1:] == "G"]
+    strength = "strong" if all(key) else "adequate" if any(key) else "weak"
+
+    return round(score / total, 4), strength
+
+
+def kozak_scores(
+    path: str, bed: str, table: pd.DataFrame, genome: str
+) -> pd.DataFrame:
+    """
+    Adds the kozak_context, kozak_score and kozak_strength columns to the
+    query table, flagging rescued starts with a weak context
+
+    @type path: str
+    @param path: path to the results directory
+    @type bed: str
+    @param bed: path to .bed file (after start rescue, if any)
+    @type table: pd.DataFrame
+    @param table: query table
+    @type genome: str
+    @param genome: path to the query genome (.fasta or .2bit)
+    """
+
+    log = Log.connect(path, Constants.FileNames.LOG)
+
+    toga = os.path.join(path, Constants.FileNames.BED)
+    starts = {}
+    if os.path.abspath(bed) != os.path.abspath(toga):
+        starts = {
+            x.name: x.thick_start if x.strand == "+" else x.thick_end
+            for x in read_bed(toga)
+        }
+
+    contexts, scores, strengths, weak_rescued = {}, {}, {}, []
+    with SeqStore(genome, bed) as store:
+        for record in store.records.values():
+            if record.thick_start >= record.thick_end or record.chrom not in store:
+                continue
+            context = kozak_context(store, record)
+            score, strength = kozak_score(context)
+            contexts[record.name], scores[record.name] = context, score
+            strengths[record.name] = strength
+
+            start = record.thick_start if record.strand == "+" else record.thick_end
+            if strength == "weak" and starts.get(record.name, start) != start:
+                weak_rescued.append(record.name)
+
+    table["kozak_context"] = table["transcripts"].map(contexts)
+    table["kozak_score"] = table["transcripts"].map(scores)
+    table["kozak_strength"] = table["transcripts"].map(strengths)
+
+    RunWarnings.connect(path).add(
+        "weak_kozak_rescued_start",
+        weak_rescued,
+        "rescued start codon in a weak Kozak context",
+    )
+
+    counts = {
+        x: list(strengths.values()).count(x) for x in ("strong", "adequate", "weak")
+    }
+    info = [
+        f"Kozak context scored for {len(contexts)} coding projections",
+        f"Kozak strength stats: {counts}",
+    ]
+    [log.record(i) for i in info]
+
+    return table
@@ -7,6 +7,9 @@ import os
 import pandas as pd
 from constants import Constants
 from logger import Log
+from modules.isoform_map import fragment_base
+from modules.table_schema import schema_stamp
+from modules.table_writer import write_table
 
 
 __author__ = "Alejandro Gonzales-Irribarren"
@@ -15,6 +18,18 @@ __github__ = "https://github.com/alejandrogzi"
 __version__ = "0.6.0-devel"
 
 
+def join_key(projection: str) -> str:
+    """
+    Returns the key a projection is joined on across TOGA tables, so a
+    fragmented projection matches whether a table writes it as
+    ENST00000355624.10.-1 or ENST00000355624.10-1
+
+    @type projection: str
+    @param projection: projection name
+    """
+    return fragment_base(projection)
+
+
 def query_table(path: str) -> pd.DataFrame:
     """
     Return a pandas DataFrame with all projections and metadata.
@@ -56,14 +71,18 @@ def query_table(path: str) -> pd.DataFrame:
     score["transcripts"] = score["gene"] + "." + score["chain"].astype(str)
     score = score[["transcripts", "pred"]]
 
-    table = pd.merge(ortho_x_loss, score, left_on="transcript", right_on="transcripts")
+    # Fragmented projections are spelled differently across tables
+    ortho_x_loss["key"] = ortho_x_loss["transcript"].map(join_key, na_action="ignore")
+    score["key"] = score["transcripts"].map(join_key)
+    table = pd.merge(ortho_x_loss, score, on="key")
 
     # Create a new column with a rename orthology relationship
     table["relation"] = table["orthology_class"].map(Constants.ORTHOLOGY_TYPE)
     table["t_gene"].fillna(table["helper"].map(isoforms_dict), inplace=True)
 
-    # Merge quality data
-    table = pd.merge(table, quality, left_on="transcripts", right_on="Projection_ID")
+    # Merge quality data, keeping projections TOGA did not grade
+    quality["key"] = quality["Projection_ID"].map(join_key)
+    table = pd.merge(table, quality.drop_duplicates("key"), on="key", how="left")
 
     table = table[
         [
@@ -88,3 +107,127 @@ def query_table(path: str) -> pd.DataFrame:
     [log.record(i) for i in info]
 
     return table
+
+
+def orthology_relations(table: pd.DataFrame) -> dict:
+    """
+    Returns a projection -> orthology relation (o2o, o2m, ...) dictionary
+
+    @type table: pd.DataFrame
+    @param table: query table
+    """
+    table = table.dropna(subset=["relation"])
+
+    return dict(zip(table["transcripts"], table["relation"]))
+
+
+def projection_classes(table: pd.DataFrame) -> dict:
+    """
+    Returns a projection -> loss class dictionary
+
+    @type table: pd.DataFrame
+    @param table: query table
+    """
+    table = table.dropna(subset=["class"])
+
+    return dict(zip(table["transcripts"], table["class"]))
+
+
+def projection_chain(projection: str) -> str:
+    """
+    Returns the chain id of a projection (the suffix after the last dot,
+    e.g. 5 in ENST00000355624.10.5)
+
+    @type projection: str
+    @param projection: projection name
+    """
+    return projection.rsplit(".", 1)[-1]
+
+
+def projection_chains(table: pd.DataFrame) -> dict:
+    """
+    Returns a projection -> chain id dictionary
+
+    @type table: pd.DataFrame
+    @param table: query table
+    """
+    return {x: projection_chain(x) for x in table["transcripts"].dropna()}
+
+
+def which_chain(table: pd.DataFrame, transcript: str) -> list:
+    """
+    Returns the chain ids a transcript was projected through. Accepts
+    a projection name or a reference transcript (all its projections)
+
+    @type table: pd.DataFrame
+    @param table: query table
+    @type transcript: str
+    @param transcript: projection or reference transcript name
+    """
+    projections = table["transcripts"]
+    hits = table[
+        (projections == transcript)
+        | (projections.str.rsplit(".", n=1).str[0] == transcript)
+    ]
+
+    return sorted({projection_chain(x) for x in hits["transcripts"]})
+
+
+def chains_for_gene(table: pd.DataFrame, gene: str) -> dict:
+    """
+    Returns the projection -> chain id pairs of a reference gene
+
+    @type table: pd.DataFrame
+    @param table: query table
+    @type gene: str
+    @param gene: reference gene name (t_gene)
+    """
+    hits = table[table["t_gene"] == gene]
+
+    return {x: projection_chain(x) for x in hits["transcripts"]}
+
+
+def build_query_table(
+    path: str, select: list = None, decimals: int = 4
+) -> str:
+    """
+    Builds the query table of a TOGA results directory and writes it,
+    returning the path of the .tsv
+
+    @type path: str
+    @param path: path to the results directory
+    @type select: list
+    @param select: columns to write and their order
+    @type decimals: int
+    @param decimals: decimals for floats (e.g. pred)
+    """
+    return write_query_table(path, query_table(path), select, decimals)
+
+
+def write_query_table(
+    path: str, table: pd.DataFrame, select: list = None, decimals: int = 4
+) -> str:
+    """
+    Writes the query table as a .tsv (see table_writer.write_table),
+    stamped with its schema version (see table_schema)
+
+    @type path: str
+    @param path: path to the results directory
+    @type table: pd.DataFrame
+    @param table: query table
+    @type select: list
+    @param select: columns to write and their order
+    @type decimals: int
+    @param decimals: decimals for floats (e.g. pred)
+    """
+
+    log = Log.connect(path, Constants.FileNames.LOG)
+
+    f = os.path.join(path, Constants.FileNames.QUERY_TABLE)
+    write_table(
+        table, f, select=select, decimals=decimals, comments=[schema_stamp()]
+    )
+
+    log.record(f"query table with {len(table)} projections written to {f}")
+
+    return f
@@ -0,0 +1,112 @@
+#!/usr/bin/env python3
+
+
+"""
+A module to list reference transcripts and genes TOGA never projected.
+
+A reference transcript with zero projections in the query annotation
+is a pipeline dropout (no chain, filtered before classification), not
+a loss call. These are reported apart from "Lost" so both cases are
+not mixed up in downstream analyses.
+"""
+
+
+import os
+from constants import Constants
+from logger import Log
+from modules.bed import read_bed
+from modules.run_warnings import RunWarnings
+from modules.table_writer import write_table
+
+
+__author__ = "Alejandro Gonzales-Irribarren"
+__email__ = "jose.gonzalesdezavala1@unmsm.edu.pe"
+__github__ = "https://github.com/alejandrogzi"
+__version__ = "0.7.0-devel"
+
+
+def reference_isoforms(isoforms: str) -> list:
+    """
+    Reads the reference (gene, transcript) pairs given to TOGA
+
+    @type isoforms: str
+    @param isoforms: path to the reference isoforms file
+    """
+    with open(isoforms, "r") as f:
+        pairs = [line.rstrip("\n").split("\t")[:2] for line in f if line.strip()]
+
+    return [x for x in pairs if len(x) == 2 and x[1] != "Transcript stable ID"]
+
+
+def projected_transcripts(path: str) -> set:
+    """
+    Returns the reference transcripts with at least one projection,
+    from the query annotation and the loss summary
+
+    @type path: str
+    @param path: path to the TOGA results directory
+    """
+    projected = {
+        record.name.rsplit(".", 1)[0]
+        for record in read_bed(os.path.join(path, Constants.FileNames.BED))
+    }
+
+    with open(os.path.join(path, Constants.FileNames.CLASS), "r") as f:
+        for line in f:
+            fields = line.split("\t")
+            if fields[0] == "PROJECTION" and len(fields) > 1:
+                projected.add(fields[1].rsplit(".", 1)[0])
+
+    return projected
+
+
+def never_projected(path: str, isoforms: str = None) -> str:
+    """
+    Writes the reference transcripts with zero projections, marking the
+    genes none of whose transcripts were projected
+
+    @type path: str
+    @param path: path to the TOGA results directory
+    @type isoforms: str
+    @param isoforms: reference isoforms (default: the one in the TOGA directory)
+    @rtype: str
+    @return: path to the never_projected.tsv table
+    """
+
+    log = Log.connect(path, Constants.FileNames.LOG)
+
+    pairs = reference_isoforms(
+        isoforms or os.path.join(path, Constants.FileNames.ISOFORMS)
+    )
+    projected = projected_transcripts(path)
+
+    genes = {}
+    for gene, transcript in pairs:
+        genes.setdefault(gene, []).append(transcript)
+
+    missing_genes = {
+        gene for gene, txs in genes.items() if not any(x in projected for x in txs)
+    }
+    rows = [
+        [gene, transcript, "gene" if gene in missing_genes else "transcript"]
+        for gene, transcript in pairs
+        if transcript not in projected
+    ]
+
+    f = os.path.join(path, Constants.FileNames.NEVER_PROJECTED)
+    write_table(rows, f, ["gene", "transcript", "level"])
+
+    RunWarnings.connect(path).add(
+        "never_projected",
+        sorted(missing_genes),
+        "reference gene without projections (pipeline dropout, not a loss call)",
+    )
+
+    info = [
+        f"{len(rows)} of {len(pairs)} reference transcripts were never projected",
+        f"{len(missing_genes)} of {len(genes)} reference genes have no projection",
+        f"unprojected reference transcripts written to {f}",
+    ]
+    [log.record(i) for i in info]
+
+    return f
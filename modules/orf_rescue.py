@@ -0,0 +1,181 @@
+#!/usr/bin/env python3
+
+
+"""
+A module to assign a CDS to exon-only projections of coding genes.
+
+Projections emitted without a CDS (thickStart == thickEnd) whose
+reference transcript is coding are searched for their longest ORF over
+the spliced query sequence. The ORF becomes the CDS when it is at least
+a fraction of the reference protein length (from TOGA's protein .fasta);
+the projection is flagged with `cds_inferred` in the query table and
+listed as a run warning, so inferred CDS are never mistaken for
+projected ones.
+"""
+
+
+import os
+import pandas as pd
+from constants import Constants
+from logger import Log
+from modules.bed import read_bed
+from modules.extract_seqs import parse_toga_header
+from modules.fasta import codon_table, fasta_to_dict, genetic_code, read_fasta, splice
+from modules.rank_projections import reference_transcript
+from modules.run_warnings import RunWarnings
+from modules.utils import output_stem
+
+
+__author__ = "Alejandro Gonzales-Irribarren"
+__email__ = "jose.gonzalesdezavala1@unmsm.edu.pe"
+__github__ = "https://github.com/alejandrogzi"
+__version__ = "0.7.0-devel"
+
+
+def reference_lengths(protein: str) -> dict:
+    """
+    Returns reference transcript -> protein length (gaps and stops
+    removed) from a TOGA protein .fasta, so every projection of a
+    reference transcript finds it, coding or not
+
+    @type protein: str
+    @param protein: path to TOGA's protein .fasta
+    """
+    lengths = {}
+
+    for header, seq in read_fasta(protein):
+        name, source = parse_toga_header(header)
+        if source == "REFERENCE":
+            lengths[reference_transcript(name)] = len(seq.replace("-", "").rstrip("*"))
+
+    return lengths
+
+
+def longest_orf(seq: str, code: str = "standard") -> tuple:
+    """
+    Returns the (start, end) of the longest start-to-stop ORF of a spliced
+    sequence in transcript orientation, end past the stop codon; None if
+    there is no complete ORF
+
+    @type seq: str
+    @param seq: spliced transcript sequence
+    @type code: str
+    @param code: genetic code (see Constants.GENETIC_CODES)
+    """
+    seq = seq.upper()
+    table = codon_table(code)
+    starts = set(Constants.START_CODONS.get(code, ["ATG"]))
+    best = None
+
+    for frame in range(3):
+        start = None
+        for i in range(frame, len(seq) - 2, 3):
+            codon = seq[i : i + 3]
+            if start is None and codon in starts:
+                start = i
+            elif start is not None and table.get(codon) == "*":
+                if best is None or i + 3 - start > best[1] - best[0]:
+                    best = (start, i + 3)
+                start = None
+
+    return best
+
+
+def transcript_position(record, offset: int) -> int:
+    """
+    Returns the 0-based genomic position of an offset in the spliced
+    exons counted in transcript orientation
+
+    @type record: BedRecord
+    @param record: projection
+    @type offset: int
+    @param offset: offset within the spliced exons
+    """
+    blocks = record.exons() if record.strand == "+" else record.exons()[::-1]
+
+    for start, end in blocks:
+        if offset < end - start:
+            return start + offset if record.strand == "+" else end - 1 - offset
+        offset -= end - start
+
+    raise IndexError(f"offset out of the exons of {record.name}")
+
+
+def rescue_orfs(
+    path: str,
+    bed: str,
+    table: pd.DataFrame,
+    genome: str,
+    min_fraction: float = None,
+    mt_contigs: set = None,
+) -> tuple:
+    """
+    Writes a copy of a .bed file where exon-only projections of coding
+    reference transcripts get their longest ORF as CDS, when it is long
+    enough compared to the reference protein
+
+    @type path: str
+    @param path: path to the results directory
+    @type bed: str
+    @param bed: path to .bed file
+    @type table: pd.DataFrame
+    @param table: query table
+    @type genome: str
+    @param genome: path to the query genome .fasta
+    @type min_fraction: float
+    @param min_fraction: minimum ORF/reference protein length (default: Constants.ORF_MIN_FRACTION)
+    @type mt_contigs: set
+    @param mt_contigs: extra contig names to treat as mitochondrial
+    @rtype: tuple
+    @return: (path to the new .bed file, query table with `cds_inferred`)
+    """
+
+    log = Log.connect(path, Constants.FileNames.LOG)
+
+    min_fraction = min_fraction or Constants.ORF_MIN_FRACTION
+    lengths = reference_lengths(os.path.join(path, Constants.FileNames.PROTEIN))
+    records = list(read_bed(bed))
+    candidates = [
+        x
+        for x in records
+        if x.thick_start >= x.thick_end and lengths.get(reference_transcript(x.name))
+    ]
+    seqs = fasta_to_dict(genome, keep={x.chrom for x in candidates})
+
+    inferred, short = [], []
+    for record in candidates:
+        if record.chrom not in seqs:
+            continue
+        seq = splice(seqs, record.chrom, record.exons(), record.strand)
+        orf = longest_orf(seq, genetic_code(record.chrom, mt_contigs))
+        reference = lengths[reference_transcript(record.name)]
+        if orf is None or (orf[1] - orf[0]) // 3 - 1 < min_fraction * reference:
+            short.append(record.name)
+            continue
+
+        first = transcript_position(record, orf[0])
+        last = transcript_position(record, orf[1] - 1)
+        record.thick_start, record.thick_end = min(first, last), max(first, last) + 1
+        inferred.append(record.name)
+
+    f = f"{output_stem(bed)}.{Constants.FileNames.ORF_BED}"
+    with open(f, "w") as out:
+        for record in records:
+            out.write(record.to_line() + "\n")
+
+    table["cds_inferred"] = table["transcripts"].isin(set(inferred))
+
+    RunWarnings.connect(path).add(
+        "cds_inferred",
+        inferred,
+        f"exon-only projection given its longest ORF as CDS (>= {min_fraction:.0%} of the reference protein)",
+    )
+
+    info = [
+        f"searched ORFs in {len(candidates)} exon-only projections of coding transcripts",
+        f"CDS inferred for {len(inferred)}, {len(short)} without a long enough ORF, annotation written to {f}",
+    ]
+
+    [log.record(i) for i in info]
+
+    return f, table
@@ -363,7 +363,7 @@ def postoga_plotter(path: str, ancestral: dict, db: list, ngenes, species="human
     commit = shell(Constants.Commands.COMMIT)
     branch = shell(Constants.Commands.BRANCH)
 
-    fig.text(0.67, 0.82, Constants.PLOTSTAMP.format(timestamp, __version__, branch, commit), fontsize=8, ha="center")
+    fig.text(0.67, 0.82, Constants.PLOTSTAMP.format(timestamp, Constants.VERSION, branch, commit), fontsize=8, ha="center")
 
     fig.text(0.1, 0.79, "A", fontsize=15, ha="center")
     fig.text(0.1, 0.525, "C", fontsize=15, ha="center")
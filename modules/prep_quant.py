@@ -0,0 +1,131 @@
+#!/usr/bin/env python3
+
+
+"""
+A module to prepare pseudo-alignment quantification inputs.
+
+Writes the spliced transcript sequences of the query annotation
+and a transcript-to-gene table with matching IDs, the two inputs
+kallisto and salmon need to quantify against a TOGA annotation.
+
+Optionally writes the gentrome (transcripts followed by the genome)
+and decoys.txt files used by salmon's selective alignment.
+"""
+
+
+import os
+from constants import Constants
+from logger import Log
+from modules.annotation_set import AnnotationSet
+from modules.errors import PostogaValueError
+from modules.fasta import fasta_to_dict, read_fasta, splice, write_fasta
+
+
+__author__ = "Alejandro Gonzales-Irribarren"
+__email__ = "jose.gonzalesdezavala1@unmsm.edu.pe"
+__github__ = "https://github.com/alejandrogzi"
+__version__ = "0.7.0-devel"
+
+
+def write_gentrome(path: str, genome: str, transcriptome: str) -> tuple:
+    """
+    Writes salmon's gentrome .fasta (transcripts first, genome last)
+    and the decoys.txt file listing the genome sequence names
+
+    @type path: str
+    @param path: path to the results directory
+    @type genome: str
+    @param genome: path to the query genome .fasta
+    @type transcriptome: str
+    @param transcriptome: path to the spliced transcripts .fasta
+    @rtype: tuple
+    @return: paths to the gentrome .fasta and the decoys file
+    """
+
+    log = Log.connect(path, Constants.FileNames.LOG)
+
+    gentrome = os.path.join(path, Constants.FileNames.GENTROME)
+    decoys = os.path.join(path, Constants.FileNames.DECOYS)
+    names = set()
+
+    with open(gentrome, "w") as fa:
+        for header, seq in read_fasta(transcriptome):
+            names.add(header)
+            write_fasta(fa, header, seq)
+
+        # salmon keeps only the first word of each header, decoy names must match it
+        with open(decoys, "w") as txt:
+            for header, seq in read_fasta(genome):
+                name = header.split()[0]
+                if name in names:
+                    raise PostogaValueError(
+                        f"genome sequence {name} collides with a transcript name, "
+                        "rename it before building a decoy-aware index",
+                        name,
+                    )
+                names.add(name)
+                write_fasta(fa, name, seq)
+                txt.write(f"{name}\n")
+
+    log.record(f"gentrome file written to {gentrome}, decoys written to {decoys}")
+
+    return gentrome, decoys
+
+
+def prep_quant(
+    path: str, genome: str, annotation: str, isoforms: str, decoys: bool = False
+) -> tuple:
+    """
+    Writes the transcriptome .fasta and tx2gene table of an annotation
+
+    @type path: str
+    @param path: path to the results directory
+    @type genome: str
+    @param genome: path to the query genome .fasta
+    @type annotation: str
+    @param annotation: path to .bed file (query_annotation/filtered)
+    @type isoforms: str
+    @param isoforms: path to the isoforms file
+    @type decoys: bool
+    @param decoys: also write the gentrome .fasta and decoys.txt for salmon
+    @rtype: tuple
+    @return: paths to the transcriptome .fasta, the tx2gene table, the
+    gentrome .fasta and the decoys file (the last two are None unless decoys)
+    """
+
+    log = Log.connect(path, Constants.FileNames.LOG)
+
+    transcripts = AnnotationSet.from_file(annotation, isoforms)
+    seqs = fasta_to_dict(genome, keep={tx.chrom for tx in transcripts})
+
+    transcriptome = os.path.join(path, Constants.FileNames.TRANSCRIPTOME)
+    tx2gene = os.path.join(path, Constants.FileNames.TX2GENE)
+    missing, written = set(), 0
+
+    with open(transcriptome, "w") as fa, open(tx2gene, "w") as tsv:
+        for tx in transcripts:
+            if tx.chrom not in seqs:
+                missing.add(tx.chrom)
+                continue
+
+            # Same ID in both files, unmapped projections are their own gene
+            write_fasta(fa, tx.name, splice(seqs, tx.chrom, tx.exons, tx.strand))
+            tsv.write(f"{tx.name}\t{tx.gene}\n")
+            written += 1
+
+    if missing:
+        log.warning(
+            f"{len(missing)} chromosomes not found in {genome}, their projections were skipped: {sorted(missing)}"
+        )
+
+    info = [
+        f"wrote {written} spliced transcripts to {transcriptome}",
+        f"tx2gene table written to {tx2gene}",
+    ]
+
+    [log.record(i) for i in info]
+
+    if decoys:
+        return transcriptome, tx2gene, *write_gentrome(path, genome, transcriptome)
+
+    return transcriptome, tx2gene, None, None
@@ -0,0 +1,99 @@
+#!/usr/bin/env python3
+
+
+"""
+A module with a sorted index over projection IDs.
+
+Prefix lookups are two binary searches over the sorted names, so finding
+every projection of a reference transcript regardless of its chain
+suffix does not scan the whole table; fuzzy lookups tolerate typos and
+version mismatches when exploring interactively:
+
+``` python
+from modules.projection_index import ProjectionIndex
+
+index = ProjectionIndex(table["transcripts"])
+index.projections_of("ENST00000612966")   # ENST00000612966.4.12, ENST00000612966.4.-1, ...
+index.fuzzy("ENST00000612996.4.12")      # closest names
+```
+"""
+
+
+import re
+from bisect import bisect_left
+from difflib import get_close_matches
+
+
+__author__ = "Alejandro Gonzales-Irribarren"
+__email__ = "jose.gonzalesdezavala1@unmsm.edu.pe"
+__github__ = "https://github.com/alejandrogzi"
+__version__ = "0.7.0-devel"
+
+
+# What may follow a reference transcript in a projection name: an optional
+# version, then a chain id or the fragment suffix (see isoform_map)
+SUFFIX = re.compile(r"^(\.\d+)?(\.\d+|\.?-1)$")
+
+
+class ProjectionIndex:
+    """A class to look up projection IDs by prefix, reference transcript or similarity."""
+
+    def __init__(self, names) -> None:
+        """
+        Constructs all the necessary attributes for the ProjectionIndex object.
+
+        @type names: iterable
+        @param names: projection IDs (duplicates and missing values are ignored)
+        """
+
+        self.names = sorted({x for x in names if isinstance(x, str) and x})
+
+    def __len__(self) -> int:
+        return len(self.names)
+
+    def __contains__(self, name: str) -> bool:
+        i = bisect_left(self.names, name)
+        return i < len(self.names) and self.names[i] == name
+
+    def prefix(self, prefix: str) -> list:
+        """
+        Returns the projection IDs starting with prefix, sorted
+
+        @type prefix: str
+        @param prefix: ID prefix
+        """
+        start = bisect_left(self.names, prefix)
+        # The last character that can follow the prefix bounds the range
+        end = bisect_left(self.names, prefix + "\U0010ffff", start)
+
+        return self.names[start:end]
+
+    def projections_of(self, transcript: str) -> list:
+        """
+        Returns the projections of a reference transcript, with or
+        without its version (ENST00000612966 or ENST00000612966.4)
+
+        @type transcript: str
+        @param transcript: reference transcript ID
+        """
+        return [
+            x
+            for x in self.prefix(transcript)
+            if SUFFIX.match(x[len(transcript) :])
+        ]
+
+    def fuzzy(self, name: str, n: int = 5, cutoff: float = 0.8) -> list:
+        """
+        Returns up to n projection IDs similar to name, best first. The
+        search is narrowed to IDs sharing its first characters when any do
+
+        @type name: str
+        @param name: (misspelled) projection ID
+        @type n: int
+        @param n: maximum number of matches
+        @type cutoff: float
+        @param cutoff: minimum similarity (0-1)
+        """
+        candidates = self.prefix(name[:4]) or self.names
+
+        return get_close_matches(name, candidates, n, cutoff)
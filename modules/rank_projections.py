@@ -0,0 +1,196 @@
+#!/usr/bin/env python3
+
+
+"""
+A module to rank the projections of each reference transcript.
+
+A reference transcript projected through several chains gets one row
+per projection in the query table. They are ordered by a weighted
+composite of normalized criteria (0-1, higher is better):
+
+    pred              TOGA orthology score
+    class             loss class, I best (see Constants.ORDER)
+    relation          o2o > o2m/m2o > m2m > o2z
+    confidence_level  TOGA transcript quality
+    <column>          any numeric query table column, `-<column>` when
+                      lower is better (e.g. -mutations)
+
+``` python
+from modules.rank_projections import rank_projections
+
+ranked = rank_projections(table, "ENST00000355624.10", {"pred": 1, "class": 0.5})
+```
+"""
+
+
+import os
+import pandas as pd
+from constants import Constants
+from logger import Log
+from modules.bed import read_bed
+from modules.errors import PostogaValueError
+from modules.make_query_table import projection_chain
+from modules.utils import output_stem
+
+
+__author__ = "Alejandro Gonzales-Irribarren"
+__email__ = "jose.gonzalesdezavala1@unmsm.edu.pe"
+__github__ = "https://github.com/alejandrogzi"
+__version__ = "0.7.0-devel"
+
+
+RELATION_RANK = {"o2o": 1.0, "o2m": 0.5, "m2o": 0.5, "m2m": 0.25, "o2z": 0.0}
+CONFIDENCE_RANK = {
+    "high_confidence": 1.0,
+    "average_confidence": 0.66,
+    "partial": 0.33,
+    "low_confidence": 0.0,
+}
+
+
+def read_weights(spec: str) -> dict:
+    """
+    Reads ranking weights from an inline 'pred=1,class=0.5,-mutations=0.2'
+    string (see module docstring for the criteria)
+
+    @type spec: str
+    @param spec: criterion=weight pairs, comma-separated
+    """
+    pairs = [x.split("=", 1) for x in spec.split(",") if x]
+
+    return {key.strip(): float(value) for key, value in pairs}
+
+
+def reference_transcript(projection: str) -> str:
+    """
+    Returns the reference transcript of a projection (the name without
+    its chain id, e.g. ENST00000355624.10 for ENST00000355624.10.5)
+
+    @type projection: str
+    @param projection: projection name
+    """
+    return projection[: -len(projection_chain(projection)) - 1]
+
+
+def criterion(table: pd.DataFrame, name: str) -> pd.Series:
+    """
+    Returns a 0-1 (higher is better) series for a ranking criterion,
+    0 where the value is missing
+
+    @type table: pd.DataFrame
+    @param table: query table
+    @type name: str
+    @param name: criterion name (see module docstring)
+    """
+    column = name.lstrip("-")
+    if column not in table.columns:
+        raise PostogaValueError(
+            f"unknown ranking criterion {name}, not a query table column", name
+        )
+
+    if column == "class":
+        worst = max(Constants.ORDER.values())
+        values = 1 - (table[column].map(Constants.ORDER) - 1) / (worst - 1)
+    elif column == "relation":
+        values = table[column].map(RELATION_RANK)
+    elif column == "confidence_level":
+        values = table[column].map(CONFIDENCE_RANK)
+    else:
+        values = pd.to_numeric(table[column], errors="coerce")
+        low, high = values.min(), values.max()
+        values = (values - low) / (high - low) if high > low else values * 0 + 1
+        if name.startswith("-"):
+            values = 1 - values
+
+    return values.fillna(0)
+
+
+def rank_table(table: pd.DataFrame, weights: dict = None) -> pd.DataFrame:
+    """
+    Adds `reference`, `rank_score` and `rank` (1 = best projection of its
+    reference transcript) columns to a copy of the query table
+
+    @type table: pd.DataFrame
+    @param table: query table
+    @type weights: dict
+    @param weights: criterion -> weight (default: Constants.RANK_WEIGHTS)
+    """
+    weights = weights or Constants.RANK_WEIGHTS
+    table = table.dropna(subset=["transcripts"]).copy()
+
+    table["reference"] = table["transcripts"].map(reference_transcript)
+    table["rank_score"] = sum(w * criterion(table, k) for k, w in weights.items()) / sum(
+        weights.values()
+    )
+    table["rank"] = (
+        table.sort_values(["rank_score", "transcripts"], ascending=[False, True])
+        .groupby("reference")
+        .cumcount()
+        + 1
+    )
+
+    return table.sort_values(["reference", "rank"])
+
+
+def rank_projections(
+    table: pd.DataFrame, transcript: str, weights: dict = None
+) -> pd.DataFrame:
+    """
+    Returns the projections of a reference transcript, best first
+
+    @type table: pd.DataFrame
+    @param table: query table
+    @type transcript: str
+    @param transcript: reference transcript name
+    @type weights: dict
+    @param weights: criterion -> weight (default: Constants.RANK_WEIGHTS)
+    """
+    table = table[table["transcripts"].map(reference_transcript) == transcript]
+
+    return rank_table(table, weights)
+
+
+def keep_top_projections(
+    path: str, bed: str, table: pd.DataFrame, k: int, weights: dict = None
+) -> str:
+    """
+    Writes a copy of a .bed file with the top-k projections of each
+    reference transcript
+
+    @type path: str
+    @param path: path to the results directory
+    @type bed: str
+    @param bed: path to .bed file
+    @type table: pd.DataFrame
+    @param table: query table
+    @type k: int
+    @param k: projections to keep per reference transcript
+    @type weights: dict
+    @param weights: criterion -> weight (default: Constants.RANK_WEIGHTS)
+    """
+
+    log = Log.connect(path, Constants.FileNames.LOG)
+
+    ranked = rank_table(table, weights)
+    keep = set(ranked[ranked["rank"] <= k]["transcripts"])
+    known = set(ranked["transcripts"])
+
+    stem = os.path.basename(output_stem(bed))
+    f = os.path.join(path, f"{stem}.{Constants.FileNames.TOP_BED.format(k)}")
+    kept = total = 0
+    with open(f, "w") as out:
+        for record in read_bed(bed):
+            total += 1
+            # Projections missing from the query table are kept as they are
+            if record.name in keep or record.name not in known:
+                out.write(record.to_line() + "\n")
+                kept += 1
+
+    info = [
+        f"ranked projections by {weights or Constants.RANK_WEIGHTS}",
+        f"kept {kept} of {total} projections (top {k} per reference transcript) in {f}",
+    ]
+
+    [log.record(i) for i in info]
+
+    return f
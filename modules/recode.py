@@ -0,0 +1,57 @@
+#!/usr/bin/env python3
+
+
+"""
+A module to collapse TOGA loss classes with a user mapping.
+
+A mapping (e.g. PI and UL -> uncertain) is only applied to what postoga
+writes (query table, `loss_class` attributes, --split_by class files and
+class matrices). Filters, gene status rules, region searches and stats
+keep comparing TOGA's own class names.
+"""
+
+
+import os
+
+
+__author__ = "Alejandro Gonzales-Irribarren"
+__email__ = "jose.gonzalesdezavala1@unmsm.edu.pe"
+__github__ = "https://github.com/alejandrogzi"
+__version__ = "0.7.0-devel"
+
+
+def read_recode(spec: str) -> dict:
+    """
+    Reads a class mapping from a two-column (class, new class) file or an
+    inline 'PI=uncertain,UL=uncertain' string
+
+    @type spec: str
+    @param spec: path to the mapping file or inline mapping
+    """
+    if os.path.isfile(spec):
+        with open(spec, "r") as f:
+            pairs = [line.split()[:2] for line in f if line.strip()]
+    else:
+        pairs = [x.split("=", 1) for x in spec.split(",") if x]
+
+    return {old.strip(): new.strip() for old, new in pairs}
+
+
+def recode(cls: str, mapping: dict) -> str:
+    return mapping.get(cls, cls) if mapping else cls
+
+
+def recode_table(table, mapping: dict):
+    """
+    Returns a copy of the query table with its class column recoded, the
+    table itself is left untouched
+
+    @type table: pd.DataFrame
+    @param table: query table
+    @type mapping: dict
+    @param mapping: class -> new class
+    """
+    if not mapping:
+        return table
+
+    return table.assign(**{"class": table["class"].map(lambda x: recode(x, mapping))})
@@ -0,0 +1,216 @@
+#!/usr/bin/env python3
+
+
+"""
+A module to annotate projections with reference gene metadata.
+
+Symbols, biotypes and descriptions are parsed from the reference
+.gtf/.gff (optionally gzipped) once and cached in a small indexed
+SQLite file keyed by the reference path, size and modification time,
+so later runs against the same reference look them up in seconds:
+
+``` python
+from modules.reference_metadata import ReferenceMetadata
+
+meta = ReferenceMetadata.load("Homo_sapiens.GRCh38.112.gtf.gz")
+meta.get("ENSG00000141510")  # {"symbol": "TP53", "biotype": "protein_coding", ...}
+```
+"""
+
+
+import hashlib
+import os
+import sqlite3
+import pandas as pd
+from constants import Constants
+from logger import Log
+from modules.fileio import open_text
+from modules.formats import detect_format
+from modules.gxf import TRANSCRIPT, GxfFeature
+
+
+__author__ = "Alejandro Gonzales-Irribarren"
+__email__ = "jose.gonzalesdezavala1@unmsm.edu.pe"
+__github__ = "https://github.com/alejandrogzi"
+__version__ = "0.7.0-devel"
+
+
+# Metadata field -> attribute keys, first present wins
+FIELDS = {
+    "symbol": ("gene_name", "Name", "gene_symbol", "gene"),
+    "biotype": ("gene_biotype", "gene_type", "biotype", "transcript_biotype"),
+    "description": ("description", "Note", "product"),
+}
+
+
+def first_attribute(feature: GxfFeature, keys: tuple) -> str:
+    """Returns the first attribute of a feature among keys, None if absent"""
+    for key in keys:
+        value = feature.attributes.get(key)
+        if value:
+            return ",".join(value) if isinstance(value, list) else str(value)
+
+    return None
+
+
+def parse_reference(reference: str):
+    """
+    Yields (id, symbol, biotype, description) rows for the genes and
+    transcripts of a reference .gtf/.gff; transcripts inherit the
+    fields their own line lacks from their gene
+
+    @type reference: str
+    @param reference: path to the reference .gtf/.gff, optionally gzipped
+    """
+    fmt = detect_format(reference)
+    genes = {}
+
+    with open_text(reference) as f:
+        for line in f:
+            if not line.strip() or line.startswith("#"):
+                continue
+            feature = GxfFeature.from_line(line, fmt)
+            if feature.feature != "gene" and feature.feature not in TRANSCRIPT:
+                continue
+
+            row = {k: first_attribute(feature, v) for k, v in FIELDS.items()}
+            if feature.feature != "gene":
+                row["biotype"] = (
+                    first_attribute(feature, ("transcript_biotype", "transcript_type"))
+                    or row["biotype"]
+                )
+            if fmt == "gtf":
+                gene = feature.attributes.get("gene_id")
+                name = gene if feature.feature == "gene" else feature.transcript_id
+            else:
+                gene = feature.attributes.get("Parent")
+                name = feature.attributes.get("ID", "")
+                # Ensembl GFF3 IDs carry a gene:/transcript: prefix
+                name = name.split(":", 1)[-1]
+
+            if feature.feature == "gene":
+                genes[name] = row
+            else:
+                inherited = genes.get((gene or "").split(":", 1)[-1], {})
+                row = {k: v or inherited.get(k) for k, v in row.items()}
+
+            if name:
+                yield name, row["symbol"], row["biotype"], row["description"]
+
+
+def cache_key(reference: str) -> str:
+    """Returns a key that changes whenever the reference file changes"""
+    path = os.path.realpath(reference)
+    stat = os.stat(path)
+
+    return hashlib.sha1(f"{path}:{stat.st_size}:{stat.st_mtime_ns}".encode()).hexdigest()
+
+
+class ReferenceMetadata:
+    """A class to look up cached reference gene/transcript metadata."""
+
+    def __init__(self, db: str) -> None:
+        """
+        Constructs all the necessary attributes for the ReferenceMetadata object.
+
+        @type db: str
+        @param db: path to the cache database
+        """
+
+        self.db = db
+        self.conn = sqlite3.connect(db)
+
+    @classmethod
+    def load(cls, reference: str, cache_dir: str = None) -> "ReferenceMetadata":
+        """
+        Returns the metadata of a reference, building its cache the first
+        time the reference (or a new version of it) is seen
+
+        @type reference: str
+        @param reference: path to the reference .gtf/.gff
+        @type cache_dir: str
+        @param cache_dir: cache directory (default: Constants.CACHE_DIR)
+        """
+        cache_dir = os.path.expanduser(cache_dir or Constants.CACHE_DIR)
+        os.makedirs(cache_dir, exist_ok=True)
+        db = os.path.join(cache_dir, f"{cache_key(reference)}.sqlite")
+
+        if not os.path.exists(db):
+            # Built aside and moved in place so readers never see a partial cache
+            tmp = f"{db}.{os.getpid()}.tmp"
+            conn = sqlite3.connect(tmp)
+            conn.execute(
+                "CREATE TABLE meta (id TEXT PRIMARY KEY, symbol TEXT, biotype TEXT, description TEXT)"
+            )
+            conn.executemany(
+                "INSERT OR REPLACE INTO meta VALUES (?, ?, ?, ?)",
+                parse_reference(reference),
+            )
+            conn.commit()
+            conn.close()
+            os.replace(tmp, db)
+
+        return cls(db)
+
+    def __len__(self) -> int:
+        return self.conn.execute("SELECT COUNT(*) FROM meta").fetchone()[0]
+
+    def get(self, name: str) -> dict:
+        """
+        Returns the symbol/biotype/description of a gene or transcript,
+        an empty dictionary if the reference does not have it
+
+        @type name: str
+        @param name: gene or transcript ID
+        """
+        row = self.conn.execute(
+            "SELECT symbol, biotype, description FROM meta WHERE id = ?", (name,)
+        ).fetchone()
+
+        return dict(zip(FIELDS, row)) if row else {}
+
+    def close(self) -> None:
+        self.conn.close()
+
+
+def annotate_reference(
+    path: str, table: pd.DataFrame, reference: str, cache_dir: str = None
+) -> pd.DataFrame:
+    """
+    Adds ref_symbol, ref_biotype and ref_description columns to the
+    query table, looked up by reference gene (then reference transcript)
+
+    @type path: str
+    @param path: path to the results directory
+    @type table: pd.DataFrame
+    @param table: query table
+    @type reference: str
+    @param reference: path to the reference .gtf/.gff
+    @type cache_dir: str
+    @param cache_dir: cache directory (default: Constants.CACHE_DIR)
+    """
+
+    log = Log.connect(path, Constants.FileNames.LOG)
+
+    meta = ReferenceMetadata.load(reference, cache_dir)
+    rows = {}
+    for gene, transcript in zip(table["t_gene"], table["helper"]):
+        if (gene, transcript) not in rows:
+            found = meta.get(str(gene)) or meta.get(str(transcript))
+            rows[(gene, transcript)] = found
+
+    for field in FIELDS:
+        table[f"ref_{field}"] = [
+            rows[x].get(field) for x in zip(table["t_gene"], table["helper"])
+        ]
+
+    found = sum(1 for x in rows.values() if x)
+    info = [
+        f"reference metadata of {reference} read from {meta.db} ({len(meta)} entries)",
+        f"reference metadata found for {found} of {len(rows)} reference genes/transcripts",
+    ]
+    meta.close()
+
+    [log.record(i) for i in info]
+
+    return table
@@ -0,0 +1,743 @@
+#!/usr/bin/env python3
+
+
+"""
+A module to refine the .gtf/.gff files written by bed2gtf/bed2gff.
+
+The conversion itself is done by the external binaries; this module
+reads their output back and applies the postoga-side options
+(phase convention, feature selection, ...) in a single rewrite of the file.
+"""
+
+
+import hashlib
+import os
+import shutil
+from collections import defaultdict
+from constants import Constants
+from logger import Log
+from modules.annotation_set import merge_intervals
+from modules.bed import read_bed
+from modules.chrom_spill import ChromSpill
+from modules.fileio import detect_compression, open_text
+from modules.gap_attribute import annotate_gaps
+from modules.gff_profile import refseq_profile
+from modules.gff_tree import gff_tree, sequence_regions
+from modules.guardrails import guard_attributes
+from modules.fasta import (
+    codon_table,
+    fasta_to_dict,
+    genetic_code,
+    is_mitochondrial,
+    reverse_complement,
+)
+from modules.gxf import (
+    CODING,
+    TRANSCRIPT,
+    GxfFeature,
+    add_tag,
+    gxf_format,
+    sort_features,
+    write_gxf,
+)
+from modules.run_warnings import RunWarnings
+from modules.seq_store import SeqStore
+from modules.utils import natural_key
+
+
+__author__ = "Alejandro Gonzales-Irribarren"
+__email__ = "jose.gonzalesdezavala1@unmsm.edu.pe"
+__github__ = "https://github.com/alejandrogzi"
+__version__ = "0.7.0-devel"
+
+
+def set_phase(features: list, convention: str = "phase") -> list:
+    """
+    Recomputes the 8th column of all features from the CDS structure.
+
+    Coding features (CDS, start/stop codons) get their GFF3 phase (bases to
+    skip to reach the next codon) or the raw frame (position of their first
+    base within a codon, i.e. the inverse of the phase); every other feature
+    gets "." regardless of what the converter wrote (e.g. the "3" sentinel).
+    The first part of a 5' partial CDS keeps the phase (1 or 2) it was
+    written with and the phases of the following parts count from it
+
+    @type features: list
+    @param features: list of GxfFeature objects
+    @type convention: str
+    @param convention: phase or frame
+    """
+    parts = defaultdict(list)
+
+    for feature in features:
+        if feature.feature in CODING:
+            parts[(feature.transcript_id, feature.feature)].append(feature)
+        else:
+            feature.phase = "."
+
+    for group in parts.values():
+        # Walk the parts in transcript orientation, accumulating coding length
+        group.sort(key=lambda x: x.start, reverse=group[0].strand == "-")
+        length = -int(group[0].phase) if group[0].phase in ("1", "2") else 0
+        for feature in group:
+            frame = length % 3
+            feature.phase = str(frame if convention == "frame" else (3 - frame) % 3)
+            length += feature.end - feature.start + 1
+
+    return features
+
+
+def gene_id(feature) -> str:
+    """Returns the gene of a gene/transcript feature"""
+    if feature.fmt == "gtf":
+        return feature.attributes.get("gene_id")
+    if feature.feature == "gene":
+        return feature.attributes.get("ID")
+    return feature.attributes.get("Parent")
+
+
+def select_features(features: list, mode: str = "all") -> list:
+    """
+    Keeps only the features needed by a minimal annotation.
+
+    cds_only keeps genes, transcripts, CDS and start/stop codons, dropping
+    non-coding transcripts (and genes left empty); exon_only keeps genes,
+    transcripts and exons
+
+    @type features: list
+    @param features: list of GxfFeature objects
+    @type mode: str
+    @param mode: all, cds_only or exon_only
+    """
+    if mode == "all":
+        return features
+
+    keep = CODING if mode == "cds_only" else ("exon",)
+    transcripts = {x.transcript_id for x in features if x.feature in keep}
+    genes = {
+        gene_id(x)
+        for x in features
+        if x.feature in TRANSCRIPT and x.transcript_id in transcripts
+    }
+
+    return [
+        x
+        for x in features
+        if x.feature in keep
+        or (x.feature in TRANSCRIPT and x.transcript_id in transcripts)
+        or (x.feature == "gene" and gene_id(x) in genes)
+    ]
+
+
+def check_terminal_codons(features: list, mt_contigs: set = None) -> dict:
+    """
+    Lists coding transcripts whose terminal codons are incomplete: CDS not
+    a multiple of 3, or no start_codon/stop_codon features written by the
+    converter (which skips codons it cannot infer). Mitochondrial transcripts
+    are skipped, their stops are often completed by polyadenylation
+
+    @type features: list
+    @param features: list of GxfFeature objects
+    @type mt_contigs: set
+    @param mt_contigs: extra contig names to treat as mitochondrial
+    @rtype: dict
+    @return: warning code -> sorted list of transcripts
+    """
+    length, codons = defaultdict(int), defaultdict(set)
+
+    for feature in features:
+        if is_mitochondrial(feature.chrom, mt_contigs):
+            continue
+        if feature.feature == "CDS":
+            length[feature.transcript_id] += feature.end - feature.start + 1
+        elif feature.feature in CODING:
+            codons[feature.transcript_id].add(feature.feature)
+
+    return {
+        "incomplete_terminal_codon": sorted(x for x, n in length.items() if n % 3),
+        "missing_start_codon": sorted(
+            x for x in length if "start_codon" not in codons[x]
+        ),
+        "missing_stop_codon": sorted(
+            x for x in length if "stop_codon" not in codons[x]
+        ),
+    }
+
+
+def trim_cds_end(group: list, excess: int, five_prime: bool, dropped: set) -> None:
+    """
+    Removes excess bases from one end of a transcript's CDS, dropping CDS
+    parts shorter than what is left to remove
+
+    @type group: list
+    @param group: CDS features of a transcript
+    @type excess: int
+    @param excess: number of bases to remove
+    @type five_prime: bool
+    @param five_prime: trim the 5' end (in transcript orientation), else the 3'
+    @type dropped: set
+    @param dropped: ids of the features removed, updated in place
+    """
+    plus = group[0].strand != "-"
+    # Walk from the trimmed end: lowest start first for a 5' trim on +
+    # or a 3' trim on -
+    group = sorted(group, key=lambda x: x.start, reverse=plus != five_prime)
+
+    for feature in group:
+        if id(feature) in dropped:
+            continue
+        length = feature.end - feature.start + 1
+        if length <= excess:
+            dropped.add(id(feature))
+            excess -= length
+            continue
+        if plus == five_prime:
+            feature.start += excess
+        else:
+            feature.end -= excess
+        break
+
+
+def trim_partial_codons(features: list) -> tuple:
+    """
+    Trims incomplete codons from both ends of each CDS so every CDS
+    written translates cleanly in frame: a 5' partial codon (the phase
+    of the first CDS part, in transcript orientation) is removed and
+    tagged cds_start_NF, and bases left over at the 3' end are removed
+    and tagged cds_end_NF. The start_codon (stop_codon) features of a
+    transcript trimmed at its 5' (3') end no longer border its CDS and
+    are dropped. Phases are read as set by set_phase
+
+    @type features: list
+    @param features: list of GxfFeature objects
+    @rtype: tuple
+    @return: the trimmed features and the set of trimmed transcripts
+    """
+    cds = defaultdict(list)
+    for feature in features:
+        if feature.feature == "CDS":
+            cds[feature.transcript_id].append(feature)
+
+    dropped, tags = set(), defaultdict(set)
+    for transcript, group in cds.items():
+        first = min(group, key=lambda x: x.start if x.strand != "-" else -x.end)
+        offset = int(first.phase) if first.phase in ("1", "2") else 0
+        if offset:
+            trim_cds_end(group, offset, True, dropped)
+            tags[transcript].add("cds_start_NF")
+            # The new first part starts on a complete codon
+            first = min(
+                (x for x in group if id(x) not in dropped),
+                key=lambda x: x.start if x.strand != "-" else -x.end,
+                default=None,
+            )
+            if first is not None:
+                first.phase = "0"
+
+        excess = sum(x.end - x.start + 1 for x in group if id(x) not in dropped) % 3
+        if excess:
+            trim_cds_end(group, excess, False, dropped)
+            tags[transcript].add("cds_end_NF")
+
+    codons = {"start_codon": "cds_start_NF", "stop_codon": "cds_end_NF"}
+    for feature in features:
+        if feature.feature in TRANSCRIPT:
+            for tag in sorted(tags.get(feature.transcript_id, ())):
+                add_tag(feature, tag)
+        elif codons.get(feature.feature) in tags.get(feature.transcript_id, ()):
+            dropped.add(id(feature))
+
+    return [x for x in features if id(x) not in dropped], set(tags)
+
+
+def terminal_bases(fetch, chrom: str, blocks: list, strand: str) -> tuple:
+    """
+    Returns the first and last codon of spliced blocks in transcript
+    orientation, fetching only those bases (a codon may span blocks)
+
+    @type fetch: function
+    @param fetch: (chrom, start, end) -> sequence of a 0-based region
+    @type chrom: str
+    @param chrom: contig name
+    @type blocks: list
+    @param blocks: sorted, merged (start, end) 0-based blocks
+    @type strand: str
+    @param strand: + or -
+    """
+    low, high, need = [], [], 3
+    for start, end in blocks:
+        size = min(need, end - start)
+        low.append(fetch(chrom, start, start + size))
+        need -= size
+        if not need:
+            break
+
+    need = 3
+    for start, end in reversed(blocks):
+        size = min(need, end - start)
+        high.insert(0, fetch(chrom, end - size, end))
+        need -= size
+        if not need:
+            break
+
+    low, high = "".join(low), "".join(high)
+    if strand == "-":
+        return reverse_complement(high), reverse_complement(low)
+
+    return low, high
+
+
+def tag_incomplete(
+    features: list, lengths: dict, fetch, mt_contigs: set = None
+) -> dict:
+    """
+    Tags incomplete transcripts with the Ensembl NF tags:
+
+    - cds_start_NF/cds_end_NF: the CDS does not start with ATG or does
+    not end with a stop codon (stop_codon features are included)
+    - mRNA_start_NF/mRNA_end_NF: the transcript touches a contig end, or
+    its CDS is incomplete at that end and there is no UTR beyond it
+
+    Mitochondrial contigs use the vertebrate mitochondrial code and its
+    alternative start codons
+
+    @type features: list
+    @param features: list of GxfFeature objects
+    @type lengths: dict
+    @param lengths: chrom -> length of the query genome contigs
+    @type fetch: function
+    @param fetch: (chrom, start, end) -> sequence of a 0-based region,
+        only the terminal codons are fetched (see terminal_bases)
+    @type mt_contigs: set
+    @param mt_contigs: extra contig names to treat as mitochondrial
+    @rtype: dict
+    @return: tag -> number of tagged transcripts
+    """
+    coding, transcripts = defaultdict(list), {}
+
+    for feature in features:
+        if feature.feature in ("CDS", "stop_codon"):
+            coding[feature.transcript_id].append((feature.start - 1, feature.end))
+        elif feature.feature in TRANSCRIPT:
+            transcripts[feature.transcript_id] = feature
+
+    counts = defaultdict(int)
+    for transcript, feature in transcripts.items():
+        if feature.chrom not in lengths:
+            continue
+
+        tags, blocks = [], merge_intervals(coding.get(transcript, []))
+        five, three = feature.start == 1, feature.end == lengths[feature.chrom]
+        if feature.strand == "-":
+            five, three = three, five
+
+        if blocks:
+            code = genetic_code(feature.chrom, mt_contigs)
+            stops = [codon for codon, aa in codon_table(code).items() if aa == "*"]
+            start, end = terminal_bases(fetch, feature.chrom, blocks, feature.strand)
+            first, last = blocks[0][0] + 1, blocks[-1][1]
+            no_five_utr = (first if feature.strand == "+" else last) == (
+                feature.start if feature.strand == "+" else feature.end
+            )
+            no_three_utr = (last if feature.strand == "+" else first) == (
+                feature.end if feature.strand == "+" else feature.start
+            )
+            if start.upper() not in Constants.START_CODONS[code]:
+                tags.append("cds_start_NF")
+                five = five or no_five_utr
+            if end.upper() not in stops:
+                tags.append("cds_end_NF")
+                three = three or no_three_utr
+
+        if five:
+            tags.append("mRNA_start_NF")
+        if three:
+            tags.append("mRNA_end_NF")
+
+        for tag in tags:
+            add_tag(feature, tag)
+            counts[tag] += 1
+
+    return dict(counts)
+
+
+def deduplicate_exons(features: list) -> tuple:
+    """
+    Merges identical .gff exons shared by several isoforms of the same
+    gene into a single exon with comma-separated Parents, as Ensembl does
+
+    @type features: list
+    @param features: list of GxfFeature objects (.gff)
+    @rtype: tuple
+    @return: the deduplicated features and the number of exons merged
+    """
+    genes = {
+        x.transcript_id: x.attributes.get("Parent")
+        for x in features
+        if x.feature in TRANSCRIPT
+    }
+    seen, kept, merged = {}, [], 0
+
+    for feature in features:
+        if feature.feature != "exon":
+            kept.append(feature)
+            continue
+
+        parent = feature.attributes.get("Parent")
+        key = (
+            genes.get(parent, parent),
+            feature.chrom,
+            feature.start,
+            feature.end,
+            feature.strand,
+        )
+        if key in seen:
+            parents = seen[key].attributes["Parent"].split(",")
+            if parent not in parents:
+                seen[key].attributes["Parent"] = ",".join(parents + [parent])
+            merged += 1
+            continue
+
+        seen[key] = feature
+        kept.append(feature)
+
+    return kept, merged
+
+
+def stable_ids(features: list) -> list:
+    """
+    Replaces the IDs of .gff sub-features (exons, CDS, UTRs, codons) with IDs
+    derived from their content instead of their emission order, so two runs
+    over the same annotation produce the same IDs
+
+    @type features: list
+    @param features: list of GxfFeature objects (.gff)
+    """
+    for feature in features:
+        if feature.feature == "gene" or feature.feature in TRANSCRIPT:
+            continue
+
+        parent = feature.attributes.get("Parent", "")
+        key = f"{feature.chrom}:{feature.start}-{feature.end}:{feature.strand}:{feature.feature}:{parent}"
+        digest = hashlib.sha1(key.encode()).hexdigest()[:10]
+        feature.attributes = {
+            "ID": f"{feature.feature.lower()}-{digest}",
+            **{k: v for k, v in feature.attributes.items() if k != "ID"},
+        }
+
+    return features
+
+
+def annotate_orthology(features: list, relations: dict) -> int:
+    """
+    Adds the orthology relation (o2o, o2m, m2o, m2m) of each projection as
+    an `orthology` attribute on its transcript line, and the relations of
+    its transcripts (comma-separated if they differ) on the gene line
+
+    @type features: list
+    @param features: list of GxfFeature objects
+    @type relations: dict
+    @param relations: projection -> orthology relation
+    @rtype: int
+    @return: number of annotated transcripts
+    """
+    genes, annotated = defaultdict(set), 0
+
+    for feature in features:
+        if feature.feature in TRANSCRIPT and feature.transcript_id in relations:
+            relation = relations[feature.transcript_id]
+            feature.attributes["orthology"] = relation
+            genes[gene_id(feature)].add(relation)
+            annotated += 1
+
+    for feature in features:
+        if feature.feature == "gene" and gene_id(feature) in genes:
+            feature.attributes["orthology"] = ",".join(sorted(genes[gene_id(feature)]))
+
+    return annotated
+
+
+def annotate_transcripts(features: list, key: str, values: dict) -> int:
+    """
+    Adds a per-projection value (e.g. chain_id) as an attribute on its
+    transcript line
+
+    @type features: list
+    @param features: list of GxfFeature objects
+    @type key: str
+    @param key: attribute name
+    @type values: dict
+    @param values: projection -> value
+    @rtype: int
+    @return: number of annotated transcripts
+    """
+    annotated = 0
+
+    for feature in features:
+        if feature.feature in TRANSCRIPT and feature.transcript_id in values:
+            feature.attributes[key] = values[feature.transcript_id]
+            annotated += 1
+
+    return annotated
+
+
+def refine_gxf(
+    path: str,
+    gxf: str,
+    *,
+    phase_convention: str = "phase",
+    features: str = "all",
+    trim_partial: bool = False,
+    genome: str = None,
+    dedup_exons: bool = False,
+    mt_contigs: set = None,
+    relations: dict = None,
+    chains: dict = None,
+    gap_flags: dict = None,
+    bed: str = None,
+    classes: dict = None,
+    alignments: dict = None,
+    max_attribute_length: int = None,
+    attribute_sep: str = None,
+    line_ending: str = "\n",
+    mrna: bool = False,
+    chrom_sizes: dict = None,
+    isoforms=None,
+    profile: dict = None,
+    cds_ratios: dict = None,
+    sort: str = "position",
+) -> str:
+    """
+    Rewrites a .gtf/.gff file applying the postoga-side options, one
+    chromosome at a time through per-chromosome spill files. Options are
+    keyword-only
+
+    @type path: str
+    @param path: path to the results directory
+    @type gxf: str
+    @param gxf: path to the .gtf/.gff file
+    @type phase_convention: str
+    @param phase_convention: phase (GFF3/GTF phase) or frame (raw frame)
+    @type features: str
+    @param features: all, cds_only or exon_only
+    @type trim_partial: bool
+    @param trim_partial: trim CDS to complete codons at both ends and tag
+        them cds_start_NF/cds_end_NF (see trim_partial_codons)
+    @type genome: str
+    @param genome: path to the query genome .fasta, enables the NF tags
+    @type dedup_exons: bool
+    @param dedup_exons: merge exons shared by isoforms (.gff only)
+    @type mt_contigs: set
+    @param mt_contigs: extra contig names to treat as mitochondrial
+    @type relations: dict
+    @param relations: projection -> orthology relation, adds `orthology`
+    @type chains: dict
+    @param chains: projection -> chain id, adds `chain_id`
+    @type gap_flags: dict
+    @param gap_flags: projection -> contains_gap/near_gap, adds `gap_flag`
+    @type bed: str
+    @param bed: source .bed file, keeps score and itemRgb as `bed_score` and
+        `item_rgb` for a lossless round trip back to BED12
+    @type classes: dict
+    @param classes: projection -> (recoded) loss class, adds `loss_class`
+    @type alignments: dict
+    @param alignments: projection -> codon alignment, adds Gap/Target to .gff CDS
+    @type max_attribute_length: int
+    @param max_attribute_length: drop longer attribute values (guardrail)
+    @type attribute_sep: str
+    @param attribute_sep: separator between attributes, the format default if None
+    @type line_ending: str
+    @param line_ending: line terminator of the rewritten file
+    @type mrna: bool
+    @param mrna: emit coding .gff transcripts as mRNA
+    @type chrom_sizes: dict
+    @param chrom_sizes: chrom -> length for the .gff `##sequence-region` pragmas
+    @type isoforms: IsoformMap
+    @param isoforms: gene of .gff transcripts without a valid Parent
+    @type profile: dict
+    @param profile: projection -> fields, adds the refseq .gff attribute profile
+    @type cds_ratios: dict
+    @param cds_ratios: projection -> query/reference CDS length, adds `cds_ratio`
+    @type sort: str
+    @param sort: feature order, position, transcript or none (see
+        sort_features); none keeps the input order within each chromosome
+    """
+
+    log = Log.connect(path, Constants.FileNames.LOG)
+    fmt = gxf_format(gxf)
+
+    bed_scores, bed_rgbs = {}, {}
+    if bed:
+        for record in read_bed(bed):
+            bed_scores[record.name] = record.score
+            bed_rgbs[record.name] = record.rgb
+
+    # attribute, projection -> value, name in the log
+    annotations = [
+        ("chain_id", chains, "chain id"),
+        ("loss_class", classes, "loss class"),
+        ("gap_flag", gap_flags, "assembly gap flag"),
+        ("cds_ratio", cds_ratios, "CDS length ratio"),
+    ]
+    counts, incomplete, codons = defaultdict(int), defaultdict(int), defaultdict(list)
+    mismatched, orphans, dropped, pragmas, kept = [], [], [], [], []
+
+    # Features are spilled per chromosome and refined one chromosome at a
+    # time: every step works within a gene, so memory stays bounded by the
+    # largest chromosome. The transcript order is global, those features
+    # are kept until the end
+    with ChromSpill(os.path.join(path, Constants.TEMP)) as spill:
+        with open_text(gxf) as f:
+            for line in f:
+                if line.strip() and not line.startswith("#"):
+                    spill.spill(line.split("\t", 1)[0], line)
+
+        # Compressed genomes are read once, others are indexed and only the
+        # terminal codons of each transcript are fetched
+        store = None
+        if genome and detect_compression(genome):
+            seqs = fasta_to_dict(genome, keep=set(spill.spills))
+            lengths = {chrom: len(seq) for chrom, seq in seqs.items()}
+            fetch = lambda chrom, start, end: seqs[chrom][start:end]
+        elif genome:
+            store = SeqStore(genome)
+            lengths, fetch = store.lengths(), store.get_region
+
+        body = os.path.join(spill.tmp, "body")
+        with open(body, "w", newline="") as out:
+            for chrom, chunk in spill.chromosomes(key=natural_key):
+                lines = [GxfFeature.from_line(x, fmt) for x in chunk]
+
+                for code, names in check_terminal_codons(lines, mt_contigs).items():
+                    codons[code].extend(names)
+
+                lines = select_features(lines, features)
+
+                if trim_partial:
+                    # Phases first, the converter may write sentinels (e.g. 3)
+                    lines, names = trim_partial_codons(set_phase(lines))
+                    counts["trimmed"] += len(names)
+
+                if genome:
+                    tags = tag_incomplete(lines, lengths, fetch, mt_contigs)
+                    for tag, count in tags.items():
+                        incomplete[tag] += count
+
+                if relations:
+                    counts["orthology"] += annotate_orthology(lines, relations)
+
+                for key, values, _ in annotations:
+                    if values:
+                        counts[key] += annotate_transcripts(lines, key, values)
+
+                if alignments and fmt == "gff":
+                    count, names = annotate_gaps(lines, alignments)
+                    counts["gaps"] += count
+                    mismatched.extend(names)
+
+                if bed:
+                    annotate_transcripts(lines, "bed_score", bed_scores)
+                    annotate_transcripts(lines, "item_rgb", bed_rgbs)
+
+                lines = set_phase(lines, phase_convention)
+
+                if dedup_exons and fmt == "gff":
+                    lines, count = deduplicate_exons(lines)
+                    counts["merged"] += count
+
+                if fmt == "gff":
+                    lines, count, names = gff_tree(lines, isoforms, mrna)
+                    counts["synthesized"] += count
+                    orphans.extend(names)
+                    if profile is not None:
+                        counts["profiled"] += refseq_profile(lines, profile)
+                    lines = stable_ids(lines)
+                    pragmas.extend(sequence_regions(lines, chrom_sizes))
+
+                if max_attribute_length:
+                    dropped.extend(guard_attributes(lines, max_attribute_length))
+
+                counts["features"] += len(lines)
+                if sort == "transcript":
+                    kept.extend(lines)
+                    continue
+
+                for feature in sort_features(lines, sort):
+                    out.write(feature.to_line(attribute_sep) + line_ending)
+
+            for feature in sort_features(kept, sort):
+                out.write(feature.to_line(attribute_sep) + line_ending)
+
+        if store is not None:
+            store.close()
+
+        write_gxf([], gxf, attribute_sep, line_ending, sorted(pragmas))
+        with open(body, "r", newline="") as src, open(gxf, "a", newline="") as dst:
+            shutil.copyfileobj(src, dst)
+
+    warnings = RunWarnings.connect(path)
+    for code, transcripts in codons.items():
+        warnings.add(
+            code,
+            sorted(transcripts),
+            f"codon features could not be inferred from the CDS of {gxf}, "
+            "treat these models cautiously",
+        )
+
+    if trim_partial:
+        log.record(
+            f"trimmed {counts['trimmed']} CDS with incomplete terminal codons "
+            "(tagged cds_start_NF/cds_end_NF)"
+        )
+
+    if genome:
+        log.record(f"incomplete transcripts by NF tag: {dict(incomplete)}")
+
+    if relations:
+        log.record(f"orthology relation added to {counts['orthology']} transcripts")
+
+    for key, values, name in annotations:
+        if values:
+            log.record(f"{name} added to {counts[key]} transcripts")
+
+    if alignments and fmt == "gff":
+        log.record(
+            f"Gap/Target alignment attributes added to {counts['gaps']} transcripts"
+        )
+        warnings.add(
+            "alignment_cds_mismatch",
+            sorted(mismatched),
+            "codon alignment does not cover the CDS, no Gap attribute written",
+        )
+
+    if dedup_exons and fmt == "gff":
+        log.record(f"merged {counts['merged']} exons shared between isoforms")
+
+    if fmt == "gff":
+        log.record(
+            f"gff tree rebuilt: {counts['synthesized']} missing genes/transcripts "
+            f"synthesized, {len(orphans)} features without Parent dropped"
+        )
+        warnings.add(
+            "gff_orphan_feature",
+            sorted({f"{x.feature}:{x.chrom}:{x.start}-{x.end}" for x in orphans}),
+            "feature without Parent dropped from the .gff",
+        )
+        if profile is not None:
+            log.record(
+                f"refseq attribute profile added to {counts['profiled']} features"
+            )
+
+    if max_attribute_length:
+        warnings.add(
+            "limit_max_attribute_length",
+            sorted(set(dropped)),
+            f"attribute longer than {max_attribute_length} characters dropped",
+        )
+
+    log.record(
+        f"refined {counts['features']} features in {gxf} "
+        f"(phase convention: {phase_convention}, features: {features})"
+    )
+
+    return gxf
@@ -0,0 +1,245 @@
+#!/usr/bin/env python3
+
+
+"""
+A module to move an annotation onto a revised assembly.
+
+The revision is described by an AGP file (new scaffolds built from the
+old sequences, `W` lines) or by a tab-separated offset table with the
+columns old_chrom, old_start, old_end, new_chrom, new_start, strand
+(0-based, half-open). Each model is moved as a whole: models whose
+blocks do not all fall in a single segment cannot be remapped and are
+reported instead of being split across scaffolds.
+"""
+
+
+import os
+from bisect import bisect_right
+from collections import defaultdict
+from constants import Constants
+from logger import Log
+from modules.bed import read_bed
+from modules.chrom_spill import ChromSpillWriter
+from modules.formats import detect_format
+from modules.gxf import gxf_format, read_gxf
+from modules.run_warnings import RunWarnings
+
+
+__author__ = "Alejandro Gonzales-Irribarren"
+__email__ = "jose.gonzalesdezavala1@unmsm.edu.pe"
+__github__ = "https://github.com/alejandrogzi"
+__version__ = "0.7.0-devel"
+
+
+FLIP = {"+": "-", "-": "+"}
+
+
+def read_segments(path: str) -> dict:
+    """
+    Reads an AGP file or an offset table into segments per old sequence
+
+    @type path: str
+    @param path: path to the .agp file or offset table
+    @rtype: dict
+    @return: old chrom -> sorted [(old_start, old_end, new_chrom, new_start, strand)]
+    """
+    segments = defaultdict(list)
+
+    with open(path, "r") as f:
+        for line in f:
+            if not line.strip() or line.startswith("#"):
+                continue
+            fields = line.rstrip("\n").split("\t")
+            if len(fields) >= 9 and fields[4] not in ("N", "U"):
+                # AGP: object, beg, end, part, type, component, beg, end, orientation
+                start, end = int(fields[6]) - 1, int(fields[7])
+                strand = "-" if fields[8] == "-" else "+"
+                segments[fields[5]].append(
+                    (start, end, fields[0], int(fields[1]) - 1, strand)
+                )
+            elif len(fields) == 6:
+                segments[fields[0]].append(
+                    (int(fields[1]), int(fields[2]), fields[3], int(fields[4]), fields[5])
+                )
+
+    return {chrom: sorted(x) for chrom, x in segments.items()}
+
+
+def remap_interval(segments: dict, chrom: str, start: int, end: int) -> tuple:
+    """
+    Moves a 0-based half-open interval to the new assembly
+
+    @type segments: dict
+    @param segments: see read_segments
+    @type chrom: str
+    @param chrom: old sequence name
+    @type start: int
+    @param start: old start
+    @type end: int
+    @param end: old end
+    @rtype: tuple
+    @return: (segment, new chrom, new start, new end, strand), None if
+        the interval is not inside a single segment
+    """
+    parts = segments.get(chrom, [])
+    i = bisect_right(parts, (start, float("inf"))) - 1
+    if i < 0 or end > parts[i][1]:
+        return None
+
+    old_start, old_end, new_chrom, new_start, strand = parts[i]
+    if strand == "+":
+        return i, new_chrom, new_start + start - old_start, new_start + end - old_start, strand
+
+    return i, new_chrom, new_start + old_end - end, new_start + old_end - start, strand
+
+
+def remap_record(segments: dict, record):
+    """
+    Moves a BED12 record to the new assembly in place, False if it
+    cannot be remapped as a whole
+
+    @type segments: dict
+    @param segments: see read_segments
+    @type record: BedRecord
+    @param record: projection
+    """
+    blocks = [remap_interval(segments, record.chrom, s, e) for s, e in record.exons()]
+    if any(x is None for x in blocks) or len({x[0] for x in blocks}) > 1:
+        return False
+
+    _, chrom, start, end, strand = remap_interval(
+        segments, record.chrom, record.start, record.end
+    )
+    exons = sorted((x[2], x[3]) for x in blocks)
+    thick = remap_interval(segments, record.chrom, record.thick_start, record.thick_end)
+
+    record.chrom, record.start, record.end = chrom, start, end
+    record.thick_start, record.thick_end = (thick[2], thick[3]) if thick else (start, start)
+    if strand == "-":
+        record.strand = FLIP.get(record.strand, record.strand)
+    record.sizes = [e - s for s, e in exons]
+    record.offsets = [s - start for s, _ in exons]
+
+    return True
+
+
+def remap_bed(segments: dict, bed: str, output: str) -> list:
+    """
+    Writes a .bed file moved to the new assembly, returning the
+    projections that could not be remapped
+
+    @type segments: dict
+    @param segments: see read_segments
+    @type bed: str
+    @param bed: path to .bed file
+    @type output: str
+    @param output: path to the remapped .bed file
+    """
+    failed = []
+
+    with open(output, "w") as out:
+        for record in read_bed(bed):
+            if remap_record(segments, record):
+                out.write(record.to_line() + "\n")
+            else:
+                failed.append(record.name)
+
+    return failed
+
+
+def model_id(feature) -> str:
+    """The transcript (or gene) a .gtf/.gff feature is moved with"""
+    return (
+        feature.transcript_id
+        or feature.attributes.get("ID")
+        or feature.attributes.get("gene_id")
+    )
+
+
+def remap_gxf(segments: dict, gxf: str, output: str, tmp: str, fmt: str = None) -> list:
+    """
+    Writes a .gtf/.gff file moved to the new assembly. Transcripts are
+    moved as a whole, genes follow their transcripts; returns the
+    transcripts (or genes) that could not be remapped. The file is read
+    twice (targets per model, then the moves) and the moved features are
+    sorted per chromosome through spill files, so only the per-model
+    targets are held in memory
+
+    @type segments: dict
+    @param segments: see read_segments
+    @type gxf: str
+    @param gxf: path to .gtf/.gff file
+    @type output: str
+    @param output: path to the remapped .gtf/.gff file
+    @type tmp: str
+    @param tmp: directory for the spill files
+    @type fmt: str
+    @param fmt: gtf or gff, instead of the extension
+    """
+    groups = defaultdict(set)
+    for feature in read_gxf(gxf, fmt):
+        hit = remap_interval(segments, feature.chrom, feature.start - 1, feature.end)
+        groups[model_id(feature)].add(hit[0] if hit else None)
+
+    failed = sorted(k for k, hits in groups.items() if None in hits or len(hits) > 1)
+    excluded = set(failed)
+
+    header = "##gff-version 3\n" if gxf_format(output, fmt) == "gff" else ""
+    with ChromSpillWriter(output, tmp, header) as out:
+        for feature in read_gxf(gxf, fmt):
+            if model_id(feature) in excluded:
+                continue
+            _, feature.chrom, start, feature.end, strand = remap_interval(
+                segments, feature.chrom, feature.start - 1, feature.end
+            )
+            feature.start = start + 1
+            if strand == "-":
+                feature.strand = FLIP.get(feature.strand, feature.strand)
+            out.write(feature.chrom, feature.start, feature.to_line() + "\n")
+
+    return failed
+
+
+def remap_annotation(path: str, annotation: str, agp: str, output: str = None) -> str:
+    """
+    Moves a .bed or .gtf/.gff annotation to a revised assembly described
+    by an AGP file or offset table, listing unmappable models as warnings
+
+    @type path: str
+    @param path: path to the results directory
+    @type annotation: str
+    @param annotation: path to the .bed/.gtf/.gff annotation
+    @type agp: str
+    @param agp: path to the .agp file or offset table
+    @type output: str
+    @param output: path to the remapped file (default: *.remapped.<ext>)
+    """
+
+    log = Log.connect(path, Constants.FileNames.LOG)
+
+    segments = read_segments(agp)
+    fmt = detect_format(annotation)
+    if not output:
+        stem, ext = os.path.splitext(os.path.basename(annotation))
+        output = os.path.join(path, f"{stem}.{Constants.FileNames.REMAPPED}{ext}")
+
+    if fmt == "bed":
+        failed = remap_bed(segments, annotation, output)
+    else:
+        tmp = os.path.join(path, Constants.TEMP)
+        failed = remap_gxf(segments, annotation, output, tmp, fmt)
+
+    RunWarnings.connect(path).add(
+        "unmappable_model",
+        failed,
+        f"model does not fall in a single segment of {agp}, left out of {output}",
+    )
+
+    info = [
+        f"remapped {annotation} with {sum(len(x) for x in segments.values())} segments of {agp}",
+        f"{len(failed)} models could not be remapped, remapped annotation written to {output}",
+    ]
+
+    [log.record(i) for i in info]
+
+    return output
@@ -0,0 +1,110 @@
+#!/usr/bin/env python3
+
+
+"""
+A module to flag projections that look like retrocopies.
+
+A processed pseudogene or retrogene is a spliced mRNA inserted back into
+the genome: its projection has no introns while the reference ortholog
+is multi-exonic. Gaps shorter than Constants.RETROCOPY_MAX_INTRON are
+not counted as introns, since TOGA splits exons around small indels.
+The signature is alignment-free (only the exon structures of both
+annotations are compared), so candidates need to be reviewed.
+"""
+
+
+import pandas as pd
+from constants import Constants
+from logger import Log
+from modules.bed import read_bed
+from modules.convert_from_bed import bed_to_gff
+from modules.rank_projections import reference_transcript
+from modules.run_warnings import RunWarnings
+from modules.utils import output_stem
+
+
+__author__ = "Alejandro Gonzales-Irribarren"
+__email__ = "jose.gonzalesdezavala1@unmsm.edu.pe"
+__github__ = "https://github.com/alejandrogzi"
+__version__ = "0.7.0-devel"
+
+
+def intron_count(record, max_gap: int = None) -> int:
+    """
+    Returns the number of introns of a projection, ignoring gaps between
+    exons shorter than max_gap
+
+    @type record: BedRecord
+    @param record: projection or reference transcript
+    @type max_gap: int
+    @param max_gap: shortest gap counted as an intron
+    """
+    max_gap = Constants.RETROCOPY_MAX_INTRON if max_gap is None else max_gap
+    exons = record.exons()
+
+    return sum(
+        exons[i][0] - exons[i - 1][1] >= max_gap for i in range(1, len(exons))
+    )
+
+
+def flag_retrocopies(
+    path: str,
+    bed: str,
+    table: pd.DataFrame,
+    reference: str,
+    isoforms: str = None,
+    gff: bool = False,
+) -> pd.DataFrame:
+    """
+    Adds a `retrocopy_candidate` column to the query table, true for
+    intron-less projections of multi-exonic reference transcripts, and
+    writes them to their own .bed (and .gff) file
+
+    @type path: str
+    @param path: path to the results directory
+    @type bed: str
+    @param bed: path to .bed file
+    @type table: pd.DataFrame
+    @param table: query table
+    @type reference: str
+    @param reference: path to the reference .bed file given to TOGA
+    @type isoforms: str
+    @param isoforms: path to the isoforms file, for the .gff
+    @type gff: bool
+    @param gff: also convert the candidates to .gff
+    """
+
+    log = Log.connect(path, Constants.FileNames.LOG)
+
+    introns = {x.name: intron_count(x) for x in read_bed(reference)}
+    candidates = [
+        x
+        for x in read_bed(bed)
+        if intron_count(x) == 0 and introns.get(reference_transcript(x.name), 0) > 0
+    ]
+    names = {x.name for x in candidates}
+
+    f = f"{output_stem(bed)}.{Constants.FileNames.RETROCOPY_BED}"
+    with open(f, "w") as out:
+        for record in candidates:
+            out.write(record.to_line() + "\n")
+
+    table["retrocopy_candidate"] = table["transcripts"].isin(names)
+
+    RunWarnings.connect(path).add(
+        "retrocopy_candidate",
+        sorted(names),
+        "intron-less projection of a multi-exonic reference transcript, possible retrocopy",
+    )
+
+    info = [
+        f"{len(names)} intron-less projections of multi-exonic reference transcripts flagged as retrocopy candidates",
+        f"retrocopy candidates written to {f}",
+    ]
+
+    [log.record(i) for i in info]
+
+    if gff and candidates:
+        bed_to_gff(path, f, isoforms)
+
+    return table
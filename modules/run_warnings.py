@@ -0,0 +1,69 @@
+#!/usr/bin/env python3
+
+
+"""
+A module to collect structured warnings during a postoga run.
+
+Warnings are appended to a tab-separated file in the results directory
+(code, id, message) so users can list the models to treat cautiously
+without grepping the log. A summary of each batch is also logged.
+
+``` python
+from modules.run_warnings import RunWarnings
+
+warnings = RunWarnings.connect("path/to/results")
+warnings.add("missing_stop_codon", ["ENST00000355624.10"], "CDS does not end with a stop codon")
+```
+"""
+
+
+import os
+from constants import Constants
+from logger import Log
+
+
+__author__ = "Alejandro Gonzales-Irribarren"
+__email__ = "jose.gonzalesdezavala1@unmsm.edu.pe"
+__github__ = "https://github.com/alejandrogzi"
+__version__ = "0.7.0-devel"
+
+
+class RunWarnings:
+    """Structured warnings collector for postoga."""
+
+    def __init__(self, path: str):
+        self.path = path
+        self.file = os.path.join(path, Constants.FileNames.WARNINGS)
+
+    def start(self):
+        with open(self.file, "w") as f:
+            f.write("code\tid\tmessage\n")
+
+    @classmethod
+    def connect(cls, path):
+        warnings = cls(path)
+        if not os.path.exists(warnings.file):
+            warnings.start()
+        return warnings
+
+    def add(self, code: str, ids: list, message: str):
+        """
+        Records the same warning for a list of ids
+
+        @type code: str
+        @param code: short machine-readable warning code
+        @type ids: list
+        @param ids: affected transcripts/genes
+        @type message: str
+        @param message: human-readable explanation
+        """
+        if not ids:
+            return
+
+        with open(self.file, "a") as f:
+            for record in ids:
+                f.write(f"{code}\t{record}\t{message}\n")
+
+        Log.connect(self.path, Constants.FileNames.LOG).warning(
+            f"{len(ids)} {code}: {message} (listed in {self.file})"
+        )
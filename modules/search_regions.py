@@ -0,0 +1,108 @@
+#!/usr/bin/env python3
+
+
+"""
+A module to write second-pass search regions for Lost and Missing genes.
+
+The query loci TOGA projected a Lost/Missing gene to (through its
+chains) are merged per gene and contig, padded by a flank and written
+as a BED6 file, so exonerate/miniprot can re-annotate those windows
+instead of the whole assembly.
+
+Regions are only derived from existing projections: the chains are not
+part of TOGA's results directory, so a Lost/Missing gene without any
+projected locus gets no region. These genes are logged and listed as
+`no_search_locus` run warnings; genes TOGA never projected at all are
+reported by --never_projected.
+"""
+
+
+import os
+import pandas as pd
+from constants import Constants
+from logger import Log
+from modules.annotation_set import merge_intervals
+from modules.bed import read_bed
+from modules.diff_runs import gene_classes
+from modules.run_warnings import RunWarnings
+from modules.utils import chrom_sizes_reader, natural_key
+
+
+__author__ = "Alejandro Gonzales-Irribarren"
+__email__ = "jose.gonzalesdezavala1@unmsm.edu.pe"
+__github__ = "https://github.com/alejandrogzi"
+__version__ = "0.7.0-devel"
+
+
+def search_regions(
+    path: str,
+    table: pd.DataFrame,
+    flank: int = Constants.SEARCH_FLANK,
+    chrom_sizes: str = None,
+    classes: set = Constants.SEARCH_CLASSES,
+) -> str:
+    """
+    Writes the padded query regions of genes with a Lost/Missing best class,
+    genes without a projected locus are reported as warnings instead
+
+    @type path: str
+    @param path: path to the results directory
+    @type table: pd.DataFrame
+    @param table: query table
+    @type flank: int
+    @param flank: bp added on each side of the projected locus
+    @type chrom_sizes: str
+    @param chrom_sizes: optional chrom.sizes file to clip regions to contigs
+    @type classes: set
+    @param classes: gene classes to search again
+    @rtype: str
+    @return: path to the search regions .bed file
+    """
+
+    log = Log.connect(path, Constants.FileNames.LOG)
+
+    targets = {gene for gene, cls in gene_classes(table).items() if cls in classes}
+    gene_of = {
+        tx: gene
+        for tx, gene in zip(table["transcripts"], table["t_gene"])
+        if gene in targets
+    }
+    sizes = chrom_sizes_reader(chrom_sizes) if chrom_sizes else {}
+
+    # The whole TOGA annotation: filters may have dropped these projections
+    loci = {}
+    for record in read_bed(os.path.join(path, Constants.FileNames.BED)):
+        if record.name in gene_of:
+            key = (gene_of[record.name], record.chrom, record.strand)
+            loci.setdefault(key, []).append(
+                (max(record.start - flank, 0), record.end + flank)
+            )
+
+    rows = []
+    for (gene, chrom, strand), intervals in loci.items():
+        for start, end in merge_intervals(intervals):
+            end = min(end, sizes.get(chrom, end))
+            rows.append((chrom, start, end, gene, strand))
+
+    rows.sort(key=lambda x: (natural_key(x[0]), x[1], x[2]))
+
+    f = os.path.join(path, Constants.FileNames.SEARCH_REGIONS)
+    with open(f, "w") as out:
+        for chrom, start, end, gene, strand in rows:
+            out.write(f"{chrom}\t{start}\t{end}\t{gene}\t0\t{strand}\n")
+
+    unplaced = sorted(targets - {x[3] for x in rows})
+    RunWarnings.connect(path).add(
+        "no_search_locus",
+        unplaced,
+        "gene has no projected locus, no search region could be derived for it",
+    )
+
+    info = [
+        f"{len(targets)} genes classified as {','.join(sorted(classes))} to search again",
+        f"{len(unplaced)} of them have no projected locus to search",
+        f"{len(rows)} search regions (flank: {flank} bp) written to {f}",
+    ]
+    [log.record(i) for i in info]
+
+    return f
@@ -0,0 +1,119 @@
+#!/usr/bin/env python3
+
+
+"""
+A module to translate TOGA codon sequences to protein.
+
+TOGA codon .fasta records are in-frame alignments: codons made only of
+gaps ('---') are deletions in the query and are dropped, codons with a
+frameshift marker ('!') or a partial gap become FRAMESHIFT, and internal
+stop codons (inactivating mutations) are kept as '*' or masked as 'X'.
+Any genetic code in Constants.GENETIC_CODES can be used.
+
+``` python
+from modules.seq import translate
+
+translate("ATG---AAA!TGTAA", code="standard")  # 'MK!*'
+```
+"""
+
+
+from constants import Constants
+from logger import Log
+from modules.fasta import codon_table, read_fasta, write_fasta
+
+
+__author__ = "Alejandro Gonzales-Irribarren"
+__email__ = "jose.gonzalesdezavala1@unmsm.edu.pe"
+__github__ = "https://github.com/alejandrogzi"
+__version__ = "0.7.0-devel"
+
+
+GAP = "-"
+FRAMESHIFT = "!"
+
+
+def mask_internal_stops(protein: str) -> str:
+    """Writes the internal stops of a protein (any '*' but the last residue) as 'X'"""
+    return protein[:-1].replace("*", "X") + protein[-1:]
+
+
+def translate(seq: str, code: str = "standard", mask_stops: bool = False) -> str:
+    """
+    Translates an in-frame TOGA codon sequence: gap codons are dropped,
+    codons with frameshifts or partial gaps become '!', and internal
+    stops are kept as '*' or masked as 'X'
+
+    @type seq: str
+    @param seq: codon sequence (gaps and '!' allowed)
+    @type code: str
+    @param code: genetic code in Constants.GENETIC_CODES
+    @type mask_stops: bool
+    @param mask_stops: write internal stop codons as 'X'
+    """
+    table = codon_table(code)
+    seq = seq.upper()
+    protein = []
+
+    for i in range(0, len(seq) - len(seq) % 3, 3):
+        codon = seq[i : i + 3]
+        if codon == GAP * 3:
+            continue
+        if FRAMESHIFT in codon or GAP in codon:
+            protein.append(FRAMESHIFT)
+        else:
+            protein.append(table.get(codon, "X"))
+
+    protein = "".join(protein)
+
+    return mask_internal_stops(protein) if mask_stops else protein
+
+
+def translate_fasta(
+    path: str,
+    fasta: str,
+    code: str = "standard",
+    mask_stops: bool = False,
+    output: str = None,
+) -> str:
+    """
+    Writes the protein translation of every record of a codon .fasta file
+
+    @type path: str
+    @param path: path to the results directory
+    @type fasta: str
+    @param fasta: path to the codon .fasta file
+    @type code: str
+    @param code: genetic code in Constants.GENETIC_CODES
+    @type mask_stops: bool
+    @param mask_stops: write internal stop codons as 'X'
+    @type output: str
+    @param output: path to the protein .fasta (default: next to the input)
+    """
+
+    log = Log.connect(path, Constants.FileNames.LOG)
+
+    stem = fasta.rsplit(".fasta", 1)[0]
+    output = output or f"{stem}.{Constants.FileNames.TRANSLATED}"
+    records, stops, frameshifts = 0, 0, 0
+
+    with open(output, "w") as out:
+        for header, seq in read_fasta(fasta):
+            protein = translate(seq, code)
+            stops += "*" in protein[:-1]
+            if mask_stops:
+                protein = mask_internal_stops(protein)
+            frameshifts += FRAMESHIFT in protein
+            write_fasta(out, header, protein)
+            records += 1
+
+    info = [
+        f"translated {records} sequences of {fasta} with the {code} genetic code "
+        f"to {output}",
+        f"{stops} with internal stop codons ({'masked' if mask_stops else 'kept'}), "
+        f"{frameshifts} with frameshifts",
+    ]
+
+    [log.record(i) for i in info]
+
+    return output
@@ -0,0 +1,112 @@
+#!/usr/bin/env python3
+
+
+"""
+A module to compute sequence checksums for identity checks across databases.
+
+Two digests are computed on the normalized sequence (upper-cased,
+trailing stop `*` removed, as Ensembl and RefSeq proteins are stored):
+
+    md5   hex MD5, as in Ensembl/UniParc checksums
+    sq    GA4GH refget sha512t24u identifier (SQ.<base64url>)
+
+Both can be used in header templates ({md5}, {sq}) and are written
+per sequence to a mapping table with --digests.
+"""
+
+
+import base64
+import hashlib
+import os
+from constants import Constants
+from logger import Log
+from modules.fasta import read_fasta
+
+
+__author__ = "Alejandro Gonzales-Irribarren"
+__email__ = "jose.gonzalesdezavala1@unmsm.edu.pe"
+__github__ = "https://github.com/alejandrogzi"
+__version__ = "0.7.0-devel"
+
+
+DIGEST_FIELDS = ("md5", "sq")
+
+
+def normalize(seq: str) -> bytes:
+    """Returns the bytes a sequence is digested as"""
+    return seq.upper().rstrip("*").encode("ascii")
+
+
+def md5_digest(seq: str) -> str:
+    """
+    Returns the hex MD5 of a normalized sequence
+
+    @type seq: str
+    @param seq: nucleotide or protein sequence
+    """
+    return hashlib.md5(normalize(seq)).hexdigest()
+
+
+def sq_digest(seq: str) -> str:
+    """
+    Returns the GA4GH refget identifier (SQ. + base64url of the first 24
+    bytes of SHA-512) of a normalized sequence
+
+    @type seq: str
+    @param seq: nucleotide or protein sequence
+    """
+    digest = hashlib.sha512(normalize(seq)).digest()[:24]
+
+    return "SQ." + base64.urlsafe_b64encode(digest).decode("ascii")
+
+
+def header_digests(template: str, seq: str) -> dict:
+    """
+    Returns the digest fields a header template uses, so sequences are
+    only hashed when their headers need it
+
+    @type template: str
+    @param template: header template with {field} placeholders
+    @type seq: str
+    @param seq: sequence of the record
+    """
+    if not template:
+        return {}
+
+    digests = {"md5": md5_digest, "sq": sq_digest}
+
+    return {
+        field: digests[field](seq)
+        for field in DIGEST_FIELDS
+        if "{" + field + "}" in template
+    }
+
+
+def write_digests(path: str, fasta: str) -> str:
+    """
+    Writes a (id, length, md5, sq) table for the records of a .fasta
+    file, id being the first word of the header
+
+    @type path: str
+    @param path: path to the results directory
+    @type fasta: str
+    @param fasta: path to .fasta file
+    """
+
+    log = Log.connect(path, Constants.FileNames.LOG)
+
+    f = os.path.join(
+        path, f"{os.path.basename(fasta)}.{Constants.FileNames.DIGESTS}"
+    )
+    n = 0
+    with open(f, "w") as out:
+        out.write("id\tlength\tmd5\tsq\n")
+        for header, seq in read_fasta(fasta):
+            name = header.split()[0] if header.strip() else header
+            length = len(seq.rstrip("*"))
+            out.write(f"{name}\t{length}\t{md5_digest(seq)}\t{sq_digest(seq)}\n")
+            n += 1
+
+    log.record(f"md5/refget digests of {n} sequences of {fasta} written to {f}")
+
+    return f
@@ -0,0 +1,489 @@
+#!/usr/bin/env python3
+
+
+"""
+A module to query sequences from an indexed .fasta or a .2bit file.
+
+SeqStore opens a genome once and fetches regions by offset, so
+workflows with many small lookups (a few transcripts, a handful of
+regions) do not scan or load the whole file. A samtools-style .fai
+index is created next to the .fasta if it is missing; large files are
+indexed in parallel chunks that start at header lines, so a '>' inside
+a description never splits a record.
+"""
+
+
+import os
+import struct
+from bisect import bisect_right
+from collections import deque
+from concurrent.futures import ProcessPoolExecutor
+from constants import Constants
+from modules.bed import read_bed
+from modules.errors import PostogaKeyError, PostogaValueError
+from modules.fasta import reverse_complement, write_fasta
+
+
+__author__ = "Alejandro Gonzales-Irribarren"
+__email__ = "jose.gonzalesdezavala1@unmsm.edu.pe"
+__github__ = "https://github.com/alejandrogzi"
+__version__ = "0.7.0-devel"
+
+
+TWOBIT_SIGNATURE = 0x1A412743
+TWOBIT_BASES = "TCAG"
+# The four bases packed in each byte value of a .2bit sequence
+TWOBIT_BYTES = [
+    "".join(TWOBIT_BASES[(byte >> shift) & 3] for shift in (6, 4, 2, 0))
+    for byte in range(256)
+]
+
+
+def record_boundaries(fasta: str, chunk_size: int) -> list:
+    """
+    Splits a .fasta file into byte ranges of about chunk_size that start
+    at a header line ('>' right after a newline), never inside a record
+    or a description; a record longer than chunk_size is one range
+
+    @type fasta: str
+    @param fasta: path to .fasta file
+    @type chunk_size: int
+    @param chunk_size: target size of each range in bytes
+    @rtype: list
+    @return: (start, end) byte ranges covering the file
+    """
+    size = os.path.getsize(fasta)
+    starts = [0]
+
+    with open(fasta, "rb") as f:
+        position = chunk_size
+        while position < size:
+            # Look for the next '\n>' from one byte before the cut, so a
+            # cut right at a header start is kept
+            f.seek(position - 1)
+            found, scanned = -1, position - 1
+            while found < 0:
+                block = f.read(1 << 16)
+                if len(block) < 2:
+                    break
+                found = block.find(b"\n>")
+                if found < 0:
+                    f.seek(-1, os.SEEK_CUR)
+                    scanned += len(block) - 1
+            if found < 0:
+                break
+            starts.append(scanned + found + 1)
+            position = starts[-1] + chunk_size
+
+    return list(zip(starts, starts[1:] + [size]))
+
+
+def index_chunk(fasta: str, start: int, end: int) -> list:
+    """
+    Returns the .fai entries [name, length, offset, bases per line, bytes
+    per line] of the records starting in a byte range (see
+    record_boundaries), reading it line by line. As with samtools, every
+    line of a record but the last must have the same width, offsets of
+    other records cannot be computed
+
+    @type fasta: str
+    @param fasta: path to .fasta file
+    @type start: int
+    @param start: first byte of the range
+    @type end: int
+    @param end: end of the range (exclusive)
+    """
+    entries, current, ended = [], None, False
+
+    with open(fasta, "rb") as f:
+        f.seek(start)
+        offset = start
+        while offset < end:
+            line = f.readline()
+            if not line:
+                break
+            if line.startswith(b">"):
+                fields = line[1:].split()
+                name = fields[0].decode() if fields else ""
+                current, ended = [name, 0, offset + len(line), 0, 0], False
+                entries.append(current)
+            elif current is not None and line.strip():
+                bases = len(line.rstrip(b"\r\n"))
+                if current[3] == 0:
+                    current[3], current[4] = bases, len(line)
+                elif ended or bases > current[3] or len(line) > current[4]:
+                    raise PostogaValueError(
+                        f"{current[0]} in {fasta} has lines of different widths, "
+                        "rewrap it (e.g. postoga reformat) before indexing",
+                        current[0],
+                    )
+                # A shorter line (or a blank one) must be the last of the record
+                ended = bases < current[3] or len(line) < current[4]
+                current[1] += bases
+            elif current is not None and current[1]:
+                ended = True
+            offset += len(line)
+
+    return entries
+
+
+def fai_entries(fasta: str, threads: int = 1, chunk_size: int = None):
+    """
+    Yields the .fai entries of a .fasta file in file order. With several
+    threads, chunks are indexed by a process pool with at most two
+    chunks per worker in flight, so memory stays bounded on multi-GB files
+
+    @type fasta: str
+    @param fasta: path to .fasta file
+    @type threads: int
+    @param threads: number of worker processes
+    @type chunk_size: int
+    @param chunk_size: bytes per chunk (default: Constants.FAI_CHUNK_SIZE)
+    """
+    chunks = record_boundaries(fasta, chunk_size or Constants.FAI_CHUNK_SIZE)
+
+    if threads <= 1 or len(chunks) == 1:
+        for start, end in chunks:
+            yield from index_chunk(fasta, start, end)
+        return
+
+    with ProcessPoolExecutor(max_workers=threads) as pool:
+        pending = deque()
+        for start, end in chunks:
+            if len(pending) >= 2 * threads:
+                yield from pending.popleft().result()
+            pending.append(pool.submit(index_chunk, fasta, start, end))
+        while pending:
+            yield from pending.popleft().result()
+
+
+def write_fai(
+    fasta: str, fai: str = None, threads: int = 1, chunk_size: int = None
+) -> str:
+    """
+    Writes a samtools-style .fai index (name, length, offset,
+    bases per line, bytes per line) for a .fasta file
+
+    @type fasta: str
+    @param fasta: path to .fasta file
+    @type fai: str
+    @param fai: path to the index (default: fasta + .fai)
+    @type threads: int
+    @param threads: number of worker processes (see fai_entries)
+    @type chunk_size: int
+    @param chunk_size: bytes per chunk (default: Constants.FAI_CHUNK_SIZE)
+    """
+    fai = fai or f"{fasta}.fai"
+
+    try:
+        with open(fai, "w") as out:
+            for entry in fai_entries(fasta, threads, chunk_size):
+                out.write("\t".join(str(x) for x in entry) + "\n")
+    except PostogaValueError:
+        # A partial index would look up to date on the next run
+        os.remove(fai)
+        raise
+
+    return fai
+
+
+def fai_columns(fai: str) -> tuple:
+    """
+    Reads a .fai index into its name column and its length, offset, line
+    bases and line bytes columns (as ints), in file order
+
+    @type fai: str
+    @param fai: path to .fai file
+    """
+    with open(fai, "r") as f:
+        text = f.read()
+
+    # Split the whole index at once and read it column by column, a
+    # line-by-line parse dominates the load of 1M+ record indexes
+    columns = text[: text.find("\n")].count("\t") + 1
+    fields = text.split()
+
+    return fields[0::columns], [map(int, fields[i::columns]) for i in range(1, 5)]
+
+
+def read_fai_entries(fai: str) -> list:
+    """
+    Reads a .fai index into (name, length, offset, line bases, line
+    bytes) tuples in file order, repeated names included (TOGA .fasta
+    files share the name of the reference and query records)
+
+    @type fai: str
+    @param fai: path to .fai file
+    """
+    names, values = fai_columns(fai)
+
+    return list(zip(names, *values))
+
+
+def read_fai(fai: str) -> dict:
+    """
+    Reads a .fai index into a name -> (length, offset, line bases,
+    line bytes) dictionary
+
+    @type fai: str
+    @param fai: path to .fai file
+    """
+    names, values = fai_columns(fai)
+
+    return dict(zip(names, zip(*values)))
+
+
+class FaiReader:
+    """A class to fetch regions from a .fasta file through its .fai index."""
+
+    def __init__(self, fasta: str, threads: int = 1) -> None:
+        fai = f"{fasta}.fai"
+        if not os.path.exists(fai) or os.path.getmtime(fai) < os.path.getmtime(
+            fasta
+        ):
+            write_fai(fasta, fai, threads)
+
+        self.entries = read_fai_entries(fai)
+        self.index = {entry[0]: entry[1:] for entry in self.entries}
+        self.handle = open(fasta, "rb")
+
+    def lengths(self) -> dict:
+        return {name: entry[0] for name, entry in self.index.items()}
+
+    def fetch(self, chrom: str, start: int, end: int) -> str:
+        length, offset, bases, width = self.index[chrom]
+        start, end = max(start, 0), min(end, length)
+        if start >= end:
+            return ""
+
+        first = offset + start // bases * width + start % bases
+        last = offset + (end - 1) // bases * width + (end - 1) % bases
+
+        self.handle.seek(first)
+        raw = self.handle.read(last - first + 1)
+
+        return raw.replace(b"\n", b"").replace(b"\r", b"").decode()
+
+    def header(self, offset: int) -> str:
+        """Returns the header line (without '>') ending right before offset"""
+        start = max(offset - 4096, 0)
+        self.handle.seek(start)
+        raw = self.handle.read(offset - start).rstrip(b"\r\n")
+
+        # The line start, not the last '>': descriptions may contain '>'
+        line = raw[raw.rfind(b"\n") + 1 :]
+
+        return (line[1:] if line.startswith(b">") else line).decode()
+
+    def records(self, names: set):
+        """
+        Yields the (header, sequence) pairs of the records named in names,
+        in file order, reading only those records
+
+        @type names: set
+        @param names: record names (first word of the header)
+        """
+        for name, length, offset, bases, width in self.entries:
+            if name not in names:
+                continue
+            seq = b""
+            if length:
+                self.handle.seek(offset)
+                raw = self.handle.read(length + (length // bases + 1) * (width - bases))
+                seq = raw.replace(b"\n", b"").replace(b"\r", b"")[:length]
+            yield self.header(offset), seq.decode()
+
+    def close(self) -> None:
+        self.handle.close()
+
+
+def overlay(seq: str, start: int, end: int, blocks: list, change) -> str:
+    """
+    Rewrites the parts of a region covered by sorted, non-overlapping
+    blocks (e.g. N or soft-masked blocks of a .2bit sequence)
+
+    @type seq: str
+    @param seq: sequence of the 0-based region [start, end)
+    @type start: int
+    @param start: 0-based start of the region
+    @type end: int
+    @param end: 0-based exclusive end of the region
+    @type blocks: list
+    @param blocks: (start, size) blocks sorted by start
+    @type change: function
+    @param change: function applied to the covered parts
+    """
+    pieces, last = [], 0
+    # The block starting right before the region may overlap it
+    first = max(bisect_right(blocks, (start, float("inf"))) - 1, 0)
+
+    for block_start, size in blocks[first:]:
+        if block_start >= end:
+            break
+        a, b = max(block_start, start) - start, min(block_start + size, end) - start
+        if a < b:
+            pieces += [seq[last:a], change(seq[a:b])]
+            last = b
+
+    return "".join(pieces) + seq[last:]
+
+
+class TwoBitReader:
+    """A class to fetch regions from a UCSC .2bit file."""
+
+    def __init__(self, twobit: str) -> None:
+        self.handle = open(twobit, "rb")
+        self.endian = "<"
+
+        signature = struct.unpack("<I", self.handle.read(4))[0]
+        if signature != TWOBIT_SIGNATURE:
+            self.endian = ">"
+        _, count, _ = self._read("III")
+
+        offsets = {}
+        for _ in range(count):
+            size = self.handle.read(1)[0]
+            name = self.handle.read(size).decode()
+            offsets[name] = self._read("I")[0]
+
+        self.index = {name: self._header(offset) for name, offset in offsets.items()}
+
+    def _read(self, fmt: str) -> tuple:
+        fmt = self.endian + fmt
+        return struct.unpack(fmt, self.handle.read(struct.calcsize(fmt)))
+
+    def _header(self, offset: int) -> tuple:
+        self.handle.seek(offset)
+        length, n_count = self._read("II")
+        n_blocks = list(zip(self._read(f"{n_count}I"), self._read(f"{n_count}I")))
+        mask_count = self._read("I")[0]
+        masks = list(zip(self._read(f"{mask_count}I"), self._read(f"{mask_count}I")))
+        self._read("I")
+
+        return length, self.handle.tell(), n_blocks, masks
+
+    def lengths(self) -> dict:
+        return {name: entry[0] for name, entry in self.index.items()}
+
+    def fetch(self, chrom: str, start: int, end: int) -> str:
+        length, offset, n_blocks, masks = self.index[chrom]
+        start, end = max(start, 0), min(end, length)
+        if start >= end:
+            return ""
+
+        self.handle.seek(offset + start // 4)
+        packed = self.handle.read((end + 3) // 4 - start // 4)
+        seq = "".join(map(TWOBIT_BYTES.__getitem__, packed))
+        seq = seq[start % 4 : start % 4 + end - start]
+
+        seq = overlay(seq, start, end, n_blocks, lambda x: "N" * len(x))
+        return overlay(seq, start, end, masks, str.lower)
+
+    def close(self) -> None:
+        self.handle.close()
+
+
+class SeqStore:
+    """
+    A class to open a genome once and query transcripts and regions
+    from it. Transcripts are resolved through an optional .bed file.
+    """
+
+    def __init__(self, genome: str, bed: str = None) -> None:
+        """
+        Constructs all the necessary attributes for the SeqStore object.
+
+        @type genome: str
+        @param genome: path to a .fasta (indexed on the fly) or .2bit file
+        @type bed: str
+        @param bed: optional .bed file to resolve transcript names
+        """
+
+        if genome.endswith(".2bit"):
+            self.reader = TwoBitReader(genome)
+        else:
+            self.reader = FaiReader(genome)
+
+        self.records = {record.name: record for record in read_bed(bed)} if bed else {}
+
+    def __enter__(self) -> "SeqStore":
+        return self
+
+    def __exit__(self, *args) -> None:
+        self.close()
+
+    def __contains__(self, chrom: str) -> bool:
+        return chrom in self.reader.index
+
+    def lengths(self) -> dict:
+        return self.reader.lengths()
+
+    def get_region(self, chrom: str, start: int, end: int, strand: str = "+") -> str:
+        """
+        Returns the sequence of a 0-based half-open region
+
+        @type chrom: str
+        @param chrom: contig name
+        @type start: int
+        @param start: 0-based start
+        @type end: int
+        @param end: 0-based exclusive end
+        @type strand: str
+        @param strand: + or -, the sequence is reverse complemented for -
+        """
+        seq = self.reader.fetch(chrom, start, end)
+
+        return reverse_complement(seq) if strand == "-" else seq
+
+    def get(self, tx: str, cds: bool = False) -> str:
+        """
+        Returns the spliced sequence of a transcript in the .bed file
+
+        @type tx: str
+        @param tx: transcript (projection) name
+        @type cds: bool
+        @param cds: return the coding sequence only
+        """
+        if tx not in self.records:
+            raise PostogaKeyError(f"{tx} is not in the .bed file of the sequence store", tx)
+        record = self.records[tx]
+        blocks = record.cds() if cds else record.exons()
+        seq = "".join(self.reader.fetch(record.chrom, s, e) for s, e in blocks)
+
+        return reverse_complement(seq) if record.strand == "-" else seq
+
+    def get_many(self, names: list, cds: bool = False) -> dict:
+        """
+        Returns the sequences of several transcripts, skipping the ones
+        missing from the .bed file or lying on contigs not in the genome
+
+        @type names: list
+        @param names: transcript (projection) names
+        @type cds: bool
+        @param cds: return the coding sequences only
+        """
+        return {
+            name: self.get(name, cds)
+            for name in names
+            if name in self.records and self.records[name].chrom in self
+        }
+
+    def extract(self, names: list, output: str, cds: bool = False) -> str:
+        """
+        Writes the sequences of several transcripts to a .fasta file
+
+        @type names: list
+        @param names: transcript (projection) names
+        @type output: str
+        @param output: path to the output .fasta file
+        @type cds: bool
+        @param cds: write the coding sequences only
+        """
+        with open(output, "w") as out:
+            for name, seq in self.get_many(names, cds).items():
+                write_fasta(out, name, seq)
+
+        return output
+
+    def close(self) -> None:
+        self.reader.close()
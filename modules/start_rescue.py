@@ -0,0 +1,123 @@
+#!/usr/bin/env python3
+
+
+"""
+A module to rescue start codons of intact projections missing an ATG.
+
+For projections classified as intact whose CDS does not start with ATG
+in query coordinates, scans downstream in frame for the next ATG within
+a maximum number of codons and moves the CDS start there, mirroring
+what curators do manually. Rescued projections are listed as warnings.
+Mitochondrial projections are skipped, non-ATG starts are valid there.
+"""
+
+
+import pandas as pd
+from constants import Constants
+from logger import Log
+from modules.bed import read_bed
+from modules.fasta import fasta_to_dict, is_mitochondrial, splice
+from modules.run_warnings import RunWarnings
+from modules.utils import output_stem
+
+
+__author__ = "Alejandro Gonzales-Irribarren"
+__email__ = "jose.gonzalesdezavala1@unmsm.edu.pe"
+__github__ = "https://github.com/alejandrogzi"
+__version__ = "0.7.0-devel"
+
+
+def find_start(seq: str, max_codons: int) -> int:
+    """
+    Returns the offset of the first in-frame ATG within max_codons codons
+    of a CDS sequence, None if there is none
+
+    @type seq: str
+    @param seq: spliced CDS sequence in transcript orientation
+    @type max_codons: int
+    @param max_codons: maximum number of codons to scan
+    """
+    seq = seq.upper()
+
+    for i in range(1, max_codons + 1):
+        if seq[i * 3 : i * 3 + 3] == "ATG":
+            return i * 3
+
+    return None
+
+
+def rescue_starts(
+    path: str,
+    bed: str,
+    table: pd.DataFrame,
+    genome: str,
+    max_codons: int,
+    mt_contigs: set = None,
+) -> str:
+    """
+    Writes a copy of a .bed file where intact projections missing an ATG
+    start at the next in-frame ATG (if found within max_codons)
+
+    @type path: str
+    @param path: path to the results directory
+    @type bed: str
+    @param bed: path to .bed file
+    @type table: pd.DataFrame
+    @param table: query table
+    @type genome: str
+    @param genome: path to the query genome .fasta
+    @type max_codons: int
+    @param max_codons: maximum number of codons to scan downstream
+    @type mt_contigs: set
+    @param mt_contigs: extra contig names to treat as mitochondrial
+    """
+
+    log = Log.connect(path, Constants.FileNames.LOG)
+
+    records = list(read_bed(bed))
+    intact = set(table[table["class"] == "I"]["transcripts"])
+    seqs = fasta_to_dict(genome, keep={record.chrom for record in records})
+
+    rescued, unrescued = [], []
+    for record in records:
+        if record.name not in intact or record.chrom not in seqs or not record.cds():
+            continue
+        if is_mitochondrial(record.chrom, mt_contigs):
+            continue
+
+        seq = splice(seqs, record.chrom, record.cds(), record.strand)
+        if seq[:3].upper() == "ATG":
+            continue
+
+        offset = find_start(seq, max_codons)
+        if offset is None:
+            unrescued.append(record.name)
+            continue
+
+        position = record.cds_position(offset)
+        if record.strand == "+":
+            record.thick_start = position
+        else:
+            record.thick_end = position + 1
+        rescued.append(record.name)
+
+    f = f"{output_stem(bed)}.{Constants.FileNames.RESCUED_BED}"
+    with open(f, "w") as out:
+        for record in records:
+            out.write(record.to_line() + "\n")
+
+    warnings = RunWarnings.connect(path)
+    warnings.add(
+        "start_rescued",
+        rescued,
+        f"CDS start moved to the next in-frame ATG (within {max_codons} codons)",
+    )
+    warnings.add(
+        "start_not_rescued",
+        unrescued,
+        f"intact projection without ATG and no in-frame ATG within {max_codons} codons",
+    )
+
+    log.record(f"rescued {len(rescued)} start codons, annotation written to {f}")
+
+    return f
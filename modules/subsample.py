@@ -0,0 +1,117 @@
+#!/usr/bin/env python3
+
+
+"""
+A module to draw a reproducible random subset of the query annotation.
+
+Useful for quick pipeline dry-runs and to build small test datasets
+from real TOGA outputs. Sampling is stratified (e.g. by loss class) so
+the subset keeps the composition of the full annotation.
+"""
+
+
+import os
+import random
+from collections import defaultdict
+from constants import Constants
+from logger import Log
+from modules.bed import read_bed
+from modules.utils import output_stem
+
+
+__author__ = "Alejandro Gonzales-Irribarren"
+__email__ = "jose.gonzalesdezavala1@unmsm.edu.pe"
+__github__ = "https://github.com/alejandrogzi"
+__version__ = "0.7.0-devel"
+
+
+def allocate(sizes: dict, n: int) -> dict:
+    """
+    Splits n draws across strata proportionally to their sizes, using
+    the largest remainder to round
+
+    @type sizes: dict
+    @param sizes: stratum -> number of items
+    @type n: int
+    @param n: total number of draws
+    """
+    total = sum(sizes.values())
+    n = min(n, total)
+    quotas = {k: n * size / total for k, size in sizes.items()}
+    counts = {k: int(q) for k, q in quotas.items()}
+
+    remainders = sorted(quotas, key=lambda k: (counts[k] - quotas[k], str(k)))
+    for k in remainders[: n - sum(counts.values())]:
+        counts[k] += 1
+
+    return counts
+
+
+def subsample(
+    path: str,
+    bed: str,
+    table,
+    n: int,
+    seed: int = 0,
+    stratify_by: str = "class",
+) -> tuple:
+    """
+    Writes a random subset of n projections of a .bed file and its
+    isoforms file. The same seed always gives the same subset
+
+    @type path: str
+    @param path: path to the results directory
+    @type bed: str
+    @param bed: path to .bed file
+    @type table: pd.DataFrame
+    @param table: query table
+    @type n: int
+    @param n: number of projections to keep
+    @type seed: int
+    @param seed: random seed
+    @type stratify_by: str
+    @param stratify_by: query table column to stratify by, None for a plain sample
+    @rtype: tuple
+    @return: (path to the subset .bed, path to the subset isoforms)
+    """
+
+    log = Log.connect(path, Constants.FileNames.LOG)
+
+    records = {record.name: record for record in read_bed(bed)}
+    strata_of = (
+        dict(zip(table["transcripts"], table[stratify_by])) if stratify_by else {}
+    )
+
+    strata = defaultdict(list)
+    for name in sorted(records):
+        strata[strata_of.get(name, "NA")].append(name)
+
+    rng = random.Random(seed)
+    counts = allocate({k: len(v) for k, v in strata.items()}, n)
+    keep = set()
+    for stratum in sorted(strata, key=str):
+        keep.update(rng.sample(strata[stratum], counts[stratum]))
+
+    stem = os.path.basename(output_stem(bed))
+    f = os.path.join(path, f"{stem}.{Constants.FileNames.SUBSAMPLE_BED}")
+    with open(f, "w") as out:
+        for name, record in records.items():
+            if name in keep:
+                out.write(record.to_line() + "\n")
+
+    genes = dict(zip(table["transcripts"], table["t_gene"]))
+    isoforms = os.path.join(path, Constants.FileNames.SUBSAMPLE_ISOFORMS)
+    with open(isoforms, "w") as out:
+        for name in records:
+            if name in keep:
+                out.write(f"{genes.get(name, name)}\t{name}\n")
+
+    info = [
+        f"subsampled {len(keep)} of {len(records)} projections (seed: {seed}, stratified by: {stratify_by})",
+        f"subsample stats: { {str(k): v for k, v in counts.items()} }",
+        f"subset written to {f} and {isoforms}",
+    ]
+
+    [log.record(i) for i in info]
+
+    return f, isoforms
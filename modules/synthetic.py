@@ -0,0 +1,325 @@
+#!/usr/bin/env python3
+
+
+"""
+A module to generate synthetic TOGA-like results directories.
+
+Writes a consistent query_annotation.bed, orthology classification,
+loss summary, isoforms, orthology scores and transcript quality
+tables (plus an optional query genome), with a controllable number
+of edge cases: fragmented projections, many2many orthologs,
+non-coding projections and minus-strand models with codons split
+across exons. Run by test.sh and available to validate user wrappers.
+"""
+
+
+import os
+import random
+from constants import Constants
+from modules.bed import BedRecord
+from modules.fasta import COMPLEMENT, write_fasta
+
+
+__author__ = "Alejandro Gonzales-Irribarren"
+__email__ = "jose.gonzalesdezavala1@unmsm.edu.pe"
+__github__ = "https://github.com/alejandrogzi"
+__version__ = "0.7.0-devel"
+
+
+CLASSES = ["I", "I", "I", "PI", "UL", "L", "M"]
+CONTIG_SIZE = 200000
+INTRON = 300
+SPACER = 2000
+
+
+class Projection:
+    """A class to hold a synthetic projection and its metadata."""
+
+    def __init__(self, record: BedRecord, gene: str, transcript: str) -> None:
+        self.record = record
+        self.gene = gene
+        self.transcript = transcript
+        self.q_gene = None
+        self.relation = "one2one"
+        self.loss = "I"
+        self.pred = 1.0
+        self.confidence = "high_confidence"
+
+
+class Layout:
+    """A class to place synthetic projections along synthetic contigs."""
+
+    def __init__(self) -> None:
+        self.contig, self.cursor = 1, SPACER
+
+    def place(self, length: int, at_end: bool = False) -> tuple:
+        """
+        Returns (chrom, start) for a locus of a given length, moving to a
+        new contig when full. at_end puts the locus at the contig end
+        """
+        if self.cursor + 2 * (length + SPACER) > CONTIG_SIZE:
+            self.contig, self.cursor = self.contig + 1, SPACER
+
+        if at_end:
+            chrom = f"chr{self.contig}"
+            self.contig, self.cursor = self.contig + 1, SPACER
+            return chrom, CONTIG_SIZE - length
+
+        start = self.cursor
+        self.cursor += length + SPACER
+
+        return f"chr{self.contig}", start
+
+
+def make_record(
+    rng: random.Random,
+    layout: Layout,
+    name: str,
+    strand: str,
+    split_codons: bool = False,
+    noncoding: bool = False,
+    at_end: bool = False,
+) -> BedRecord:
+    """
+    Builds a 3-exon BED12 record. Exon lengths are multiples of 3 unless
+    split_codons is set, in which case codons straddle exon boundaries
+    (the CDS stays a multiple of 3)
+
+    @type rng: random.Random
+    @param rng: seeded random generator
+    @type layout: Layout
+    @param layout: contig layout to place the record in
+    @type name: str
+    @param name: projection name
+    @type strand: str
+    @param strand: + or -
+    """
+    sizes = [3 * rng.randint(20, 60) for _ in range(3)]
+    if split_codons:
+        sizes[0], sizes[1] = sizes[0] + 1, sizes[1] - 1
+
+    offsets = [0, sizes[0] + INTRON, sizes[0] + sizes[1] + 2 * INTRON]
+    length = offsets[-1] + sizes[-1]
+    chrom, start = layout.place(length, at_end)
+    end = start + length
+    thick = (start, start) if noncoding else (start, end)
+
+    return BedRecord(
+        [
+            chrom,
+            start,
+            end,
+            name,
+            "1000",
+            strand,
+            thick[0],
+            thick[1],
+            "0,0,200",
+            "3",
+            ",".join(str(x) for x in sizes),
+            ",".join(str(x) for x in offsets),
+        ]
+    )
+
+
+def synthetic_projections(
+    n_genes: int = 20,
+    seed: int = 0,
+    fragments: int = 2,
+    many2many: int = 2,
+    noncoding: int = 2,
+    split_codons: int = 2,
+) -> list:
+    """
+    Builds synthetic projections with a given number of each edge case
+
+    @type n_genes: int
+    @param n_genes: number of reference genes
+    @type seed: int
+    @param seed: random seed, the same seed gives the same dataset
+    @type fragments: int
+    @param fragments: genes projected as two pieces at contig ends, the
+        second one carrying the fragment suffix instead of a chain id
+    @type many2many: int
+    @param many2many: pairs of genes with a many2many relation
+    @type noncoding: int
+    @param noncoding: non-coding projections (thickStart == thickEnd)
+    @type split_codons: int
+    @param split_codons: minus-strand projections with codons split by introns
+    """
+    rng = random.Random(seed)
+    layout = Layout()
+    projections = []
+
+    for i in range(n_genes):
+        gene, transcript = f"ENSG{i:011d}", f"ENST{i:011d}"
+        chain = rng.randint(1, 99999)
+        strand = "-" if i < split_codons else rng.choice("+-")
+
+        record = make_record(
+            rng,
+            layout,
+            f"{transcript}.{chain}",
+            strand,
+            split_codons=i < split_codons,
+            noncoding=split_codons <= i < split_codons + noncoding,
+        )
+        projection = Projection(record, gene, transcript)
+        projection.q_gene = f"reg_{i}"
+        projection.loss = rng.choice(CLASSES)
+        projection.pred = round(rng.uniform(0.5, 1.0), 6)
+        projections.append(projection)
+
+        if n_genes - fragments <= i:
+            # A second piece on another contig end, named as TOGA names
+            # transcripts joined from fragments (no chain id, see fragment_base)
+            piece = make_record(
+                rng,
+                layout,
+                f"{transcript}.{Constants.FRAGMENT_SUFFIX}",
+                strand,
+                at_end=True,
+            )
+            fragment = Projection(piece, gene, transcript)
+            fragment.q_gene, fragment.loss = f"reg_{i}_b", "PI"
+            fragment.relation, projection.relation = "one2many", "one2many"
+            fragment.confidence = "low_confidence"
+            projections.append(fragment)
+
+    # many2many: consecutive gene pairs share their query gene
+    for k in range(min(many2many, n_genes // 2)):
+        a, b = projections[2 * k], projections[2 * k + 1]
+        b.q_gene = a.q_gene
+        a.relation = b.relation = "many2many"
+
+    return projections
+
+
+def synthetic_genome(rng: random.Random, projections: list, path: str) -> str:
+    """
+    Writes a random query genome where coding projections start with ATG
+    and end with a stop codon
+
+    @type rng: random.Random
+    @param rng: seeded random generator
+    @type projections: list
+    @param projections: list of Projection objects
+    @type path: str
+    @param path: path to the output .fasta
+    """
+    contigs = sorted({p.record.chrom for p in projections}, key=lambda x: int(x[3:]))
+    seqs = {c: [rng.choice("ACGT") for _ in range(CONTIG_SIZE)] for c in contigs}
+
+    for p in projections:
+        record = p.record
+        if record.thick_start >= record.thick_end:
+            continue
+        cds = sum(end - start for start, end in record.cds())
+        for offset, codon in ((0, "ATG"), (cds - 3, "TAA")):
+            for i, base in enumerate(codon):
+                if record.strand == "-":
+                    base = base.translate(COMPLEMENT)
+                seqs[record.chrom][record.cds_position(offset + i)] = base
+
+    with open(path, "w") as out:
+        for contig in contigs:
+            write_fasta(out, contig, "".join(seqs[contig]))
+
+    return path
+
+
+def best_class(classes: list) -> str:
+    """Returns the most intact loss class (see Constants.ORDER)"""
+    return min(classes, key=lambda x: Constants.ORDER[x])
+
+
+def synthetic_toga(
+    path: str,
+    n_genes: int = 20,
+    seed: int = 0,
+    fragments: int = 2,
+    many2many: int = 2,
+    noncoding: int = 2,
+    split_codons: int = 2,
+    genome: bool = False,
+) -> str:
+    """
+    Writes a synthetic TOGA results directory readable by postoga
+
+    @type path: str
+    @param path: output directory
+    @type n_genes: int
+    @param n_genes: number of reference genes
+    @type seed: int
+    @param seed: random seed, the same seed gives the same dataset
+    @type fragments: int
+    @param fragments: genes projected as two pieces (see synthetic_projections)
+    @type many2many: int
+    @param many2many: pairs of genes with a many2many relation
+    @type noncoding: int
+    @param noncoding: non-coding projections
+    @type split_codons: int
+    @param split_codons: minus-strand projections with codons split by introns
+    @type genome: bool
+    @param genome: also write a query genome matching the projections
+    """
+    projections = synthetic_projections(
+        n_genes, seed, fragments, many2many, noncoding, split_codons
+    )
+    os.makedirs(os.path.join(path, "temp"), exist_ok=True)
+
+    def table(name: str, header: list, rows: list) -> None:
+        with open(os.path.join(path, name), "w") as f:
+            if header:
+                f.write("\t".join(header) + "\n")
+            for row in rows:
+                f.write("\t".join(str(x) for x in row) + "\n")
+
+    table(
+        Constants.FileNames.BED, None, [[p.record.to_line()] for p in projections]
+    )
+    table(
+        Constants.FileNames.ORTHOLOGY,
+        ["t_gene", "t_transcript", "q_gene", "q_transcript", "orthology_class"],
+        [
+            [p.gene, p.transcript, p.q_gene, p.record.name, p.relation]
+            for p in projections
+        ],
+    )
+
+    transcripts, genes = {}, {}
+    for p in projections:
+        transcripts.setdefault(p.transcript, []).append(p.loss)
+        genes.setdefault(p.gene, []).append(p.loss)
+
+    table(
+        Constants.FileNames.CLASS,
+        None,
+        [["PROJECTION", p.record.name, p.loss] for p in projections]
+        + [["TRANSCRIPT", t, best_class(x)] for t, x in transcripts.items()]
+        + [["GENE", g, best_class(x)] for g, x in genes.items()],
+    )
+    table(
+        Constants.FileNames.ISOFORMS,
+        ["Gene stable ID", "Transcript stable ID"],
+        sorted({(p.gene, p.transcript) for p in projections}),
+    )
+    table(
+        Constants.FileNames.SCORES,
+        ["gene", "chain", "pred"],
+        [[p.transcript, p.record.name.rsplit(".", 1)[1], p.pred] for p in projections],
+    )
+    table(
+        Constants.FileNames.QUALITY,
+        ["Projection_ID", "confidence_level"],
+        [[p.record.name, p.confidence] for p in projections],
+    )
+
+    if genome:
+        synthetic_genome(
+            random.Random(seed),
+            projections,
+            os.path.join(path, Constants.FileNames.SYNTHETIC_GENOME),
+        )
+
+    return path
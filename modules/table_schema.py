@@ -0,0 +1,129 @@
+#!/usr/bin/env python3
+
+
+"""
+A module to version the query table schema and migrate old tables.
+
+Query tables start with a `#postoga_schema=N` line. Tables without it
+predate versioning and are schema 1. Each migration upgrades a table
+by one version, so any old table reaches the current schema
+(Constants.QUERY_TABLE_SCHEMA) by chaining them:
+
+``` python
+from modules.table_schema import read_query_table
+
+table = read_query_table("old_run/query_table.tsv")  # current schema
+```
+"""
+
+
+import pandas as pd
+from constants import Constants
+from modules.errors import PostogaValueError
+from modules.table_writer import write_table
+
+
+__author__ = "Alejandro Gonzales-Irribarren"
+__email__ = "jose.gonzalesdezavala1@unmsm.edu.pe"
+__github__ = "https://github.com/alejandrogzi"
+__version__ = "0.7.0-devel"
+
+
+PREFIX = "postoga_schema="
+
+
+def schema_stamp() -> str:
+    """Returns the comment written on top of current query tables"""
+    return f"{PREFIX}{Constants.QUERY_TABLE_SCHEMA}"
+
+
+def read_header(path: str) -> tuple:
+    """
+    Returns the schema version of a query table and the number of
+    leading comment lines
+
+    @type path: str
+    @param path: path to the query table
+    """
+    version, comments = 1, 0
+
+    with open(path, "r") as f:
+        for line in f:
+            if not line.startswith("#"):
+                break
+            comments += 1
+            if line[1:].strip().startswith(PREFIX):
+                version = int(line[1:].strip()[len(PREFIX) :])
+
+    return version, comments
+
+
+def v1_to_v2(table: pd.DataFrame) -> pd.DataFrame:
+    """
+    Schema 1 -> 2: the orthology relation lives in `relation` with
+    short codes (o2o, o2m, ...), older tables kept TOGA's long names
+    """
+    if "orthology_class" in table.columns and "relation" not in table.columns:
+        table = table.rename(columns={"orthology_class": "relation"})
+    if "relation" in table.columns:
+        table["relation"] = table["relation"].replace(Constants.ORTHOLOGY_TYPE)
+
+    return table
+
+
+# version -> function upgrading a table from that version to the next
+MIGRATIONS = {1: v1_to_v2}
+
+
+def migrate(table: pd.DataFrame, version: int) -> pd.DataFrame:
+    """
+    Upgrades a query table from a schema version to the current one
+
+    @type table: pd.DataFrame
+    @param table: query table
+    @type version: int
+    @param version: schema version of the table
+    """
+    if version > Constants.QUERY_TABLE_SCHEMA:
+        raise PostogaValueError(
+            f"query table schema {version} is newer than this postoga "
+            f"({Constants.QUERY_TABLE_SCHEMA}), upgrade postoga",
+            str(version),
+        )
+
+    while version < Constants.QUERY_TABLE_SCHEMA:
+        table = MIGRATIONS[version](table)
+        version += 1
+
+    return table
+
+
+def read_query_table(path: str) -> pd.DataFrame:
+    """
+    Reads a query table of any schema version, migrated to the current one
+
+    @type path: str
+    @param path: path to the query table
+    """
+    version, comments = read_header(path)
+    table = pd.read_csv(path, sep="\t", skiprows=comments, na_values=["NA"])
+
+    return migrate(table, version)
+
+
+def migrate_query_table(path: str, output: str = None, decimals: int = 4) -> str:
+    """
+    Rewrites a query table in the current schema, stamped with its version
+
+    @type path: str
+    @param path: path to the query table
+    @type output: str
+    @param output: path to the migrated table (default: overwrite path)
+    @type decimals: int
+    @param decimals: decimals for floats
+    """
+    table = read_query_table(path)
+
+    return write_table(
+        table, output or path, decimals=decimals, comments=[schema_stamp()]
+    )
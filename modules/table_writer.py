@@ -0,0 +1,112 @@
+#!/usr/bin/env python3
+
+
+"""
+A module with the shared .tsv writer used by postoga tables.
+
+Columns can be selected and reordered by the user, and values are
+formatted by type, the same way in every run: floats with a fixed
+number of decimals (no scientific notation), missing values as NA.
+Downstream parsers keep working when postoga adds new columns.
+"""
+
+
+from math import isnan
+from modules.errors import PostogaValueError
+
+
+__author__ = "Alejandro Gonzales-Irribarren"
+__email__ = "jose.gonzalesdezavala1@unmsm.edu.pe"
+__github__ = "https://github.com/alejandrogzi"
+__version__ = "0.7.0-devel"
+
+
+NA = "NA"
+DECIMALS = 4
+
+
+def format_value(value, decimals: int = DECIMALS) -> str:
+    """
+    Formats a single value: fixed decimals for floats, NA for None/NaN
+
+    @type value: any
+    @param value: value to format
+    @type decimals: int
+    @param decimals: decimals for floats
+    """
+    if value is None or (isinstance(value, float) and isnan(value)):
+        return NA
+    if isinstance(value, bool):
+        return str(value).lower()
+    if isinstance(value, float):
+        return f"{value:.{decimals}f}"
+    if isinstance(value, (list, tuple, set)):
+        return ",".join(format_value(x, decimals) for x in value)
+
+    return str(value)
+
+
+def select_columns(columns: list, select: list = None) -> list:
+    """
+    Returns the columns to write, in the user order if a selection
+    is given. Unknown columns raise a PostogaValueError
+
+    @type columns: list
+    @param columns: available columns
+    @type select: list
+    @param select: user-chosen columns
+    """
+    if not select:
+        return list(columns)
+
+    unknown = [x for x in select if x not in columns]
+    if unknown:
+        raise PostogaValueError(
+            f"unknown columns {unknown}, available: {list(columns)}", unknown[0]
+        )
+
+    return list(select)
+
+
+def write_table(
+    rows,
+    path: str,
+    columns: list = None,
+    select: list = None,
+    decimals: int = DECIMALS,
+    comments: list = None,
+) -> str:
+    """
+    Writes rows (dicts, sequences or a pandas DataFrame) as a .tsv with a header
+
+    @type rows: iterable | pd.DataFrame
+    @param rows: rows as dicts or sequences aligned with columns
+    @type path: str
+    @param path: path to the output .tsv
+    @type columns: list
+    @param columns: column names (default: DataFrame columns or first dict keys)
+    @type select: list
+    @param select: columns to write and their order
+    @type decimals: int
+    @param decimals: decimals for floats
+    @type comments: list
+    @param comments: lines written as `#` comments before the header
+    """
+    if hasattr(rows, "to_dict"):
+        columns = columns or list(rows.columns)
+        rows = rows.to_dict("records")
+
+    rows = [row if isinstance(row, dict) else dict(zip(columns, row)) for row in rows]
+    columns = columns or (list(rows[0]) if rows else [])
+    columns = select_columns(columns, select)
+
+    with open(path, "w") as out:
+        for comment in comments or []:
+            out.write(f"#{comment}\n")
+        out.write("\t".join(columns) + "\n")
+        for row in rows:
+            out.write(
+                "\t".join(format_value(row.get(x), decimals) for x in columns) + "\n"
+            )
+
+    return path
@@ -0,0 +1,148 @@
+#!/usr/bin/env python3
+
+
+"""
+A module to report clusters of tandem duplicates in the query.
+
+Projections of a reference gene through different chains are copies of
+that gene in the query; isoforms projected through the same chain (and
+overlapping copies) are one copy. Copies of a gene on the same contig
+no further apart than a maximum distance form a tandem cluster, reported
+with its copy count and span for gene family expansion analyses.
+"""
+
+
+import os
+import pandas as pd
+from collections import defaultdict
+from constants import Constants
+from logger import Log
+from modules.bed import read_bed
+from modules.make_query_table import projection_chain
+from modules.table_writer import write_table
+
+
+__author__ = "Alejandro Gonzales-Irribarren"
+__email__ = "jose.gonzalesdezavala1@unmsm.edu.pe"
+__github__ = "https://github.com/alejandrogzi"
+__version__ = "0.7.0-devel"
+
+
+def gene_copies(bed: str, genes: dict) -> dict:
+    """
+    Returns (gene, chrom) -> copies as [start, end, chains, projections],
+    one per chain, overlapping copies merged
+
+    @type bed: str
+    @param bed: path to .bed file
+    @type genes: dict
+    @param genes: projection -> reference gene
+    """
+    loci = defaultdict(dict)
+
+    for record in read_bed(bed):
+        gene = genes.get(record.name)
+        if not gene:
+            continue
+        chain = projection_chain(record.name)
+        copy = loci[(gene, record.chrom)].setdefault(
+            chain, [record.start, record.end, {chain}, []]
+        )
+        copy[0], copy[1] = min(copy[0], record.start), max(copy[1], record.end)
+        copy[3].append(record.name)
+
+    copies = {}
+    for key, chains in loci.items():
+        merged = []
+        for copy in sorted(chains.values(), key=lambda x: x[0]):
+            if merged and copy[0] < merged[-1][1]:
+                merged[-1][1] = max(merged[-1][1], copy[1])
+                merged[-1][2] |= copy[2]
+                merged[-1][3] += copy[3]
+            else:
+                merged.append(copy)
+        copies[key] = merged
+
+    return copies
+
+
+def tandem_clusters(copies: list, max_distance: int) -> list:
+    """
+    Groups sorted, non-overlapping copies into clusters of consecutive
+    copies at most max_distance apart; only clusters of 2+ copies
+
+    @type copies: list
+    @param copies: [start, end, chains, projections] sorted by start
+    @type max_distance: int
+    @param max_distance: maximum distance between adjacent copies
+    """
+    clusters = []
+
+    for copy in copies:
+        if clusters and copy[0] - clusters[-1][-1][1] <= max_distance:
+            clusters[-1].append(copy)
+        else:
+            clusters.append([copy])
+
+    return [x for x in clusters if len(x) > 1]
+
+
+def tandem_duplicates(
+    path: str, bed: str, table: pd.DataFrame, max_distance: int = None
+) -> str:
+    """
+    Writes the tandem duplicate clusters of the query (gene, chrom,
+    start, end, copies, span, chains, projections)
+
+    @type path: str
+    @param path: path to the results directory
+    @type bed: str
+    @param bed: path to .bed file
+    @type table: pd.DataFrame
+    @param table: query table (projection -> t_gene)
+    @type max_distance: int
+    @param max_distance: maximum distance between adjacent copies
+        (default: Constants.TANDEM_MAX_DISTANCE)
+    @rtype: str
+    @return: path to the tandem_duplicates.tsv table
+    """
+
+    log = Log.connect(path, Constants.FileNames.LOG)
+
+    max_distance = max_distance or Constants.TANDEM_MAX_DISTANCE
+    genes = dict(
+        table.dropna(subset=["transcripts", "t_gene"])[["transcripts", "t_gene"]].values
+    )
+
+    rows = []
+    for (gene, chrom), copies in sorted(gene_copies(bed, genes).items()):
+        for cluster in tandem_clusters(copies, max_distance):
+            start, end = cluster[0][0], max(x[1] for x in cluster)
+            rows.append(
+                [
+                    gene,
+                    chrom,
+                    start,
+                    end,
+                    len(cluster),
+                    end - start,
+                    ",".join(sorted(set().union(*(x[2] for x in cluster)))),
+                    ",".join(sum((x[3] for x in cluster), [])),
+                ]
+            )
+
+    f = os.path.join(path, Constants.FileNames.TANDEM_DUPLICATES)
+    write_table(
+        rows,
+        f,
+        ["gene", "chrom", "start", "end", "copies", "span", "chains", "projections"],
+    )
+
+    info = [
+        f"{len(rows)} tandem duplicate clusters (copies at most {max_distance} bp apart) in {len({x[0] for x in rows})} genes",
+        f"{sum(x[4] for x in rows)} gene copies in tandem clusters, table written to {f}",
+    ]
+
+    [log.record(i) for i in info]
+
+    return f
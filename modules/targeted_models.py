@@ -0,0 +1,214 @@
+#!/usr/bin/env python3
+
+
+"""
+A module to ingest miniprot/exonerate models and reconcile them with TOGA.
+
+Targeted re-annotation (e.g. of search_regions.bed) gives protein-to-
+genome models for genes TOGA called Lost or Missing. Each model is
+assigned to its reference gene through the target protein name and,
+following a policy, reported only (report), added next to the TOGA
+projections (add) or used to replace the overlapping Lost/Missing
+projections of the same gene (upgrade).
+"""
+
+
+import os
+import pandas as pd
+from collections import Counter, defaultdict
+from constants import Constants
+from logger import Log
+from modules.bed import read_bed
+from modules.diff_runs import gene_classes
+from modules.gxf import GxfFeature
+from modules.gxf_to_bed import to_bed_record
+from modules.table_writer import write_table
+
+
+__author__ = "Alejandro Gonzales-Irribarren"
+__email__ = "jose.gonzalesdezavala1@unmsm.edu.pe"
+__github__ = "https://github.com/alejandrogzi"
+__version__ = "0.7.0-devel"
+
+
+def read_miniprot(gff: str) -> list:
+    """
+    Reads a miniprot .gff (mRNA lines with a Target attribute, CDS and
+    stop_codon children) into (BedRecord, target protein) pairs
+
+    @type gff: str
+    @param gff: path to the miniprot .gff file
+    """
+    targets, children = {}, defaultdict(list)
+
+    with open(gff, "r") as f:
+        for line in f:
+            if line.startswith("#") or line.count("\t") != 8:
+                continue
+            feature = GxfFeature.from_line(line, "gff")
+            if feature.feature == "mRNA":
+                targets[feature.attributes["ID"]] = feature.attributes.get(
+                    "Target", ""
+                ).split(" ")[0]
+            elif "Parent" in feature.attributes:
+                children[feature.attributes["Parent"]].append(feature)
+
+    return [
+        (to_bed_record(name, children[name]), target)
+        for name, target in targets.items()
+        if children[name]
+    ]
+
+
+def read_exonerate(gff: str) -> list:
+    """
+    Reads exonerate --showtargetgff output (gene lines followed by their
+    cds lines, mixed with the alignment text) into (BedRecord, target
+    protein) pairs. protein2genome exons match the cds lines
+
+    @type gff: str
+    @param gff: path to the exonerate output
+    """
+    models, current = [], None
+
+    with open(gff, "r") as f:
+        for line in f:
+            if line.startswith("#") or line.count("\t") != 8:
+                continue
+            feature = GxfFeature.from_line(line, "gtf")
+            if feature.feature == "gene":
+                current = [feature.attributes.get("sequence", ""), []]
+                models.append(current)
+            elif current and feature.feature == "cds":
+                feature.feature = "CDS"
+                current[1].append(feature)
+
+    return [
+        (to_bed_record(f"exonerate_{i + 1}", features), target)
+        for i, (target, features) in enumerate(models)
+        if features
+    ]
+
+
+def overlaps(a, b) -> bool:
+    return a.chrom == b.chrom and a.start < b.end and b.start < a.end
+
+
+def reconcile_models(
+    path: str,
+    bed: str,
+    table: pd.DataFrame,
+    models: str,
+    source: str = "miniprot",
+    policy: str = "add",
+    isoforms: str = None,
+    append: bool = False,
+) -> tuple:
+    """
+    Reconciles targeted models with the TOGA projections of their genes
+
+    @type path: str
+    @param path: path to the results directory
+    @type bed: str
+    @param bed: path to the TOGA .bed file
+    @type table: pd.DataFrame
+    @param table: query table
+    @type models: str
+    @param models: miniprot .gff or exonerate --showtargetgff output
+    @type source: str
+    @param source: miniprot or exonerate
+    @type policy: str
+    @param policy: report, add or upgrade (see the module docstring)
+    @type isoforms: str
+    @param isoforms: isoforms file of the .bed, extended with added models
+    @type append: bool
+    @param append: add the rows to the reconciliation table of a previous
+        source instead of overwriting it
+    @rtype: tuple
+    @return: (path to the reconciled .bed, path to its isoforms)
+    """
+
+    log = Log.connect(path, Constants.FileNames.LOG)
+
+    reader = read_miniprot if source == "miniprot" else read_exonerate
+    classes = gene_classes(table)
+    to_gene = {}
+    for column in ("t_gene", "helper"):
+        to_gene.update(zip(table[column], table["t_gene"]))
+    genes = dict(zip(table["transcripts"], table["t_gene"]))
+
+    records = list(read_bed(bed))
+    by_gene = defaultdict(list)
+    for record in records:
+        by_gene[genes.get(record.name)].append(record)
+
+    removed, added, rows = set(), [], []
+
+    for i, (model, target) in enumerate(reader(models)):
+        gene = to_gene.get(target, to_gene.get(target.rsplit(".", 1)[0]))
+        cls = classes.get(gene)
+        replaced = [x.name for x in by_gene.get(gene, []) if overlaps(x, model)]
+
+        if gene is None:
+            action = "unassigned"
+        elif cls not in Constants.SEARCH_CLASSES:
+            action = "kept_toga"
+        elif policy == "upgrade" and replaced:
+            action = "upgraded"
+        elif policy in ("add", "upgrade"):
+            action, replaced = "added", []
+        else:
+            action, replaced = "reported", []
+
+        model.name = f"{target.rsplit('.', 1)[0]}.{source}_{i + 1}"
+        if action in ("added", "upgraded"):
+            removed.update(replaced)
+            added.append((gene, model))
+            genes[model.name] = gene
+
+        rows.append([source, model.name, target, gene, cls, action, ",".join(replaced)])
+
+    # Per-source outputs: a second source reads the first one's files
+    f = os.path.join(path, f"{source}.{Constants.FileNames.RECONCILED_BED}")
+    with open(f, "w") as out:
+        for record in records:
+            if record.name not in removed:
+                out.write(record.to_line() + "\n")
+        for _, model in added:
+            out.write(model.to_line() + "\n")
+
+    pairs = []
+    if isoforms:
+        with open(isoforms, "r") as f_iso:
+            pairs = [x for x in f_iso if x.rstrip("\n").split("\t")[-1] not in removed]
+
+    reconciled = os.path.join(
+        path, f"{source}.{Constants.FileNames.RECONCILED_ISOFORMS}"
+    )
+    with open(reconciled, "w") as out:
+        out.writelines(pairs)
+        for gene, model in added:
+            out.write(f"{gene}\t{model.name}\n")
+
+    table_path = os.path.join(path, Constants.FileNames.RECONCILIATION)
+    previous = []
+    if append and os.path.isfile(table_path):
+        with open(table_path, "r") as f_table:
+            next(f_table, None)
+            previous = [x.rstrip("\n").split("\t") for x in f_table if x.strip()]
+
+    write_table(
+        previous + rows,
+        table_path,
+        ["source", "model", "target", "gene", "toga_class", "action", "replaced"],
+    )
+
+    actions = dict(Counter(x[5] for x in rows))
+    info = [
+        f"{len(rows)} {source} models reconciled with TOGA (policy: {policy})",
+        f"reconciliation actions: {actions}",
+        f"reconciled annotation written to {f} and {reconciled}",
+    ]
+    [log.record(i) for i in info]
+
+    return f, reconciled
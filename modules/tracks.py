@@ -0,0 +1,481 @@
+#!/usr/bin/env python3
+
+
+"""
+A module to write genome browser tracks from the query annotation.
+
+Tracks are written as .bedGraph files and, when a chrom.sizes file is
+available and bedGraphToBigWig is installed, converted to .bw. The
+annotation itself can be written as BigBed with bedToBigBed, optionally
+as BED12+2 with the orthology relation and loss class of each projection.
+"""
+
+
+import os
+import subprocess
+import pandas as pd
+from collections import defaultdict
+from constants import Constants
+from logger import Log
+from modules.annotation_set import merge_intervals
+from modules.bed import read_bed
+from modules.capabilities import supports
+from modules.errors import PostogaToolError
+from modules.utils import chrom_sizes_reader, output_stem
+from modules.window_stats import sweep_windows
+
+
+__author__ = "Alejandro Gonzales-Irribarren"
+__email__ = "jose.gonzalesdezavala1@unmsm.edu.pe"
+__github__ = "https://github.com/alejandrogzi"
+__version__ = "0.7.0-devel"
+
+
+def paint_intervals(intervals: list, reduce=max) -> list:
+    """
+    Splits overlapping valued intervals of a chromosome into
+    non-overlapping segments, reducing the values of each segment
+
+    @type intervals: list
+    @param intervals: list of (start, end, value) tuples
+    @type reduce: function
+    @param reduce: function applied to the values covering a segment
+    @rtype: list
+    @return: sorted list of (start, end, value) tuples
+    """
+    points = sorted({x for start, end, _ in intervals for x in (start, end)})
+    opening = defaultdict(list)
+    for start, end, value in intervals:
+        opening[start].append((end, value))
+
+    segments, active = [], []
+    for left, right in zip(points, points[1:]):
+        active = [(end, value) for end, value in active if end > left]
+        active.extend(opening.get(left, []))
+        if not active:
+            continue
+        value = reduce([value for _, value in active])
+        # Book-ended segments with the same value are written as one
+        if segments and segments[-1][1] == left and segments[-1][2] == value:
+            segments[-1] = (segments[-1][0], right, value)
+        else:
+            segments.append((left, right, value))
+
+    return segments
+
+
+def write_bedgraph(tracks: dict, bedgraph: str) -> str:
+    """
+    Writes chromosome -> segments to a .bedGraph file
+
+    @type tracks: dict
+    @param tracks: chrom -> [(start, end, value), ...]
+    @type bedgraph: str
+    @param bedgraph: path to the output .bedGraph
+    """
+    with open(bedgraph, "w") as f:
+        for chrom in sorted(tracks):
+            for start, end, value in tracks[chrom]:
+                f.write(f"{chrom}\t{start}\t{end}\t{value:g}\n")
+
+    return bedgraph
+
+
+def run_tool(args: list, output: str, env: dict = None) -> str:
+    """
+    Runs a track conversion tool, raising a PostogaToolError (and
+    removing any partial output) if it fails or writes nothing
+
+    @type args: list
+    @param args: command and its arguments
+    @type output: str
+    @param output: path to the file the tool writes
+    @type env: dict
+    @param env: extra environment variables
+    @rtype: str
+    @return: standard output of the tool
+    """
+    try:
+        result = subprocess.run(
+            args, capture_output=True, text=True, env={**os.environ, **(env or {})}
+        )
+        detail = result.stderr.strip()
+    except OSError as e:
+        result, detail = None, e
+
+    if result is None or result.returncode or not os.path.exists(output):
+        if os.path.exists(output):
+            os.remove(output)
+        raise PostogaToolError(f"{args[0]} failed writing {output}: {detail}", output)
+
+    return result.stdout.strip()
+
+
+def to_bigwig(path: str, bedgraph: str, chrom_sizes: str) -> str:
+    """
+    Converts a .bedGraph to .bw with bedGraphToBigWig
+
+    @type path: str
+    @param path: path to the results directory
+    @type bedgraph: str
+    @param bedgraph: path to a sorted .bedGraph
+    @type chrom_sizes: str
+    @param chrom_sizes: path to a chrom.sizes file
+    @rtype: str
+    @return: path to the .bw file, None if the tool is not available (a
+        failing tool raises a PostogaToolError)
+    """
+
+    log = Log.connect(path, Constants.FileNames.LOG)
+
+    if not supports(Constants.ToolNames.BEDGRAPHTOBIGWIG):
+        log.warning(
+            f"{Constants.ToolNames.BEDGRAPHTOBIGWIG} not found in PATH, keeping {bedgraph} only"
+        )
+        return None
+
+    bigwig = f"{os.path.splitext(bedgraph)[0]}.bw"
+    run_tool(
+        ["sort", "-k1,1", "-k2,2n", bedgraph, "-o", bedgraph],
+        bedgraph,
+        {"LC_COLLATE": "C"},
+    )
+    run_tool(
+        [Constants.ToolNames.BEDGRAPHTOBIGWIG, bedgraph, chrom_sizes, bigwig], bigwig
+    )
+
+    log.record(f"bigwig file written to {bigwig}")
+
+    return bigwig
+
+
+# autoSql of the BED12+2 BigBed written with the query table fields
+BIGBED_AUTOSQL = """table togaProjection
+"TOGA projection with its orthology relation and loss class"
+    (
+    string chrom;      "Query contig"
+    uint   chromStart; "Start position"
+    uint   chromEnd;   "End position"
+    string name;       "Projection"
+    uint   score;      "Score (0-1000)"
+    char[1] strand;    "+ or -"
+    uint thickStart;   "Start of the CDS"
+    uint thickEnd;     "End of the CDS"
+    uint reserved;     "itemRgb"
+    int blockCount;    "Number of exons"
+    int[blockCount] blockSizes;  "Exon sizes"
+    int[blockCount] chromStarts; "Exon starts relative to chromStart"
+    string relation;   "Orthology relation (o2o, o2m, m2o, m2m, o2z)"
+    string lossClass;  "Loss class (I, PI, UL, L, M, PG, ...)"
+    )
+"""
+
+
+def bigbed_score(score: str) -> int:
+    """Returns a .bed score as an integer in 0-1000, as BigBed requires"""
+    try:
+        return min(max(int(float(score)), 0), 1000)
+    except ValueError:
+        return 0
+
+
+def to_bigbed(
+    path: str, bed: str, chrom_sizes: str, table: pd.DataFrame = None
+) -> str:
+    """
+    Converts a .bed file to BigBed with bedToBigBed; with the query table
+    the orthology relation and loss class are added as BED12+2 fields
+
+    @type path: str
+    @param path: path to the results directory
+    @type bed: str
+    @param bed: path to .bed file
+    @type chrom_sizes: str
+    @param chrom_sizes: path to a chrom.sizes file
+    @type table: pd.DataFrame
+    @param table: query table, for the extra fields
+    @rtype: str
+    @return: path to the .bb file, None if the tool is not available (a
+        failing tool raises a PostogaToolError)
+    """
+
+    log = Log.connect(path, Constants.FileNames.LOG)
+
+    if not supports(Constants.ToolNames.BEDTOBIGBED):
+        log.warning(
+            f"{Constants.ToolNames.BEDTOBIGBED} not found in PATH, skipping BigBed output"
+        )
+        return None
+
+    extra = {}
+    if table is not None:
+        rows = table.dropna(subset=["transcripts"])
+        extra = {
+            tx: [str(rel) if pd.notna(rel) else ".", str(cls) if pd.notna(cls) else "."]
+            for tx, rel, cls in zip(rows["transcripts"], rows["relation"], rows["class"])
+        }
+
+    sizes = chrom_sizes_reader(chrom_sizes)
+    records = sorted(
+        (x for x in read_bed(bed) if x.chrom in sizes),
+        key=lambda x: (x.chrom, x.start),
+    )
+
+    stem = os.path.join(path, os.path.basename(output_stem(bed)))
+    sorted_bed, bigbed = f"{stem}.bigbed.bed", f"{stem}.bb"
+    with open(sorted_bed, "w") as out:
+        for record in records:
+            fields = record.to_line().split("\t")
+            fields[4] = str(bigbed_score(fields[4]))
+            if table is not None:
+                fields += extra.get(record.name, [".", "."])
+            out.write("\t".join(fields) + "\n")
+
+    options = ["-type=bed12"]
+    if table is not None:
+        autosql = f"{stem}.as"
+        with open(autosql, "w") as f:
+            f.write(BIGBED_AUTOSQL)
+        options = ["-type=bed12+2", f"-as={autosql}", "-tab"]
+    cmd = [Constants.ToolNames.BEDTOBIGBED, *options, sorted_bed, chrom_sizes, bigbed]
+    sh = run_tool(cmd, bigbed)
+
+    info = [
+        f"running {' '.join(cmd)}",
+        sh,
+        f"{len(records)} projections written to BigBed {bigbed}",
+    ]
+
+    [log.record(i) for i in info]
+
+    return bigbed
+
+
+def score_track(
+    path: str,
+    bed: str,
+    table: pd.DataFrame,
+    column: str = "pred",
+    chrom_sizes: str = None,
+) -> str:
+    """
+    Paints a per-projection value of the query table over the projection
+    spans, keeping the highest value where projections overlap
+
+    @type path: str
+    @param path: path to the results directory
+    @type bed: str
+    @param bed: path to .bed file
+    @type table: pd.DataFrame
+    @param table: query table
+    @type column: str
+    @param column: numeric column of the query table to paint (default: pred)
+    @type chrom_sizes: str
+    @param chrom_sizes: optional chrom.sizes file to also write a .bw
+    """
+
+    log = Log.connect(path, Constants.FileNames.LOG)
+
+    values = table.set_index("transcripts")[column].dropna().to_dict()
+    intervals = defaultdict(list)
+
+    for record in read_bed(bed):
+        if record.name in values:
+            intervals[record.chrom].append(
+                (record.start, record.end, float(values[record.name]))
+            )
+
+    tracks = {chrom: paint_intervals(x) for chrom, x in intervals.items()}
+    bedgraph = write_bedgraph(
+        tracks,
+        os.path.join(path, Constants.FileNames.SCORE_BEDGRAPH.format(column)),
+    )
+
+    log.record(
+        f"{column} track of {sum(len(x) for x in intervals.values())} projections written to {bedgraph}"
+    )
+
+    if chrom_sizes:
+        to_bigwig(path, bedgraph, chrom_sizes)
+
+    return bedgraph
+
+
+def coverage_segments(intervals: list) -> list:
+    """
+    Returns the depth of coverage of a chromosome as non-overlapping
+    (start, end, depth) segments, zero-depth gaps left out
+
+    @type intervals: list
+    @param intervals: list of (start, end) tuples
+    """
+    events = defaultdict(int)
+    for start, end in intervals:
+        events[start] += 1
+        events[end] -= 1
+
+    segments, depth = [], 0
+    points = sorted(events)
+    for left, right in zip(points, points[1:]):
+        depth += events[left]
+        if not depth:
+            continue
+        if segments and segments[-1][1] == left and segments[-1][2] == depth:
+            segments[-1] = (segments[-1][0], right, depth)
+        else:
+            segments.append((left, right, depth))
+
+    return segments
+
+
+def bin_segments(segments: list, size: int, length: int = None) -> list:
+    """
+    Averages depth segments over fixed-size bins, skipping empty bins. The
+    last bin is cut at the chromosome end and averaged over its own length
+
+    @type segments: list
+    @param segments: sorted (start, end, depth) tuples
+    @type size: int
+    @param size: bin size in bp
+    @type length: int
+    @param length: chromosome length (default: the last segment end)
+    """
+    bins = []
+
+    for start, end, overlapping in sweep_windows(segments, size, length=length):
+        if overlapping:
+            total = sum(
+                (min(e, end) - max(s, start)) * depth for s, e, depth in overlapping
+            )
+            bins.append((start, end, total / (end - start)))
+
+    return bins
+
+
+def coverage_track(
+    path: str,
+    bed: str,
+    level: str = "exon",
+    bin_size: int = None,
+    chrom_sizes: str = None,
+) -> str:
+    """
+    Writes the number of projections covering each base (exons or whole
+    transcript spans) as a .bedGraph, per base or averaged over bins,
+    and as .bw if chrom sizes are known
+
+    @type path: str
+    @param path: path to the results directory
+    @type bed: str
+    @param bed: path to .bed file
+    @type level: str
+    @param level: exon or transcript
+    @type bin_size: int
+    @param bin_size: average over bins of this many bp, per base if None
+    @type chrom_sizes: str
+    @param chrom_sizes: optional chrom.sizes file to also write a .bw
+    """
+
+    log = Log.connect(path, Constants.FileNames.LOG)
+
+    intervals = defaultdict(list)
+    for record in read_bed(bed):
+        if level == "exon":
+            intervals[record.chrom].extend(record.exons())
+        else:
+            intervals[record.chrom].append((record.start, record.end))
+
+    tracks = {chrom: coverage_segments(x) for chrom, x in intervals.items()}
+    if bin_size:
+        # bedGraphToBigWig rejects bins past the end of a contig
+        sizes = chrom_sizes_reader(chrom_sizes) if chrom_sizes else {}
+        tracks = {
+            chrom: bin_segments(x, bin_size, sizes.get(chrom))
+            for chrom, x in tracks.items()
+        }
+
+    bedgraph = write_bedgraph(
+        tracks,
+        os.path.join(path, Constants.FileNames.COVERAGE_BEDGRAPH.format(level)),
+    )
+
+    log.record(
+        f"{level} coverage of {sum(len(x) for x in intervals.values())} intervals written to {bedgraph}"
+        + (f" ({bin_size} bp bins)" if bin_size else " (per base)")
+    )
+
+    if chrom_sizes:
+        to_bigwig(path, bedgraph, chrom_sizes)
+
+    return bedgraph
+
+
+def density_track(
+    path: str,
+    bed: str,
+    table: pd.DataFrame,
+    window: int = Constants.DENSITY_WINDOW,
+    chrom_sizes: str = None,
+) -> dict:
+    """
+    Writes the number of genes and exons per genomic window as .bedGraph
+    tracks (.bw if chrom sizes are known). With chrom sizes every window
+    is written, so annotation deserts show up as zero runs
+
+    @type path: str
+    @param path: path to the results directory
+    @type bed: str
+    @param bed: path to .bed file
+    @type table: pd.DataFrame
+    @param table: query table
+    @type window: int
+    @param window: window size in bp
+    @type chrom_sizes: str
+    @param chrom_sizes: optional chrom.sizes file
+    @rtype: dict
+    @return: feature (gene, exon) -> path to its .bedGraph
+    """
+
+    log = Log.connect(path, Constants.FileNames.LOG)
+
+    genes = dict(zip(table["transcripts"], table["t_gene"]))
+    gene_spans, exons = defaultdict(list), defaultdict(list)
+
+    for record in read_bed(bed):
+        gene = genes.get(record.name, record.name)
+        gene_spans[(record.chrom, gene)].append((record.start, record.end))
+        exons[record.chrom].extend(merge_intervals(record.exons()))
+
+    spans = defaultdict(list)
+    for (chrom, _), intervals in gene_spans.items():
+        spans[chrom].extend(merge_intervals(intervals))
+
+    if chrom_sizes:
+        sizes = chrom_sizes_reader(chrom_sizes)
+    else:
+        sizes = {chrom: max(end for _, end in x) for chrom, x in spans.items()}
+        log.warning("no chrom sizes known, density tracks end at the last feature")
+
+    files = {}
+    for feature, intervals in (("gene", spans), ("exon", exons)):
+        tracks = {}
+        for chrom, length in sizes.items():
+            tracks[chrom] = [
+                (start, end, len(overlapping))
+                for start, end, overlapping in sweep_windows(
+                    intervals.get(chrom, []), window, length=length
+                )
+            ]
+
+        files[feature] = write_bedgraph(
+            tracks,
+            os.path.join(path, Constants.FileNames.DENSITY_BEDGRAPH.format(feature)),
+        )
+        log.record(
+            f"{feature} density ({window} bp windows) written to {files[feature]}"
+        )
+
+        if chrom_sizes:
+            to_bigwig(path, files[feature], chrom_sizes)
+
+    return files
@@ -0,0 +1,230 @@
+#!/usr/bin/env python3
+
+
+"""
+A module to build union gene models from the query annotation.
+
+A union gene model collapses all the isoforms of a gene into a
+single set of non-overlapping exons, the format expected by read
+counters like featureCounts or HTSeq-count. The same models give a
+gene-level .bed (one line per locus, colored by its best class) for
+overlap analyses.
+"""
+
+
+import pandas as pd
+from collections import defaultdict
+from constants import Constants
+from logger import Log
+from modules.annotation_set import AnnotationSet, Transcript, merge_intervals
+from modules.bed import read_bed
+from modules.gxf import gtf_line
+from modules.isoform_map import IsoformMap
+from modules.utils import output_stem
+
+
+__author__ = "Alejandro Gonzales-Irribarren"
+__email__ = "jose.gonzalesdezavala1@unmsm.edu.pe"
+__github__ = "https://github.com/alejandrogzi"
+__version__ = "0.7.0-devel"
+
+
+def build_union_models(annotation: AnnotationSet) -> dict:
+    """
+    Groups the exons of all transcripts by gene locus and merges them
+
+    @type annotation: AnnotationSet
+    @param annotation: projections with their genes
+    @rtype: dict
+    @return: a dictionary of the form {(gene, chrom, strand): [(start, end), ...]}
+    """
+    exons = defaultdict(list)
+
+    # Projections without a gene are kept as their own locus
+    for tx in annotation:
+        exons[(tx.gene, tx.chrom, tx.strand)].extend(tx.exons)
+
+    return {locus: merge_intervals(blocks) for locus, blocks in exons.items()}
+
+
+class UnsortedBedError(ValueError):
+    """Raised when a .bed file is not sorted and grouped by gene"""
+
+
+def grouped_union_models(bed: str, isoforms: IsoformMap):
+    """
+    Yields (locus, exons) union models in a single streaming pass over
+    a .bed file sorted by (chrom, start) with the projections of each
+    gene contiguous, in the same order as sorting build_union_models.
+    Raises UnsortedBedError as soon as the file breaks that layout
+
+    @type bed: str
+    @param bed: path to .bed file
+    @type isoforms: IsoformMap
+    @param isoforms: gene <-> transcript map
+    """
+    locus, blocks, last, done = None, [], None, set()
+
+    for record in read_bed(bed):
+        if last and (record.chrom, record.start) < last:
+            raise UnsortedBedError(f"{bed} is not sorted at {record.name}")
+        last = (record.chrom, record.start)
+
+        current = (isoforms.get(record.name, record.name), record.chrom, record.strand)
+        if current != locus:
+            if locus:
+                yield locus, merge_intervals(blocks)
+                done.add(locus)
+            if current in done:
+                raise UnsortedBedError(f"{bed} is not grouped by gene at {record.name}")
+            locus, blocks = current, []
+        blocks.extend(record.exons())
+
+    if locus:
+        yield locus, merge_intervals(blocks)
+
+
+def write_union_gtf(gtf: str, models) -> list:
+    """
+    Writes (locus, exons) union models to a .gtf file in the given order,
+    returning the loci written
+
+    @type gtf: str
+    @param gtf: path to the output .gtf file
+    @type models: iterable
+    @param models: ((gene, chrom, strand), [(start, end), ...]) pairs
+    """
+    loci = []
+
+    with open(gtf, "w") as f:
+        for (gene, chrom, strand), exons in models:
+            loci.append((gene, chrom, strand))
+            attributes = {"gene_id": gene, "transcript_id": gene}
+            f.write(
+                gtf_line(chrom, "gene", exons[0][0], exons[-1][1], strand, attributes)
+                + "\n"
+            )
+            for number, (start, end) in enumerate(exons, 1):
+                f.write(
+                    gtf_line(
+                        chrom,
+                        "exon",
+                        start,
+                        end,
+                        strand,
+                        {**attributes, "exon_number": number},
+                    )
+                    + "\n"
+                )
+
+    return loci
+
+
+def union_model(path: str, bed: str, isoforms: str) -> str:
+    """
+    Writes a .gtf file with one union model per gene. Sorted .bed files
+    with genes grouped (as TOGA usually writes them) are streamed in
+    one pass; otherwise models are aggregated in memory and sorted
+
+    @type path: str
+    @param path: path to the results directory
+    @type bed: str
+    @param bed: path to .bed file
+    @type isoforms: str
+    @param isoforms: path to the isoforms file
+    """
+
+    log = Log.connect(path, Constants.FileNames.LOG)
+
+    isoforms = IsoformMap.load(isoforms)
+    gtf = f"{output_stem(bed)}.{Constants.FileNames.UNION_GTF}"
+
+    try:
+        loci = write_union_gtf(gtf, grouped_union_models(bed, isoforms))
+        mode = "streamed (sorted and grouped input)"
+    except UnsortedBedError as e:
+        models = build_union_models(AnnotationSet.from_file(bed, isoforms))
+        loci = write_union_gtf(
+            gtf, sorted(models.items(), key=lambda x: (x[0][1], x[1][0][0]))
+        )
+        mode = f"aggregated ({e})"
+
+    info = [
+        f"built {len(loci)} union gene models from {len(set(x[0] for x in loci))} genes, {mode}",
+        f"union model gtf file written to {gtf}",
+    ]
+
+    [log.record(i) for i in info]
+
+    return gtf
+
+
+def class_rgb(cls: str) -> str:
+    """
+    Returns the itemRgb of a class from Constants.CATEGORY_COLORS,
+    black for classes without a hex color
+
+    @type cls: str
+    @param cls: loss class
+    """
+    color = Constants.CATEGORY_COLORS.get(cls, "")
+    if not color.startswith("#"):
+        return "0,0,0"
+
+    return ",".join(str(int(color[i : i + 2], 16)) for i in (1, 3, 5))
+
+
+def genes_bed(path: str, bed: str, table: pd.DataFrame, isoforms: str) -> str:
+    """
+    Writes a BED12 file with one line per gene: the merged exons of all
+    its projections, the widest CDS span and the itemRgb of the most
+    intact class (see Constants.ORDER)
+
+    @type path: str
+    @param path: path to the results directory
+    @type bed: str
+    @param bed: path to .bed file
+    @type table: pd.DataFrame
+    @param table: query table
+    @type isoforms: str
+    @param isoforms: path to the isoforms file
+    """
+
+    log = Log.connect(path, Constants.FileNames.LOG)
+
+    annotation = AnnotationSet.from_file(bed, isoforms)
+    models = build_union_models(annotation)
+    classes = table.set_index("transcripts")["class"].to_dict()
+
+    thick, best = {}, {}
+    for tx in annotation:
+        locus = (tx.gene, tx.chrom, tx.strand)
+        if tx.is_coding:
+            start, end = thick.get(locus, tx.thick)
+            thick[locus] = (min(start, tx.thick[0]), max(end, tx.thick[1]))
+        cls = classes.get(tx.name)
+        if cls in Constants.ORDER and (
+            locus not in best or Constants.ORDER[cls] < Constants.ORDER[best[locus]]
+        ):
+            best[locus] = cls
+
+    out = f"{output_stem(bed)}.{Constants.FileNames.GENES_BED}"
+    with open(out, "w") as f:
+        for locus, exons in sorted(
+            models.items(), key=lambda x: (x[0][1], x[1][0][0])
+        ):
+            gene, chrom, strand = locus
+            attributes = {"bed_score": "0", "item_rgb": class_rgb(best.get(locus))}
+            record = Transcript(
+                gene, chrom, strand, exons, thick.get(locus), gene, attributes
+            ).to_bed()
+            f.write(record.to_line() + "\n")
+
+    info = [
+        f"collapsed projections into {len(models)} gene loci",
+        f"gene-level bed file written to {out}",
+    ]
+
+    [log.record(i) for i in info]
+
+    return out
@@ -3,6 +3,8 @@
 """ A module with postoga base utility functions. """
 
 
+import os
+import re
 import subprocess
 import pandas as pd
 
@@ -41,3 +43,48 @@ def ancestral_reader(ancestral: str) -> list:
     @param ancestral: path to ancestral file
     """
     return pd.read_csv(ancestral, sep="\t", header=None).iloc[:, 0].to_list()
+
+
+def chrom_sizes_reader(chrom_sizes: str) -> dict:
+    """
+    Reads a chrom.sizes file and returns a chrom -> length dictionary
+
+    @type chrom_sizes: str
+    @param chrom_sizes: path to chrom.sizes file
+    """
+    with open(chrom_sizes, "r") as f:
+        rows = [line.split()[:2] for line in f if line.strip()]
+
+    return {chrom: int(length) for chrom, length in rows}
+
+
+def natural_key(name: str) -> list:
+    """
+    Sorting key that orders embedded numbers numerically (chr2 < chr10)
+
+    @type name: str
+    @param name: string to sort
+    """
+    return [int(x) if x.isdigit() else x for x in re.split(r"(\d+)", name)]
+
+
+def output_stem(path: str, exts: tuple = (".bed", ".gtf", ".gff3", ".gff")) -> str:
+    """
+    Returns a path without its annotation extension (and compression one),
+    only looking at the file name, so dots in directory names and files
+    without an extension are kept as they are
+
+    @type path: str
+    @param path: path to the file
+    @type exts: tuple
+    @param exts: extensions to remove
+    """
+    directory, name = os.path.split(path)
+    name = re.sub(r"\.(gz|bgz|zst|zstd|xz)$", "", name)
+
+    for ext in exts:
+        if name.lower().endswith(ext) and len(name) > len(ext):
+            name = name[: -len(ext)]
+            break
+
+    return os.path.join(directory, name)
@@ -0,0 +1,210 @@
+#!/usr/bin/env python3
+
+
+"""
+A module to pre-flight BED12 (or BED4/BED6) files before conversion.
+
+Every line is checked on its own and every problem is reported with its
+line number, field and an actionable message, instead of the converters
+stopping at the first broken record:
+
+``` python
+from modules.validate_bed import validate_bed
+
+for issue in validate_bed("query_annotation.bed"):
+    print(issue.line, issue.name, issue.field, issue.message)
+```
+"""
+
+
+import os
+from collections import namedtuple
+from constants import Constants
+from logger import Log
+from modules.fileio import open_text
+from modules.errors import PostogaValueError
+from modules.run_warnings import RunWarnings
+
+
+__author__ = "Alejandro Gonzales-Irribarren"
+__email__ = "jose.gonzalesdezavala1@unmsm.edu.pe"
+__github__ = "https://github.com/alejandrogzi"
+__version__ = "0.7.0-devel"
+
+
+Issue = namedtuple("Issue", ["line", "name", "field", "message"])
+
+INTEGER_FIELDS = {
+    1: "chromStart",
+    2: "chromEnd",
+    6: "thickStart",
+    7: "thickEnd",
+    9: "blockCount",
+}
+
+
+def blocks(column: str) -> list:
+    """Returns the integers of a blockSizes/blockStarts column"""
+    return [int(x) for x in column.strip(",").split(",") if x]
+
+
+def check_fields(fields: list) -> list:
+    """
+    Returns (field, message) pairs for everything wrong in the fields of
+    a BED12 line (or BED4/BED6, read as a single non-coding block), empty
+    if the line is valid
+
+    @type fields: list
+    @param fields: tab-separated fields of the line
+    """
+    if len(fields) not in (4, 6) and len(fields) < 12:
+        return [
+            ("line", f"expected 4, 6 or 12 tab-separated fields, found {len(fields)}")
+        ]
+
+    bed12 = len(fields) >= 12
+    issues = []
+    values = {}
+    for i, field in INTEGER_FIELDS.items():
+        if i >= len(fields):
+            continue
+        try:
+            values[field] = int(fields[i])
+        except ValueError:
+            issues.append((field, f"{fields[i]!r} is not an integer"))
+    block_fields = ((10, "blockSizes"), (11, "blockStarts")) if bed12 else ()
+    for i, field in block_fields:
+        try:
+            values[field] = blocks(fields[i])
+        except ValueError:
+            issues.append(
+                (field, f"{fields[i]!r} is not a comma-separated integer list")
+            )
+    # Only BED4/BED6 records may leave the strand unknown
+    strands = ("+", "-") if bed12 else ("+", "-", ".")
+    if len(fields) > 4 and fields[5] not in strands:
+        issues.append(("strand", f"{fields[5]!r} is not one of {', '.join(strands)}"))
+    if issues:
+        return issues
+
+    start, end = values["chromStart"], values["chromEnd"]
+
+    if start < 0 or end <= start:
+        issues.append(("chromEnd", f"empty or negative interval {start}-{end}"))
+    if not bed12:
+        return issues
+
+    sizes, offsets = values["blockSizes"], values["blockStarts"]
+    thick_start, thick_end = values["thickStart"], values["thickEnd"]
+
+    if not values["blockCount"] == len(sizes) == len(offsets):
+        issues.append(
+            (
+                "blockCount",
+                f"blockCount {values['blockCount']} but {len(sizes)} sizes and {len(offsets)} starts",
+            )
+        )
+        return issues
+
+    if any(x <= 0 for x in sizes):
+        issues.append(("blockSizes", "blocks must have a positive size"))
+    if offsets and offsets[0] != 0:
+        issues.append(("blockStarts", f"first block starts at {offsets[0]}, not 0"))
+    for i in range(1, len(offsets)):
+        if offsets[i] < offsets[i - 1] + sizes[i - 1]:
+            issues.append(
+                (
+                    "blockStarts",
+                    f"block {i + 1} starts at {offsets[i]}, before block {i} ends ({offsets[i - 1] + sizes[i - 1]}); blocks must be sorted and not overlap",
+                )
+            )
+            break
+    if offsets and offsets[-1] + sizes[-1] != end - start:
+        issues.append(
+            (
+                "blockSizes",
+                f"last block ends at {start + offsets[-1] + sizes[-1]}, not at chromEnd {end}",
+            )
+        )
+
+    if not start <= thick_start <= thick_end <= end:
+        issues.append(
+            (
+                "thickStart",
+                f"thick interval {thick_start}-{thick_end} is not inside {start}-{end}",
+            )
+        )
+    elif thick_start < thick_end:
+        exons = [(start + o, start + o + s) for o, s in zip(offsets, sizes)]
+        if not any(s <= thick_start < e for s, e in exons):
+            issues.append(("thickStart", f"CDS start {thick_start} falls in an intron"))
+        if not any(s < thick_end <= e for s, e in exons):
+            issues.append(("thickEnd", f"CDS end {thick_end} falls in an intron"))
+
+    return issues
+
+
+def validate_bed(bed: str) -> list:
+    """
+    Returns the Issue list (line, name, field, message) of a .bed file,
+    empty if every line is a valid BED12 (or BED4/BED6) record
+
+    @type bed: str
+    @param bed: path to .bed file, optionally gzipped
+    """
+    issues = []
+
+    with open_text(bed) as f:
+        for number, line in enumerate(f, 1):
+            if not line.strip() or line.startswith(("#", "track", "browser")):
+                continue
+            fields = line.rstrip("\r\n").split("\t")
+            name = fields[3] if len(fields) > 3 else "."
+            issues.extend(
+                Issue(number, name, field, message)
+                for field, message in check_fields(fields)
+            )
+
+    return issues
+
+
+def preflight_bed(path: str, bed: str, strict: bool = False) -> list:
+    """
+    Validates a .bed file before conversion, writing the issues to a
+    .tsv report and the run warnings; with strict, any issue stops the run
+
+    @type path: str
+    @param path: path to the results directory
+    @type bed: str
+    @param bed: path to .bed file
+    @type strict: bool
+    @param strict: raise a PostogaValueError if the file has issues
+    """
+
+    log = Log.connect(path, Constants.FileNames.LOG)
+
+    issues = validate_bed(bed)
+    report = os.path.join(path, Constants.FileNames.BED_VALIDATION)
+    with open(report, "w") as f:
+        f.write("line\tprojection\tfield\tmessage\n")
+        for issue in issues:
+            f.write("\t".join(str(x) for x in issue) + "\n")
+
+    RunWarnings.connect(path).add(
+        "invalid_bed",
+        sorted({x.name if x.name != "." else f"line {x.line}" for x in issues}),
+        f"BED12 record fails validation, see {report}",
+    )
+
+    log.record(
+        f"validated {bed}: {len(issues)} issues in {len({x.line for x in issues})} lines, report written to {report}"
+    )
+
+    if issues and strict:
+        first = issues[0]
+        raise PostogaValueError(
+            f"{bed} line {first.line} ({first.name}): {first.field}: {first.message} ({len(issues)} issues, see {report})",
+            f"{bed}:{first.line}",
+        )
+
+    return issues
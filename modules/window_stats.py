@@ -0,0 +1,184 @@
+#!/usr/bin/env python3
+
+
+"""
+A module with a generic windowed aggregation for custom genome scans.
+
+Features of a .bed or .gtf/.gff file are counted, scored or measured in
+fixed-size (optionally sliding) windows, returning one row per window
+and one column per statistic:
+
+``` python
+from modules.window_stats import windowed_stats
+
+table = windowed_stats("query_annotation.gtf", 1000000, 500000,
+                       ["count", "mean_score", "coverage"], feature="gene")
+```
+
+Every windowed scan of postoga (these statistics and the coverage and
+density tracks) walks its windows with the same sweep line (see
+sweep_windows).
+"""
+
+
+import pandas as pd
+from collections import defaultdict
+from modules.annotation_set import merge_intervals
+from modules.errors import PostogaValueError
+from modules.formats import detect_format, open_annotation
+from modules.utils import chrom_sizes_reader, natural_key
+
+
+__author__ = "Alejandro Gonzales-Irribarren"
+__email__ = "jose.gonzalesdezavala1@unmsm.edu.pe"
+__github__ = "https://github.com/alejandrogzi"
+__version__ = "0.7.0-devel"
+
+
+STATS = ["count", "sum_score", "mean_score", "coverage"]
+
+
+def to_score(value: str) -> float:
+    """Returns a numeric score, None for '.' or non-numeric values"""
+    try:
+        return float(value)
+    except ValueError:
+        return None
+
+
+def read_features(annotation: str, feature: str = None) -> dict:
+    """
+    Reads (start, end, score) intervals per chromosome from a .bed (any
+    number of columns >= 3), genePred or .gtf/.gff file (optionally
+    gzipped), optionally keeping one .gtf/.gff feature type only
+
+    @type annotation: str
+    @param annotation: path to a .bed/.gtf/.gff/genePred file
+    @type feature: str
+    @param feature: .gtf/.gff feature type to keep (e.g. gene, exon)
+    @rtype: dict
+    @return: chrom -> [(start, end, score), ...], 0-based half-open
+    """
+    intervals = defaultdict(list)
+
+    if detect_format(annotation) in ("gtf", "gff"):
+        for x in open_annotation(annotation):
+            if feature is None or x.feature == feature:
+                intervals[x.chrom].append((x.start - 1, x.end, to_score(x.score)))
+    else:
+        for x in open_annotation(annotation):
+            intervals[x.chrom].append((x.start, x.end, to_score(x.score)))
+
+    return intervals
+
+
+def sweep_windows(intervals: list, window: int, step: int = None, length: int = None):
+    """
+    Yields (start, end, overlapping intervals) for the windows of a
+    chromosome. Intervals enter the active set once, in start order, and
+    leave it once the windows pass their end, so each one is visited
+    while it overlaps a window only. The last window is cut at length
+
+    @type intervals: list
+    @param intervals: (start, end, ...) tuples of a chromosome
+    @type window: int
+    @param window: window size in bp
+    @type step: int
+    @param step: distance between window starts (default: window, no overlap)
+    @type length: int
+    @param length: chromosome length (default: the last interval end)
+    """
+    intervals = sorted(intervals)
+    if length is None:
+        length = max((x[1] for x in intervals), default=0)
+
+    active, following = [], 0
+    for start in range(0, length, step or window):
+        end = min(start + window, length)
+        while following < len(intervals) and intervals[following][0] < end:
+            active.append(intervals[following])
+            following += 1
+        active = [x for x in active if x[1] > start]
+
+        yield start, end, active
+
+        if end == length:
+            break
+
+
+def window_stats(intervals: list, start: int, end: int, stats: list) -> list:
+    """
+    Computes statistics of the intervals overlapping a window
+
+    @type intervals: list
+    @param intervals: (start, end, score) tuples overlapping the window
+    @type start: int
+    @param start: window start
+    @type end: int
+    @param end: window end
+    @type stats: list
+    @param stats: statistics to compute (see STATS)
+    """
+    scores = [x[2] for x in intervals if x[2] is not None]
+    covered = sum(
+        min(e, end) - max(s, start)
+        for s, e in merge_intervals([(x[0], x[1]) for x in intervals])
+    )
+
+    values = {
+        "count": len(intervals),
+        "sum_score": sum(scores) if scores else None,
+        "mean_score": sum(scores) / len(scores) if scores else None,
+        "coverage": covered / (end - start),
+    }
+
+    return [values[x] for x in stats]
+
+
+def windowed_stats(
+    annotation: str,
+    window: int,
+    step: int = None,
+    stats: list = ("count",),
+    feature: str = None,
+    chrom_sizes: str = None,
+) -> pd.DataFrame:
+    """
+    Aggregates the features of an annotation in genomic windows
+
+    @type annotation: str
+    @param annotation: path to a .bed/.gtf/.gff/genePred file
+    @type window: int
+    @param window: window size in bp
+    @type step: int
+    @param step: distance between window starts (default: window, no overlap)
+    @type stats: list
+    @param stats: statistics per window (count, sum_score, mean_score, coverage)
+    @type feature: str
+    @param feature: .gtf/.gff feature type to keep
+    @type chrom_sizes: str
+    @param chrom_sizes: optional chrom.sizes file to scan whole contigs
+    @rtype: pd.DataFrame
+    @return: a table with the columns [chrom, start, end, *stats]
+    """
+    unknown = [x for x in stats if x not in STATS]
+    if unknown:
+        raise PostogaValueError(
+            f"unknown window statistics: {unknown}, choose from {STATS}", unknown[0]
+        )
+
+    intervals = read_features(annotation, feature)
+    if chrom_sizes:
+        sizes = chrom_sizes_reader(chrom_sizes)
+    else:
+        sizes = {chrom: max(x[1] for x in v) for chrom, v in intervals.items()}
+
+    rows = []
+    for chrom in sorted(sizes, key=natural_key):
+        for start, end, overlapping in sweep_windows(
+            intervals.get(chrom, []), window, step, sizes[chrom]
+        ):
+            values = window_stats(overlapping, start, end, stats)
+            rows.append([chrom, start, end] + values)
+
+    return pd.DataFrame(rows, columns=["chrom", "start", "end"] + list(stats))
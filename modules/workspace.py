@@ -0,0 +1,247 @@
+#!/usr/bin/env python3
+
+
+"""
+A module to guard a TOGA results directory during a postoga run.
+
+A lock file in the output directory stops a second postoga run on the
+same directory from overwriting the outputs of the first one; a lock
+left by a crashed run on the same host is detected and replaced. When
+a run on a results directory finishes, a manifest lists the files it
+wrote grouped by kind and the annotation/, sequences/ and reports/
+subdirectories link to them (the files stay where each step wrote them):
+
+``` python
+from modules.workspace import Workspace
+
+with Workspace("path/to/results"):
+    ...
+
+with Workspace("path/to/outputs", layout=False):  # lock only
+    ...
+```
+"""
+
+
+import json
+import os
+import socket
+import time
+from constants import Constants
+from modules.errors import PostogaIOError
+
+
+__author__ = "Alejandro Gonzales-Irribarren"
+__email__ = "jose.gonzalesdezavala1@unmsm.edu.pe"
+__github__ = "https://github.com/alejandrogzi"
+__version__ = "0.7.0-devel"
+
+
+# Output kind -> file name endings (compression extensions aside),
+# anything else is a report
+LAYOUT = {
+    "annotation": (".bed", ".gtf", ".gff", ".gff3", ".saf", ".bb"),
+    "sequences": (".fa", ".fasta", ".fna", ".faa"),
+}
+KINDS = list(LAYOUT) + ["reports"]
+
+
+def pid_alive(pid: int) -> bool:
+    """Returns True if a process with this pid exists on this host"""
+    try:
+        os.kill(pid, 0)
+    except ProcessLookupError:
+        return False
+    except PermissionError:
+        return True
+
+    return True
+
+
+def output_kind(name: str) -> str:
+    """
+    Returns the manifest group of an output file
+
+    @type name: str
+    @param name: file name
+    """
+    name = name.lower()
+    for extension in set(Constants.COMPRESSION_EXTENSIONS.values()):
+        if name.endswith(extension):
+            name = name[: -len(extension)]
+
+    for kind, endings in LAYOUT.items():
+        if name.endswith(endings):
+            return kind
+
+    return "reports"
+
+
+class Workspace:
+    """A class to lock a results directory and record what a run wrote."""
+
+    def __init__(self, path: str, layout: bool = True) -> None:
+        """
+        Constructs all the necessary attributes for the Workspace object.
+
+        @type path: str
+        @param path: path to the results directory
+        @type layout: bool
+        @param layout: write the manifest and the per-kind subdirectories,
+            else only lock the directory
+        """
+
+        self.path = path
+        self.layout = layout
+        self.lock = os.path.join(path, Constants.FileNames.LOCK)
+        self.manifest = os.path.join(path, Constants.FileNames.MANIFEST)
+        self.before = {}
+
+    def __enter__(self) -> "Workspace":
+        self.acquire()
+        if self.layout:
+            self.before = self.snapshot()
+        return self
+
+    def __exit__(self, kind, *args) -> None:
+        try:
+            if kind is None and self.layout:
+                self.link_outputs(self.write_manifest())
+        finally:
+            self.release()
+
+    def owner(self) -> dict:
+        """Returns the host/pid/start of the current lock, {} if unreadable"""
+        try:
+            with open(self.lock, "r") as f:
+                return json.load(f)
+        except (OSError, ValueError):
+            return {}
+
+    def acquire(self) -> None:
+        """
+        Creates the lock file atomically, replacing a stale lock of a
+        dead process on this host. Raises a PostogaIOError if another
+        run holds the directory
+        """
+        os.makedirs(self.path, exist_ok=True)
+
+        for _ in range(3):
+            try:
+                fd = os.open(self.lock, os.O_CREAT | os.O_EXCL | os.O_WRONLY)
+            except FileExistsError:
+                owner = self.owner()
+                stale = owner.get("host") == socket.gethostname() and not pid_alive(
+                    owner.get("pid", -1)
+                )
+                if not stale:
+                    raise PostogaIOError(
+                        f"{self.path} is in use by another postoga run "
+                        f"({owner or 'unknown owner'}); remove {self.lock} if that "
+                        "run is gone",
+                        self.lock,
+                    )
+                self.break_stale(owner)
+                continue
+
+            with os.fdopen(fd, "w") as f:
+                json.dump(
+                    {
+                        "host": socket.gethostname(),
+                        "pid": os.getpid(),
+                        "start": time.strftime("%Y-%m-%d %H:%M:%S"),
+                    },
+                    f,
+                )
+            return
+
+        raise PostogaIOError(
+            f"could not replace the stale lock of {self.path}; remove {self.lock} "
+            f"and {self.lock}.break if no other postoga run is starting there",
+            self.lock,
+        )
+
+    def break_stale(self, owner: dict) -> None:
+        """
+        Removes a stale lock only if it still belongs to owner, holding a
+        second exclusive lock meanwhile: two runs that found the same
+        stale lock cannot remove the fresh lock the first one takes
+
+        @type owner: dict
+        @param owner: contents of the stale lock (see owner)
+        """
+        breaker = f"{self.lock}.break"
+        try:
+            fd = os.open(breaker, os.O_CREAT | os.O_EXCL | os.O_WRONLY)
+        except FileExistsError:
+            # Another run is replacing it, retry taking the lock
+            time.sleep(0.1)
+            return
+
+        try:
+            if self.owner() == owner:
+                os.remove(self.lock)
+        finally:
+            os.close(fd)
+            os.remove(breaker)
+
+    def release(self) -> None:
+        """Removes the lock file if this process owns it"""
+        if self.owner().get("pid") == os.getpid():
+            os.remove(self.lock)
+
+    def snapshot(self) -> dict:
+        """Returns relative path -> mtime of every file in the directory"""
+        files = {}
+
+        for root, dirs, names in os.walk(self.path):
+            if root == self.path:
+                # The per-kind subdirectories only hold links to outputs
+                dirs[:] = [x for x in dirs if x not in KINDS]
+            for name in names:
+                full = os.path.join(root, name)
+                files[os.path.relpath(full, self.path)] = os.stat(full).st_mtime_ns
+
+        return files
+
+    def write_manifest(self) -> dict:
+        """
+        Writes a .json manifest with the files created or modified by the
+        run, grouped by kind (see LAYOUT), and returns the groups
+        """
+        skip = {Constants.FileNames.LOCK, Constants.FileNames.MANIFEST}
+        layout = {kind: [] for kind in KINDS}
+
+        for name, stamp in sorted(self.snapshot().items()):
+            if name in skip or self.before.get(name) == stamp:
+                continue
+            layout[output_kind(name)].append(name)
+
+        with open(self.manifest, "w") as f:
+            json.dump({"owner": self.owner(), "outputs": layout}, f, indent=2)
+
+        return layout
+
+    def link_outputs(self, layout: dict) -> None:
+        """
+        Links the outputs of the run from the annotation/, sequences/ and
+        reports/ subdirectories (relative links, sub-paths kept), dropping
+        links left by earlier runs to files that no longer exist
+
+        @type layout: dict
+        @param layout: kind -> relative paths of the outputs (see write_manifest)
+        """
+        for kind, names in layout.items():
+            for name in names:
+                link = os.path.join(self.path, kind, name)
+                os.makedirs(os.path.dirname(link), exist_ok=True)
+                if os.path.lexists(link):
+                    os.remove(link)
+                target = os.path.join(self.path, name)
+                os.symlink(os.path.relpath(target, os.path.dirname(link)), link)
+
+            for root, _, files in os.walk(os.path.join(self.path, kind)):
+                for name in files:
+                    link = os.path.join(root, name)
+                    if os.path.islink(link) and not os.path.exists(link):
+                        os.remove(link)
@@ -8,6 +8,10 @@ import pandas as pd
 import os
 from constants import Constants
 from logger import Log
+from modules.bed import read_bed
+from modules.errors import PostogaKeyError
+from modules.isoform_map import IsoformMap, fragment_base
+from modules.run_warnings import RunWarnings
 
 
 __author__ = "Alejandro Gonzales-Irribarren"
@@ -37,3 +41,72 @@ def isoform_writer(path: str, table: pd.DataFrame) -> str:
     log.record(f"gene-to-projection hash with {len(table)} entries written to {f}")
 
     return f
+
+
+def complete_isoforms(
+    path: str, bed: str, isoforms: str, synthetic: bool = False
+) -> str:
+    """
+    Makes sure every projection of a .bed file has a gene before the
+    converters run. Fragmented transcripts (e.g. ENST00000355624.10.-1)
+    missing from the isoforms file are matched to the gene of their
+    reference transcript; the rest abort the run, or with `synthetic`
+    go under a gene named after their reference transcript. Returns the
+    isoforms file itself if nothing was missing
+
+    @type path: str
+    @param path: path to the results directory
+    @type bed: str
+    @param bed: path to .bed file
+    @type isoforms: str
+    @param isoforms: path to the isoforms file
+    @type synthetic: bool
+    @param synthetic: emit unmatched transcripts under a synthetic gene
+    """
+
+    log = Log.connect(path, Constants.FileNames.LOG)
+
+    genes = IsoformMap.load(isoforms)
+    matched, orphans = {}, {}
+    for record in read_bed(bed):
+        if record.name in genes.tx2gene:
+            continue
+        gene = genes.resolve(record.name)
+        if gene is None:
+            orphans[record.name] = (
+                f"{Constants.FRAGMENT_GENE_PREFIX}{fragment_base(record.name)}"
+            )
+        else:
+            matched[record.name] = gene
+
+    if orphans and not synthetic:
+        raise PostogaKeyError(
+            f"{len(orphans)} projections have no gene in {isoforms} (e.g. {next(iter(orphans))}); use --fragment_genes to emit them under synthetic genes",
+            next(iter(orphans)),
+        )
+
+    if not matched and not orphans:
+        return isoforms
+
+    RunWarnings.connect(path).add(
+        "fragment_gene",
+        sorted(orphans),
+        f"projection has no gene in {isoforms}, emitted under a synthetic {Constants.FRAGMENT_GENE_PREFIX}* gene",
+    )
+
+    f = os.path.join(path, Constants.FileNames.FRAGMENT_ISOFORMS)
+    with open(f, "w") as out:
+        for tx, gene in genes.tx2gene.items():
+            out.write(f"{gene}\t{tx}\n")
+        for tx, gene in {**matched, **orphans}.items():
+            out.write(f"{gene}\t{tx}\n")
+
+    info = [
+        f"{len(matched)} fragmented projections matched to the gene of their reference transcript",
+        f"{len(orphans)} projections without a gene emitted under synthetic genes",
+        f"completed isoforms written to {f}",
+    ]
+
+    [log.record(i) for i in info]
+
+    return f
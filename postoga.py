@@ -14,21 +14,92 @@ files for downstream analysis.
 import os
 import argparse
 import sys
+import logging
 from constants import Constants
 from logger import Log
-from modules.convert_from_bed import bed_to_gtf, bed_to_gff
-from modules.make_query_table import query_table
-from modules.write_isoforms import isoform_writer
+from modules.convert_from_bed import (
+    bed_to_gtf,
+    bed_to_gff,
+    bed_to_saf,
+    compress_file,
+    convert,
+    read_chrom_map,
+    rename_chroms,
+)
+from modules.make_query_table import (
+    query_table,
+    orthology_relations,
+    projection_chains,
+    projection_classes,
+    write_query_table,
+)
+from modules.write_isoforms import complete_isoforms, isoform_writer
 from modules.filter_query_annotation import filter_bed, get_stats_from_bed
 from modules.assembly_stats import qual_by_ancestral
 from modules.haplotype_branch import merge_haplotypes
 from modules.plotter import postoga_plotter
+from modules.union_model import genes_bed, union_model
+from modules.prep_quant import prep_quant
+from modules.consequences import call_consequences
+from modules.tracks import coverage_track, density_track, score_track, to_bigbed
+from modules.fasta import reformat_fasta, write_chrom_sizes
+from modules.diff_runs import diff_runs
+from modules.capabilities import capabilities
+from modules.refine_annotation import refine_gxf
+from modules.gap_attribute import read_alignments
+from modules.run_warnings import RunWarnings
+from modules.extract_seqs import (
+    extract_seqs,
+    extract_seqs_batch,
+    read_metadata,
+    table_metadata,
+    verify_cds_lengths,
+    write_paired,
+)
+from modules.start_rescue import rescue_starts
+from modules.kozak import kozak_scores
+from modules.circular_contigs import normalize_circular
+from modules.assembly_gaps import flag_gaps
+from modules.contig_ends import flag_contig_ends
+from modules.contig_quality import contig_quality
+from modules.subsample import subsample
+from modules.guardrails import guard_bed
+from modules.rank_projections import keep_top_projections, read_weights
+from modules.synthetic import synthetic_toga
+from modules.gxf_to_bed import convert_from_gxf, verify_round_trip
+from modules.class_matrix import class_matrix
+from modules.recode import read_recode, recode_table
+from modules.never_projected import never_projected
+from modules.gene_status import gene_status
+from modules.homology_hits import rescue_candidates
+from modules.search_regions import search_regions
+from modules.targeted_models import reconcile_models
+from modules.window_stats import STATS, windowed_stats
+from modules.junction_peptides import export_peptides
+from modules.table_schema import migrate_query_table
+from modules.remap import remap_annotation
+from modules.chunk_outputs import chunk_outputs
+from modules.isoform_map import IsoformMap
+from modules.utils import chrom_sizes_reader
+from modules.reference_metadata import annotate_reference
+from modules.validate_bed import preflight_bed
+from modules.seq_digest import write_digests
+from modules.gff_profile import profile_info
+from modules.orf_rescue import rescue_orfs
+from modules.cds_ratio import cds_ratios
+from modules.retrocopies import flag_retrocopies
+from modules.tandem_duplicates import tandem_duplicates
+from modules.seq import translate_fasta
+from modules.contig_categories import category_stats
+from modules.table_writer import write_table
+from modules.workspace import Workspace
+from modules.errors import PostogaError
 
 
 __author__ = "Alejandro Gonzales-Irribarren"
 __email__ = "jose.gonzalesdezavala1@unmsm.edu.pe"
 __github__ = "https://github.com/alejandrogzi"
-__version__ = "0.6.0-devel"
+__version__ = Constants.VERSION
 
 
 class TogaDir:
@@ -45,7 +116,62 @@ class TogaDir:
         self.mode = args.mode
         self.args = args
 
-        if args.mode != "haplotype":
+        if args.mode == "diff":
+            """The diff branch of postoga"""
+            self.run_a = args.run_a
+            self.run_b = args.run_b
+            self.log = Log(args.run_b, Constants.FileNames.LOG)
+        elif args.mode == "matrix":
+            """The multi-species matrix branch of postoga"""
+            self.paths = args.matrix_paths.split(",")
+            self.names = args.names.split(",") if args.names else None
+            self.shape = args.shape
+            self.recode = read_recode(args.recode) if args.recode else None
+            self.symbols = args.symbols.split(",") if args.symbols else None
+            self.aliases = args.aliases.split(",") if args.aliases else None
+            self.output = args.output
+            self.log = Log(self.paths[0], Constants.FileNames.LOG)
+        elif args.mode == "windows":
+            """The windowed statistics branch of postoga"""
+            self.output = args.output
+            self.log = Log(
+                os.path.dirname(os.path.abspath(self.output)), Constants.FileNames.LOG
+            )
+        elif args.mode == "migrate":
+            """The query table migration branch of postoga"""
+            self.output = args.output or args.input
+            self.log = Log(
+                os.path.dirname(os.path.abspath(self.output)), Constants.FileNames.LOG
+            )
+        elif args.mode == "convert":
+            """The .bed to .gtf/.gff branch of postoga"""
+            self.output = args.output
+            self.log = Log(
+                os.path.dirname(os.path.abspath(args.bed)), Constants.FileNames.LOG
+            )
+        elif args.mode == "tobed":
+            """The .gtf/.gff to BED12 branch of postoga"""
+            self.log = Log(
+                os.path.dirname(os.path.abspath(args.input)), Constants.FileNames.LOG
+            )
+        elif args.mode == "remap":
+            """The assembly remapping branch of postoga"""
+            self.output = args.output
+            self.log = Log(
+                os.path.dirname(os.path.abspath(args.input)), Constants.FileNames.LOG
+            )
+        elif args.mode == "reformat":
+            """The .fasta normalization branch of postoga"""
+            self.output = args.output
+            self.log = Log(
+                os.path.dirname(os.path.abspath(self.output)), Constants.FileNames.LOG
+            )
+        elif args.mode == "synthetic":
+            """The synthetic data branch of postoga"""
+            self.outdir = args.outdir
+            os.makedirs(self.outdir, exist_ok=True)
+            self.log = Log(args.outdir, Constants.FileNames.LOG)
+        elif args.mode != "haplotype":
             """The default branch of postoga"""
             ##### STEP 1 #####
             self.path = args.path
@@ -56,6 +182,111 @@ class TogaDir:
             self.by_rel = args.by_rel if args.by_rel else None
             self.threshold = args.threshold if args.threshold else None
             self.species = args.species
+            self.union = args.union_model
+            self.genes_bed = args.genes_bed
+            self.genome = args.genome
+            self.quant = args.prep_quant
+            self.decoys = args.decoys
+            self.vcf = args.vcf
+            self.score_track = args.score_track
+            self.density = args.density
+            self.coverage = args.coverage
+            self.coverage_bin = args.coverage_bin
+            self.bigbed = args.bigbed
+            self.compress = args.compress
+            self.chrom_map = args.chrom_map
+            self.unmapped_chroms = args.unmapped_chroms
+            self.junction_peptides = args.junction_peptides
+            self.chrom_sizes = args.chrom_sizes
+            self.phase_convention = args.phase_convention
+            self.attribute_sep = args.attribute_sep
+            self.gap_attribute = args.gap_attribute
+            self.line_ending = args.line_ending
+            self.sort = args.sort
+            self.features = args.features
+            self.trim_partial = args.trim_partial_codons
+            self.nf_tags = args.nf_tags
+            self.dedup_exons = args.dedup_exons
+            self.extract = args.extract.split(",") if args.extract else []
+            self.extract_beds = (
+                dict(x.split("=", 1) for x in args.extract_beds.split(","))
+                if args.extract_beds
+                else {}
+            )
+            self.header_template = args.header_template
+            self.duplicates = args.duplicates
+            self.on_missing = args.on_missing
+            self.split_by = args.split_by
+            self.paired = args.paired.split(",") if args.paired else []
+            self.paired_layout = args.paired_layout
+            self.order = args.order
+            self.extract_filters = {
+                "min_len": args.min_len,
+                "max_ambiguous_frac": args.max_ambiguous_frac,
+                "require_start_stop": args.require_start_stop,
+            }
+            self.threads = args.threads
+            self.query_species = args.query_species
+            self.metadata = args.metadata
+            self.verify_cds = args.verify_cds
+            self.translate = args.translate
+            self.mask_stops = args.mask_stops
+            self.rescue_starts = args.rescue_starts
+            self.remap = args.remap
+            self.fragment_genes = args.fragment_genes
+            self.chunks = args.chunks
+            self.mrna = args.mrna
+            self.reference_annotation = args.reference_annotation
+            self.cache_dir = args.cache_dir
+            self.validate_bed = args.validate_bed
+            self.digests = args.digests
+            self.fasta_index = args.fasta_index
+            self.gff_profile = args.gff_profile
+            self.orf_rescue = args.orf_rescue
+            self.limits = {
+                "max_exons": args.max_exons,
+                "max_span": args.max_span,
+                "max_attribute_length": args.max_attribute_length,
+            }
+            self.kozak = args.kozak
+            self.cds_ratio = args.cds_ratio
+            self.retrocopies = args.retrocopies
+            self.retrocopy_gff = args.retrocopy_gff
+            self.tandem = args.tandem
+            self.contig_categories = args.contig_categories
+            self.mt_contigs = (
+                set(args.mt_contigs.split(",")) if args.mt_contigs else None
+            )
+            self.circular = set(args.circular.split(",")) if args.circular else set()
+            self.gaps = args.gaps
+            self.find_gaps = args.find_gaps
+            self.gap_margin = args.gap_margin
+            self.contig_ends = args.contig_ends
+            self.contig_quality = args.contig_quality
+            self.round_trip = args.round_trip
+            self.recode = read_recode(args.recode) if args.recode else None
+            self.never_projected = args.never_projected
+            self.gene_status = args.gene_status
+            self.expression = args.expression
+            self.homology = args.homology
+            self.min_expression = args.min_expression
+            self.max_evalue = args.max_evalue
+            self.hits = args.hits
+            self.min_bitscore = args.min_bitscore
+            self.search_regions = args.search_regions
+            self.miniprot = args.miniprot
+            self.exonerate = args.exonerate
+            self.reconcile = args.reconcile
+            self.write_table = args.write_table
+            self.columns = args.columns.split(",") if args.columns else None
+            self.decimals = args.decimals
+            self.subsample = args.subsample
+            self.top_projections = args.top_projections
+            self.rank_weights = (
+                read_weights(args.rank_weights) if args.rank_weights else None
+            )
+            self.seed = args.seed
+            self.stratify_by = args.stratify_by
         else:
             """The haplotype branch of postoga"""
             self.paths = args.haplotype_path.split(",")
@@ -77,11 +308,122 @@ class TogaDir:
         self.log.record(
             f"running in mode {self.mode} with arguments: {vars(self.args)}"
         )
+        self.log.record(f"capabilities: {capabilities()}")
+
+        if self.mode == "diff":
+            self.diff = diff_runs(self.run_a, self.run_b)
+            self.log.close()
+
+        elif self.mode == "matrix":
+            self.matrix = class_matrix(
+                self.paths,
+                self.names,
+                self.shape,
+                self.output,
+                self.recode,
+                self.symbols,
+                self.aliases,
+            )
+            self.log.close()
+
+        elif self.mode == "windows":
+            self.windows = windowed_stats(
+                self.args.input,
+                self.args.window,
+                self.args.step,
+                self.args.stats.split(","),
+                self.args.feature,
+                self.args.chrom_sizes,
+            )
+            write_table(self.windows, self.output)
+            self.log.record(
+                f"{len(self.windows)} windows of {self.args.input} written to {self.output}"
+            )
+            self.log.close()
+
+        elif self.mode == "migrate":
+            self.output = migrate_query_table(self.args.input, self.output)
+            self.log.record(
+                f"{self.args.input} migrated to query table schema {Constants.QUERY_TABLE_SCHEMA} in {self.output}"
+            )
+            self.log.close()
+
+        elif self.mode == "convert":
+            self.output = convert(
+                os.path.dirname(os.path.abspath(self.args.bed)),
+                self.args.bed,
+                self.args.isoforms,
+                self.output,
+                self.args.format,
+                self.args.compress,
+                self.args.chrom_map,
+                self.args.unmapped_chroms,
+            )
+            self.log.record(
+                f"{self.args.bed} converted to "
+                + ("stdout" if self.output == "-" else self.output)
+            )
+            self.log.close()
+
+        elif self.mode == "tobed":
+            self.output, self.isoforms = convert_from_gxf(
+                self.args.input, self.args.output, self.args.isoforms, self.args.format
+            )
+            self.log.record(
+                f"{self.args.input} converted to BED12 in {self.output}"
+                + (f", isoforms written to {self.isoforms}" if self.isoforms else "")
+            )
+            self.log.close()
+
+        elif self.mode == "remap":
+            self.output = remap_annotation(
+                os.path.dirname(os.path.abspath(self.args.input)),
+                self.args.input,
+                self.args.agp,
+                self.output,
+            )
+            self.log.close()
+
+        elif self.mode == "reformat":
+            counts = reformat_fasta(
+                self.args.input,
+                self.output,
+                self.args.wrap,
+                self.args.upper,
+                self.args.dedup,
+            )
+            self.log.record(
+                f"{counts['written']} of {counts['records']} records of {self.args.input} written to {self.output} ({counts['duplicates']} duplicated names, {counts['empty']} empty sequences)"
+            )
+            self.log.close()
+
+        elif self.mode == "synthetic":
+            self.outdir = synthetic_toga(
+                self.outdir,
+                self.args.n_genes,
+                self.args.seed,
+                self.args.fragments,
+                self.args.many2many,
+                self.args.noncoding,
+                self.args.split_codons,
+                self.args.genome,
+            )
+            self.log.record(f"synthetic TOGA results written to {self.outdir}")
+            self.log.close()
 
-        if self.mode != "haplotype":
+        elif self.mode != "haplotype":
+            RunWarnings(self.path).start()
             self.table = query_table(self.path)
             self.isoforms = isoform_writer(self.path, self.table)
 
+            if self.never_projected:
+                never_projected(self.path)
+
+            if self.reference_annotation:
+                self.table = annotate_reference(
+                    self.path, self.table, self.reference_annotation, self.cache_dir
+                )
+
             if any([self.by_class, self.by_rel, self.threshold]):
                 self.bed, self.stats, self.ngenes = filter_bed(
                     self.path, self.table, self.by_class, self.by_rel, self.threshold
@@ -94,10 +436,413 @@ class TogaDir:
                 self.base_stats, self.ngenes = get_stats_from_bed(self.bed, self.table)
                 self.stats = None
 
+            if self.top_projections:
+                self.bed = keep_top_projections(
+                    self.path,
+                    self.bed,
+                    self.table,
+                    self.top_projections,
+                    self.rank_weights,
+                )
+
+            if self.subsample:
+                self.bed, self.isoforms = subsample(
+                    self.path,
+                    self.bed,
+                    self.table,
+                    self.subsample,
+                    self.seed,
+                    None if self.stratify_by == "none" else self.stratify_by,
+                )
+
+            if self.remap:
+                self.bed = remap_annotation(self.path, self.bed, self.remap)
+
+            if self.genome and not self.chrom_sizes:
+                self.chrom_sizes = write_chrom_sizes(
+                    self.genome,
+                    os.path.join(self.path, Constants.FileNames.CHROM_SIZES),
+                )
+
+            if self.chrom_sizes:
+                self.bed = normalize_circular(
+                    self.path,
+                    self.bed,
+                    self.chrom_sizes,
+                    self.circular | (self.mt_contigs or set()),
+                )
+
+            self.bed = guard_bed(self.path, self.bed, self.limits)
+
+            if self.rescue_starts:
+                self.bed = rescue_starts(
+                    self.path,
+                    self.bed,
+                    self.table,
+                    self.genome,
+                    self.rescue_starts,
+                    self.mt_contigs,
+                )
+
+            if self.orf_rescue:
+                self.bed, self.table = rescue_orfs(
+                    self.path,
+                    self.bed,
+                    self.table,
+                    self.genome,
+                    self.orf_rescue,
+                    self.mt_contigs,
+                )
+
+            if self.kozak:
+                self.table = kozak_scores(self.path, self.bed, self.table, self.genome)
+
+            self.cds_ratios = None
+            if self.cds_ratio:
+                self.cds_ratios = cds_ratios(self.path, self.bed, self.decimals)
+                self.table["cds_ratio"] = self.table["transcripts"].map(
+                    self.cds_ratios
+                )
+
+            if self.retrocopies:
+                self.table = flag_retrocopies(
+                    self.path,
+                    self.bed,
+                    self.table,
+                    self.retrocopies,
+                    self.isoforms,
+                    self.retrocopy_gff,
+                )
+
+            if self.tandem:
+                tandem_duplicates(self.path, self.bed, self.table, self.tandem)
+
+            if self.contig_categories is not None:
+                self.table = category_stats(
+                    self.path,
+                    self.bed,
+                    self.table,
+                    self.contig_categories or None,
+                    self.q_assembly,
+                    self.mt_contigs,
+                )
+
+            self.gap_flags = None
+            if self.gaps or self.find_gaps:
+                self.gap_flags = flag_gaps(
+                    self.path, self.bed, self.gaps, self.genome, self.gap_margin
+                )
+                self.table["gap_flag"] = self.table["transcripts"].map(self.gap_flags)
+
+            self.contig_end_flags = None
+            if self.contig_ends is not None:
+                self.contig_end_flags = flag_contig_ends(
+                    self.path, self.bed, self.chrom_sizes, self.table, self.contig_ends
+                )
+                self.table["contig_end"] = self.table["transcripts"].map(
+                    self.contig_end_flags
+                )
+
+            if self.contig_quality:
+                contig_quality(
+                    self.path,
+                    self.bed,
+                    self.table,
+                    self.chrom_sizes,
+                    self.gaps
+                    or (
+                        os.path.join(self.path, Constants.FileNames.GAPS)
+                        if self.find_gaps
+                        else None
+                    ),
+                    [x for x in (self.gap_flags, self.contig_end_flags) if x],
+                    decimals=self.decimals,
+                )
+
+            if self.hits:
+                self.table = rescue_candidates(
+                    self.path,
+                    self.table,
+                    self.hits,
+                    self.max_evalue,
+                    self.min_bitscore,
+                )
+
+            if self.search_regions is not None:
+                search_regions(
+                    self.path, self.table, self.search_regions, self.chrom_sizes
+                )
+
+            sources = [x for x in ("miniprot", "exonerate") if getattr(self, x)]
+            for i, source in enumerate(sources):
+                self.bed, self.isoforms = reconcile_models(
+                    self.path,
+                    self.bed,
+                    self.table,
+                    getattr(self, source),
+                    source,
+                    self.reconcile,
+                    self.isoforms,
+                    append=i > 0,
+                )
+
+            if self.gene_status:
+                gene_status(
+                    self.path,
+                    self.table,
+                    self.expression,
+                    self.homology,
+                    self.min_expression,
+                    self.max_evalue,
+                    self.decimals,
+                )
+
+            # recoded classes are only written, steps above compare TOGA classes
+            self.recoded = recode_table(self.table, self.recode)
+
+            if self.write_table:
+                write_query_table(self.path, self.recoded, self.columns, self.decimals)
+
+            if self.validate_bed:
+                preflight_bed(self.path, self.bed, self.validate_bed == "strict")
+
+            if self.to:
+                self.isoforms = complete_isoforms(
+                    self.path, self.bed, self.isoforms, self.fragment_genes
+                )
+
+            self.gtf, self.gff = None, None
             if self.to == "gtf":
                 self.gtf = bed_to_gtf(self.path, self.bed, self.isoforms)
             elif self.to == "gff":
                 self.gff = bed_to_gff(self.path, self.bed, self.isoforms)
+            elif self.to == "saf":
+                self.saf = bed_to_saf(self.path, self.bed, self.isoforms, self.union)
+
+            self.annotation = self.gtf or self.gff
+            if self.annotation:
+                refine_gxf(
+                    self.path,
+                    self.annotation,
+                    phase_convention=self.phase_convention,
+                    features=self.features,
+                    trim_partial=self.trim_partial,
+                    genome=self.genome if self.nf_tags else None,
+                    dedup_exons=self.dedup_exons,
+                    mt_contigs=self.mt_contigs,
+                    relations=orthology_relations(self.table),
+                    chains=projection_chains(self.table),
+                    gap_flags=self.gap_flags,
+                    bed=self.bed if self.round_trip else None,
+                    classes=projection_classes(self.recoded),
+                    alignments=(
+                        read_alignments(
+                            os.path.join(self.path, Constants.FileNames.CODON)
+                        )
+                        if self.gap_attribute
+                        else None
+                    ),
+                    max_attribute_length=self.limits["max_attribute_length"],
+                    attribute_sep=(
+                        Constants.ATTRIBUTE_SEPARATORS[self.attribute_sep]
+                        if self.attribute_sep
+                        else None
+                    ),
+                    line_ending=Constants.LINE_ENDINGS[self.line_ending],
+                    mrna=self.mrna,
+                    chrom_sizes=(
+                        chrom_sizes_reader(self.chrom_sizes)
+                        if self.chrom_sizes
+                        else None
+                    ),
+                    isoforms=IsoformMap.load(self.isoforms),
+                    profile=(
+                        profile_info(self.table)
+                        if self.gff_profile == "refseq"
+                        else None
+                    ),
+                    cds_ratios=self.cds_ratios,
+                    sort=self.sort,
+                )
+                if self.round_trip:
+                    verify_round_trip(self.path, self.bed, self.annotation)
+
+            if self.verify_cds and "nucleotide" not in self.extract:
+                self.extract.append("nucleotide")
+
+            if self.translate and "codon" not in self.extract:
+                self.extract.append("codon")
+
+            if self.header_template:
+                self.header_fields = table_metadata(self.table, self.query_species)
+                if self.metadata:
+                    for tx, fields in read_metadata(self.metadata).items():
+                        self.header_fields.setdefault(tx, {}).update(fields)
+            else:
+                self.header_fields = None
+
+            groups = None
+            if self.split_by == "gene":
+                groups = IsoformMap.load(self.isoforms)
+            elif self.split_by == "class":
+                groups = projection_classes(self.recoded)
+
+            self.seqs, self.missing, self.headers = {}, {}, {}
+            for kind in self.extract:
+                (
+                    self.seqs[kind],
+                    self.missing[kind],
+                    self.headers[kind],
+                ) = extract_seqs(
+                    self.path,
+                    self.bed,
+                    os.path.join(self.path, Constants.TOGA_FASTA[kind]),
+                    self.header_template,
+                    self.header_fields,
+                    self.duplicates,
+                    kind,
+                    self.on_missing,
+                    self.order,
+                    self.threads,
+                    self.extract_filters,
+                    self.fasta_index,
+                    groups,
+                )
+
+            self.paired_seqs = {
+                kind: write_paired(
+                    self.path,
+                    self.bed,
+                    os.path.join(self.path, Constants.TOGA_FASTA[kind]),
+                    self.paired_layout,
+                    self.order,
+                )
+                for kind in self.paired
+            }
+
+            if self.extract_beds:
+                self.batch_seqs = {
+                    kind: extract_seqs_batch(
+                        self.path,
+                        self.extract_beds,
+                        os.path.join(self.path, Constants.TOGA_FASTA[kind]),
+                        self.header_template,
+                        self.header_fields,
+                        self.duplicates,
+                        self.on_missing,
+                        self.order,
+                        self.threads,
+                        self.extract_filters,
+                        self.fasta_index,
+                        groups,
+                    )[0]
+                    for kind in self.extract
+                }
+
+            if self.translate:
+                self.translated = translate_fasta(
+                    self.path, self.seqs["codon"], self.translate, self.mask_stops
+                )
+
+            if self.digests:
+                self.digest_tables = [
+                    write_digests(self.path, x) for x in self.seqs.values()
+                ]
+
+            if self.chunks:
+                self.chunk_files = chunk_outputs(
+                    self.path,
+                    self.bed,
+                    self.isoforms,
+                    self.chunks,
+                    self.annotation,
+                    list(self.seqs.values()),
+                )
+
+            if self.verify_cds and self.annotation:
+                verify_cds_lengths(
+                    self.path,
+                    self.annotation,
+                    self.seqs["nucleotide"],
+                    self.headers["nucleotide"],
+                )
+
+            if self.union and self.to != "saf":
+                self.union_gtf = union_model(self.path, self.bed, self.isoforms)
+
+            if self.genes_bed:
+                self.genes = genes_bed(self.path, self.bed, self.table, self.isoforms)
+
+            if self.quant:
+                (
+                    self.transcriptome,
+                    self.tx2gene,
+                    self.gentrome,
+                    self.decoy_names,
+                ) = prep_quant(
+                    self.path, self.genome, self.bed, self.isoforms, self.decoys
+                )
+
+            if self.vcf:
+                self.consequences = call_consequences(
+                    self.path,
+                    self.vcf,
+                    self.bed,
+                    self.genome,
+                    self.isoforms,
+                    self.mt_contigs,
+                )
+
+            if self.score_track:
+                self.score_bedgraph = score_track(
+                    self.path, self.bed, self.table, self.score_track, self.chrom_sizes
+                )
+
+            if self.junction_peptides:
+                self.peptides = export_peptides(
+                    self.path,
+                    self.bed,
+                    self.genome,
+                    self.isoforms,
+                    self.junction_peptides,
+                    self.vcf,
+                    self.mt_contigs,
+                )
+
+            if self.density:
+                self.density_bedgraphs = density_track(
+                    self.path, self.bed, self.table, self.density, self.chrom_sizes
+                )
+
+            if self.coverage:
+                self.coverage_bedgraph = coverage_track(
+                    self.path,
+                    self.bed,
+                    self.coverage,
+                    self.coverage_bin,
+                    self.chrom_sizes,
+                )
+
+            if self.bigbed:
+                self.bb = to_bigbed(
+                    self.path,
+                    self.bed,
+                    self.chrom_sizes,
+                    self.table if self.bigbed == "annotated" else None,
+                )
+
+            if self.annotation and self.chrom_map:
+                rename_chroms(
+                    self.path,
+                    self.annotation,
+                    read_chrom_map(self.chrom_map),
+                    self.unmapped_chroms,
+                )
+
+            if self.annotation and self.compress != "none":
+                self.annotation = compress_file(
+                    self.path, self.annotation, self.compress
+                )
 
             ##### STEP 2 #####
             self.ancestral_stats = qual_by_ancestral(
@@ -120,6 +865,15 @@ class TogaDir:
             self.log.close()
 
 
+def positive_int(value: str) -> int:
+    """argparse type for counts that must be at least 1"""
+    number = int(value)
+    if number < 1:
+        raise argparse.ArgumentTypeError(f"expected an integer >= 1, got {value}")
+
+    return number
+
+
 def base_branch(subparsers):
     base_parser = subparsers.add_parser("base", help="Base mode")
     base_parser.add_argument(
@@ -149,79 +903,1215 @@ def base_branch(subparsers):
     base_parser.add_argument(
         "-to",
         "--to",
-        help="Specify the conversion format for .bed (query_annotation/filtered) file (gtf, gff3)",
+        help="Specify the conversion format for .bed (query_annotation/filtered) file (gtf, gff3, saf)",
         required=True,
         type=str,
-        choices=["gtf", "gff"],
+        choices=["gtf", "gff", "saf"],
     )
     base_parser.add_argument(
-        "-aq",
-        "--assembly_qual",
-        help="Calculate assembly quality based on a list of genes provided by the user (default: Ancestral_placental.txt)",
+        "-z",
+        "--compress",
+        help="Compress the converted .gtf/.gff once every step is done: gz, bgzf for tabix indexing (needs bgzip), zstd (needs zstandard) or xz (default: none)",
         required=False,
+        choices=["none", "gz", "bgzf", "zstd", "xz"],
         type=str,
-        default=Constants.FileNames.ANCESTRAL,
+        default="none",
     )
     base_parser.add_argument(
-        "-sp",
-        "--species",
-        help="Species name to be used as a reference for the assembly quality calculation (default: human)",
+        "-cm",
+        "--chrom_map",
+        help="Two-column (name, alias) table to rename the chromosomes of the converted .gtf/.gff with (e.g. GenBank accessions to UCSC names)",
         required=False,
-        choices=["human", "mouse", "chicken"],
         type=str,
-        default=Constants.SPECIES_DEFAULT,
     )
-
-
-def haplotype_branch(subparsers):
-    haplotype_parser = subparsers.add_parser("haplotype", help="Haplotype mode")
-    haplotype_parser.add_argument(
-        "-hp",
-        "--haplotype_path",
-        help="Path to TOGA results directories separated by commas (path1,path2,path3)",
-        required=True,
+    base_parser.add_argument(
+        "-uc",
+        "--unmapped_chroms",
+        help="What to do with .gtf/.gff lines on chromosomes missing from --chrom_map: error or drop (default: error)",
+        required=False,
+        choices=["error", "drop"],
         type=str,
+        default="error",
     )
-    haplotype_parser.add_argument(
-        "-r",
-        "--rule",
-        help="Rule to merge haplotype assemblies (default: I>PI>UL>L>M>PM>PG>abs)",
+    base_parser.add_argument(
+        "-pc",
+        "--phase_convention",
+        help="Convention for the 8th column of coding features: phase (GTF/GFF3 phase) or frame (raw frame) (default: phase)",
         required=False,
         type=str,
-        default="I>PI>UL>L>M>PM>PG>NF",
+        choices=["phase", "frame"],
+        default="phase",
     )
-    haplotype_parser.add_argument(
-        "-s",
-        "--source",
-        help="Source of the haplotype classes (query, loss)",
+    base_parser.add_argument(
+        "-rm",
+        "--remap",
+        help="Path to an AGP file or offset table (old_chrom, old_start, old_end, new_chrom, new_start, strand) to move the annotation onto a revised assembly before any genome-based step",
         required=False,
         type=str,
-        choices=["query", "loss"],
-        default="loss",
     )
-
-
-def parser():
-    """Argument parser for postoga"""
-    app = argparse.ArgumentParser()
-    subparsers = app.add_subparsers(dest="mode", help="Select mode")
-
-    base_branch(subparsers)
-    haplotype_branch(subparsers)
-
-    if len(sys.argv) < 2:
-        app.print_help()
-        sys.exit(0)
-
-    args = app.parse_args()
-
-    return args
-
-
-def main():
-    args = parser()
-    master = TogaDir(args)
-    master.run()
+    base_parser.add_argument(
+        "-frg",
+        "--fragment_genes",
+        help=f"Emit projections without a gene in the isoforms (e.g. fragmented ones that cannot be matched to their reference transcript) under a synthetic {Constants.FRAGMENT_GENE_PREFIX}<transcript> gene instead of aborting",
+        required=False,
+        action="store_true",
+    )
+    base_parser.add_argument(
+        "-ck",
+        "--chunks",
+        help="Split the converted annotation and extracted .fasta files into N chunks of roughly equal size that never split a gene (written to chunks/)",
+        required=False,
+        type=positive_int,
+    )
+    base_parser.add_argument(
+        "-mr",
+        "--mrna",
+        help="Emit coding transcripts as mRNA instead of transcript in the .gff",
+        required=False,
+        action="store_true",
+    )
+    base_parser.add_argument(
+        "-ra",
+        "--reference_annotation",
+        help="Path to the reference .gtf/.gff (optionally gzipped) to add ref_symbol, ref_biotype and ref_description columns to the query table",
+        required=False,
+        type=str,
+    )
+    base_parser.add_argument(
+        "-cd",
+        "--cache_dir",
+        help=f"Directory of the reference metadata cache, reused across runs (default: {Constants.CACHE_DIR})",
+        required=False,
+        type=str,
+    )
+    base_parser.add_argument(
+        "-vb",
+        "--validate_bed",
+        help="Validate every BED12 record before conversion and write the issues to bed_validation.tsv; strict stops the run if there is any",
+        required=False,
+        type=str,
+        choices=["report", "strict"],
+    )
+    base_parser.add_argument(
+        "-tl",
+        "--translate",
+        help="Translate the extracted codon sequences to protein with this genetic code, dropping alignment gaps and marking frameshifts as '!' (extracts codon if needed)",
+        required=False,
+        choices=list(Constants.GENETIC_CODES),
+        type=str,
+    )
+    base_parser.add_argument(
+        "-ms",
+        "--mask_stops",
+        help="Write internal stop codons as X in --translate output",
+        required=False,
+        action="store_true",
+    )
+    base_parser.add_argument(
+        "-dg",
+        "--digests",
+        help="Write an (id, length, md5, GA4GH sq) digest table next to every extracted .fasta for cross-database identity checks",
+        required=False,
+        action="store_true",
+    )
+    base_parser.add_argument(
+        "-fx",
+        "--fasta_index",
+        help="Fetch extracted sequences through a samtools-style .fai index of each TOGA .fasta (written if missing), reading only the requested records",
+        required=False,
+        action="store_true",
+    )
+    base_parser.add_argument(
+        "-gfp",
+        "--gff_profile",
+        help="Add the NCBI RefSeq/AgBioData attribute profile (gbkey, gene, product, Dbxref) to the .gff, filled from the query table and --reference_annotation (requires --to gff)",
+        required=False,
+        type=str,
+        choices=["refseq"],
+    )
+    base_parser.add_argument(
+        "-orf",
+        "--orf_rescue",
+        help=f"Give exon-only projections of coding transcripts their longest ORF as CDS when it reaches this fraction of the reference protein length, flagged as cds_inferred (default fraction: {Constants.ORF_MIN_FRACTION}, requires --genome)",
+        required=False,
+        type=float,
+        nargs="?",
+        const=Constants.ORF_MIN_FRACTION,
+    )
+    base_parser.add_argument(
+        "-mxe",
+        "--max_exons",
+        help="Skip projections with more exons than this (e.g. 5000), each one logged and reported as a guardrail trip (default: off)",
+        required=False,
+        type=int,
+        default=Constants.LIMITS["max_exons"],
+    )
+    base_parser.add_argument(
+        "-mxs",
+        "--max_span",
+        help="Skip projections spanning more bp than this (e.g. 5000000, mind long genes), each one logged and reported as a guardrail trip (default: off)",
+        required=False,
+        type=int,
+        default=Constants.LIMITS["max_span"],
+    )
+    base_parser.add_argument(
+        "-mxa",
+        "--max_attribute_length",
+        help="Drop .gtf/.gff attribute values longer than this (e.g. 10000), transcript_id/gene_id/ID/Parent are always kept (default: off)",
+        required=False,
+        type=int,
+        default=Constants.LIMITS["max_attribute_length"],
+    )
+    base_parser.add_argument(
+        "-ga",
+        "--gap_attribute",
+        help="Add GFF3 Gap/Target attributes to CDS features from TOGA's codon alignments, showing where indels landed relative to the reference (requires --to gff)",
+        required=False,
+        action="store_true",
+    )
+    base_parser.add_argument(
+        "-as",
+        "--attribute_sep",
+        help="Separator between attributes of the .gtf/.gff (default: space for gtf, none for gff), for legacy consumers expecting exact spacing",
+        required=False,
+        type=str,
+        choices=list(Constants.ATTRIBUTE_SEPARATORS),
+    )
+    base_parser.add_argument(
+        "-le",
+        "--line_ending",
+        help="Line endings of the .gtf/.gff (default: lf)",
+        required=False,
+        type=str,
+        choices=list(Constants.LINE_ENDINGS),
+        default="lf",
+    )
+    base_parser.add_argument(
+        "-so",
+        "--sort",
+        help="Order of the .gtf/.gff features: by gene and transcript position, by gene and transcript ID, or as converted within each chromosome; sub-features go gene < transcript < exon < CDS < UTRs < codons (default: position)",
+        required=False,
+        type=str,
+        choices=Constants.SORT_MODES,
+        default="position",
+    )
+    base_parser.add_argument(
+        "-ft",
+        "--features",
+        help="Features to keep in the converted annotation: all, cds_only (genes, transcripts, CDS, codons) or exon_only (default: all)",
+        required=False,
+        type=str,
+        choices=["all", "cds_only", "exon_only"],
+        default="all",
+    )
+    base_parser.add_argument(
+        "-tp",
+        "--trim_partial_codons",
+        help="Trim partial codons from both CDS ends and tag the transcript with "
+        "cds_start_NF/cds_end_NF",
+        required=False,
+        action="store_true",
+    )
+    base_parser.add_argument(
+        "-nf",
+        "--nf_tags",
+        help="Tag incomplete transcripts with Ensembl cds_start_NF, cds_end_NF, mRNA_start_NF and mRNA_end_NF (requires --genome)",
+        required=False,
+        action="store_true",
+    )
+    base_parser.add_argument(
+        "-de",
+        "--dedup_exons",
+        help="Write exons shared by several isoforms of a gene once, with comma-separated Parents (gff only)",
+        required=False,
+        action="store_true",
+    )
+    base_parser.add_argument(
+        "-ex",
+        "--extract",
+        help="Comma-separated TOGA .fasta files to extract query sequences of the annotation from (nucleotide, codon, protein)",
+        required=False,
+        type=str,
+    )
+    base_parser.add_argument(
+        "-spb",
+        "--split_by",
+        help="Also write the --extract (and --extract_beds) sequences as one .fasta per gene (isoforms map) or per orthology class in a *.split directory next to each .fasta",
+        required=False,
+        choices=["gene", "class"],
+        type=str,
+    )
+    base_parser.add_argument(
+        "-pr",
+        "--paired",
+        help="Comma-separated TOGA .fasta files (nucleotide, codon, protein) to write the reference and query sequences of each projection side by side from, for curation",
+        required=False,
+        type=str,
+    )
+    base_parser.add_argument(
+        "-pl",
+        "--paired_layout",
+        help="Layout of the --paired files: adjacent .fasta records or text alignment blocks with a match line, for aligned (codon/protein) files (default: adjacent)",
+        required=False,
+        choices=["adjacent", "aligned"],
+        type=str,
+        default="adjacent",
+    )
+    base_parser.add_argument(
+        "-eb",
+        "--extract_beds",
+        help="Comma-separated label=path .bed files (e.g. I=intact.bed,L=lost.bed) to extract in a single pass over each --extract .fasta, with class-labeled headers",
+        required=False,
+        type=str,
+    )
+    base_parser.add_argument(
+        "-ht",
+        "--header_template",
+        help="Header template for extracted sequences, e.g. '{tx}|{gene}|{class}|{species}'; any query table or --metadata column can be used, plus {md5} and {sq} sequence digests",
+        required=False,
+        type=str,
+    )
+    base_parser.add_argument(
+        "-du",
+        "--duplicates",
+        help="What to do with projections found more than once in a .fasta file (default: keep_first)",
+        required=False,
+        choices=Constants.DUPLICATE_POLICIES,
+        type=str,
+        default="keep_first",
+    )
+    base_parser.add_argument(
+        "-om",
+        "--on_missing",
+        help="What to do with projections absent from a .fasta file: stop the run, skip them, skip them with a warning or write them with an empty sequence (default: skip)",
+        required=False,
+        choices=Constants.MISSING_POLICIES,
+        type=str,
+        default="skip",
+    )
+    base_parser.add_argument(
+        "-ml",
+        "--min_len",
+        help="Exclude extracted sequences shorter than this (gaps not counted)",
+        required=False,
+        type=int,
+    )
+    base_parser.add_argument(
+        "-ma",
+        "--max_ambiguous_frac",
+        help="Exclude extracted sequences with a larger fraction of ambiguous positions (N/X)",
+        required=False,
+        type=float,
+    )
+    base_parser.add_argument(
+        "-ss",
+        "--require_start_stop",
+        help="Exclude extracted sequences without a start and a stop codon",
+        required=False,
+        action="store_true",
+    )
+    base_parser.add_argument(
+        "-or",
+        "--order",
+        help="Order of extracted sequences: as in the .bed file or natural-sorted by name (default: bed)",
+        required=False,
+        choices=["bed", "natural"],
+        type=str,
+        default="bed",
+    )
+    base_parser.add_argument(
+        "-tr",
+        "--threads",
+        help="Number of threads used to format extracted sequences and of processes indexing TOGA .fasta files for --fasta_index (default: 1)",
+        required=False,
+        type=int,
+        default=1,
+    )
+    base_parser.add_argument(
+        "-qs",
+        "--query_species",
+        help="Name of the query species, available as {species} in --header_template",
+        required=False,
+        type=str,
+    )
+    base_parser.add_argument(
+        "-md",
+        "--metadata",
+        help="Tab-separated table with a header, keyed by projection name in the first column, with extra fields for --header_template",
+        required=False,
+        type=str,
+    )
+    base_parser.add_argument(
+        "-vc",
+        "--verify_cds",
+        help="Cross-check CDS lengths of the converted annotation against the extracted nucleotide sequences",
+        required=False,
+        action="store_true",
+    )
+    base_parser.add_argument(
+        "-rs",
+        "--rescue_starts",
+        help="Move the CDS start of intact projections missing an ATG to the next in-frame ATG within N codons (requires --genome)",
+        required=False,
+        type=int,
+    )
+    base_parser.add_argument(
+        "-kz",
+        "--kozak",
+        help="Score the Kozak context of each start codon into the query table and warn on rescued starts in weak contexts (requires --genome)",
+        required=False,
+        action="store_true",
+    )
+    base_parser.add_argument(
+        "-cr",
+        "--cds_ratio",
+        help="Add the query/reference CDS length ratio of each projection (reference from TOGA's protein .fasta) as a query table column and a `cds_ratio` transcript attribute",
+        required=False,
+        action="store_true",
+    )
+    base_parser.add_argument(
+        "-rcp",
+        "--retrocopies",
+        help="Reference .bed given to TOGA; flag intron-less projections of multi-exonic reference transcripts as `retrocopy_candidate` in the query table and write them to *.retrocopies.bed",
+        required=False,
+        type=str,
+    )
+    base_parser.add_argument(
+        "-rgf",
+        "--retrocopy_gff",
+        help="Also convert the retrocopy candidates to .gff (requires --retrocopies)",
+        required=False,
+        action="store_true",
+    )
+    base_parser.add_argument(
+        "-mt",
+        "--mt_contigs",
+        help="Comma-separated contigs to treat as mitochondrial, on top of the usual names (chrM, MT, ...)",
+        required=False,
+        type=str,
+    )
+    base_parser.add_argument(
+        "-cc",
+        "--circular",
+        help="Comma-separated circular contigs (e.g. plasmids) where projections may span the origin; mitochondrial contigs are always circular",
+        required=False,
+        type=str,
+    )
+    base_parser.add_argument(
+        "-gp",
+        "--gaps",
+        help="Assembly gaps .bed file, projections spanning or near gaps are flagged",
+        required=False,
+        type=str,
+    )
+    base_parser.add_argument(
+        "-fg",
+        "--find_gaps",
+        help="Compute assembly gaps as N-runs of --genome and flag projections spanning or near them",
+        required=False,
+        action="store_true",
+    )
+    base_parser.add_argument(
+        "-gm",
+        "--gap_margin",
+        help=f"Distance in bp to consider a projection near an assembly gap (default: {Constants.GAP_MARGIN})",
+        required=False,
+        type=int,
+        default=Constants.GAP_MARGIN,
+    )
+    base_parser.add_argument(
+        "-ce",
+        "--contig_ends",
+        help=f"Flag projections within this many bp of a contig end (default margin: {Constants.CONTIG_END_MARGIN}); needs --genome or --chrom_sizes",
+        required=False,
+        type=int,
+        nargs="?",
+        const=Constants.CONTIG_END_MARGIN,
+    )
+    base_parser.add_argument(
+        "-cat",
+        "--contig_categories",
+        help="Break projection, gene, class and ancestral completeness counts down by contig category (autosome, sex, unplaced, MT) into contig_categories.tsv; categories from a (contig, category) table if given, from contig names otherwise",
+        required=False,
+        type=str,
+        nargs="?",
+        const="",
+    )
+    base_parser.add_argument(
+        "-cq",
+        "--contig_quality",
+        help="Write a per-contig quality table (gene density, flagged fraction, mean orthology score, gap burden)",
+        required=False,
+        action="store_true",
+    )
+    base_parser.add_argument(
+        "-wt",
+        "--write_table",
+        help="Write the query table (with any flag columns) to query_table.tsv",
+        required=False,
+        action="store_true",
+    )
+    base_parser.add_argument(
+        "-co",
+        "--columns",
+        help="Comma-separated columns (and their order) for --write_table",
+        required=False,
+        type=str,
+    )
+    base_parser.add_argument(
+        "-dp",
+        "--decimals",
+        help="Decimals for floating point values in output tables (default: 4)",
+        required=False,
+        type=int,
+        default=4,
+    )
+    base_parser.add_argument(
+        "-rc",
+        "--recode",
+        help="Class mapping applied to written classes (query table, loss_class attributes, --split_by class, matrices), as a two-column file or inline (e.g. PI=uncertain,UL=uncertain)",
+        required=False,
+        type=str,
+    )
+    base_parser.add_argument(
+        "-np",
+        "--never_projected",
+        help="Report reference transcripts/genes with zero projections (dropouts, not losses)",
+        required=False,
+        action="store_true",
+    )
+    base_parser.add_argument(
+        "-td",
+        "--tandem",
+        help=f"Report clusters of tandem duplicates: copies of a reference gene (projections through different chains) on the same contig at most DISTANCE bp apart (default: {Constants.TANDEM_MAX_DISTANCE})",
+        required=False,
+        type=int,
+        nargs="?",
+        const=Constants.TANDEM_MAX_DISTANCE,
+        metavar="DISTANCE",
+    )
+    base_parser.add_argument(
+        "-gs",
+        "--gene_status",
+        help="Call a final gene status (annotated/putative/absent) from TOGA class plus --expression/--homology evidence",
+        required=False,
+        action="store_true",
+    )
+    base_parser.add_argument(
+        "-xp",
+        "--expression",
+        help="Two-column (id, TPM/count) expression table used by --gene_status",
+        required=False,
+        type=str,
+    )
+    base_parser.add_argument(
+        "-hm",
+        "--homology",
+        help="Two-column (id, e-value) or DIAMOND/BLAST/HMMER hit table used by --gene_status",
+        required=False,
+        type=str,
+    )
+    base_parser.add_argument(
+        "-me",
+        "--min_expression",
+        help=f"Minimum expression counted as support (default: {Constants.MIN_EXPRESSION})",
+        required=False,
+        type=float,
+        default=Constants.MIN_EXPRESSION,
+    )
+    base_parser.add_argument(
+        "-mv",
+        "--max_evalue",
+        help=f"Maximum e-value counted as support (default: {Constants.MAX_EVALUE})",
+        required=False,
+        type=float,
+        default=Constants.MAX_EVALUE,
+    )
+    base_parser.add_argument(
+        "-hi",
+        "--hits",
+        help="DIAMOND/BLAST tabular or HMMER --tblout hits of the query proteome vs the reference; flags Lost genes with strong hits as rescue candidates",
+        required=False,
+        type=str,
+    )
+    base_parser.add_argument(
+        "-mb",
+        "--min_bitscore",
+        help=f"Minimum bitscore of a strong --hits hit (default: {Constants.MIN_BITSCORE})",
+        required=False,
+        type=float,
+        default=Constants.MIN_BITSCORE,
+    )
+    base_parser.add_argument(
+        "-sr",
+        "--search_regions",
+        help=f"Write the projected query loci of Lost/Missing genes, padded by this many bp (default flank: {Constants.SEARCH_FLANK}), as a BED for targeted re-annotation (genes without a projected locus are listed as no_search_locus warnings)",
+        required=False,
+        type=int,
+        nargs="?",
+        const=Constants.SEARCH_FLANK,
+    )
+    base_parser.add_argument(
+        "-mn",
+        "--miniprot",
+        help="miniprot .gff of targeted re-annotation (e.g. of --search_regions) to reconcile with TOGA",
+        required=False,
+        type=str,
+    )
+    base_parser.add_argument(
+        "-xr",
+        "--exonerate",
+        help="exonerate --showtargetgff output of targeted re-annotation to reconcile with TOGA",
+        required=False,
+        type=str,
+    )
+    base_parser.add_argument(
+        "-rp",
+        "--reconcile",
+        help="What to do with targeted models of Lost/Missing genes: report only, add them, or upgrade (replace overlapping projections) (default: add)",
+        required=False,
+        choices=Constants.RECONCILE_POLICIES,
+        type=str,
+        default="add",
+    )
+    base_parser.add_argument(
+        "-rt",
+        "--round_trip",
+        help="Keep BED score/itemRgb as attributes and verify that the .gtf/.gff converts back to the identical BED12, reporting lossy projections",
+        required=False,
+        action="store_true",
+    )
+    base_parser.add_argument(
+        "-tk",
+        "--top_projections",
+        help="Keep only the K best projections of each reference transcript, ranked by --rank_weights",
+        required=False,
+        type=int,
+    )
+    base_parser.add_argument(
+        "-rw",
+        "--rank_weights",
+        help=f"Projection ranking criteria and weights, e.g. pred=1,class=0.5,-mutations=0.2 (default: {','.join(f'{k}={v}' for k, v in Constants.RANK_WEIGHTS.items())})",
+        required=False,
+        type=str,
+    )
+    base_parser.add_argument(
+        "-sn",
+        "--subsample",
+        help="Keep a reproducible random subset of this many projections (e.g. for dry-runs)",
+        required=False,
+        type=int,
+    )
+    base_parser.add_argument(
+        "-sd",
+        "--seed",
+        help="Random seed for --subsample (default: 0)",
+        required=False,
+        type=int,
+        default=0,
+    )
+    base_parser.add_argument(
+        "-sb",
+        "--stratify_by",
+        help="Query table column to stratify --subsample by (default: class)",
+        required=False,
+        choices=["class", "relation", "confidence_level", "none"],
+        type=str,
+        default="class",
+    )
+    base_parser.add_argument(
+        "-aq",
+        "--assembly_qual",
+        help="Calculate assembly quality based on a list of genes provided by the user (default: Ancestral_placental.txt)",
+        required=False,
+        type=str,
+        default=Constants.FileNames.ANCESTRAL,
+    )
+    base_parser.add_argument(
+        "-sp",
+        "--species",
+        help="Species name to be used as a reference for the assembly quality calculation (default: human)",
+        required=False,
+        choices=["human", "mouse", "chicken"],
+        type=str,
+        default=Constants.SPECIES_DEFAULT,
+    )
+    base_parser.add_argument(
+        "-um",
+        "--union_model",
+        help="Write a union gene model .gtf (overlapping exons of all isoforms merged per gene) for read counting; with --to saf, the .saf is written from union models",
+        required=False,
+        action="store_true",
+    )
+    base_parser.add_argument(
+        "-gb",
+        "--genes_bed",
+        help="Write a gene-level .bed (isoforms collapsed to one line per locus, itemRgb by best class) for overlap analyses",
+        required=False,
+        action="store_true",
+    )
+    base_parser.add_argument(
+        "-g",
+        "--genome",
+        help="Path to the query genome .fasta, needed by sequence-based steps (--prep_quant, --vcf, --nf_tags, --rescue_starts, --kozak)",
+        required=False,
+        type=str,
+    )
+    base_parser.add_argument(
+        "-pq",
+        "--prep_quant",
+        help="Write the spliced transcriptome .fasta and tx2gene table for kallisto/salmon (requires --genome)",
+        required=False,
+        action="store_true",
+    )
+    base_parser.add_argument(
+        "-dc",
+        "--decoys",
+        help="With --prep_quant, also write the gentrome .fasta and decoys.txt for a decoy-aware salmon index",
+        required=False,
+        action="store_true",
+    )
+    base_parser.add_argument(
+        "-vcf",
+        "--vcf",
+        help="Path to a .vcf file to classify variant consequences against the annotation (requires --genome)",
+        required=False,
+        type=str,
+    )
+    base_parser.add_argument(
+        "-st",
+        "--score_track",
+        help="Paint a numeric query table column (e.g. pred) over projection spans as a .bedGraph (.bw if chrom sizes are known)",
+        required=False,
+        type=str,
+    )
+    base_parser.add_argument(
+        "-jp",
+        "--junction_peptides",
+        help=f"Write peptides spanning exon junctions (and --vcf variants) with this many residues per side (default: {Constants.PEPTIDE_FLANK}) for proteogenomic searches; needs --genome",
+        required=False,
+        type=int,
+        nargs="?",
+        const=Constants.PEPTIDE_FLANK,
+    )
+    base_parser.add_argument(
+        "-dn",
+        "--density",
+        help=f"Write gene and exon counts per window of this many bp (default window: {Constants.DENSITY_WINDOW}) as .bedGraph (.bw if chrom sizes are known)",
+        required=False,
+        type=int,
+        nargs="?",
+        const=Constants.DENSITY_WINDOW,
+    )
+    base_parser.add_argument(
+        "-cov",
+        "--coverage",
+        help="Write the number of projections covering each base, counting exons or whole transcript spans, as .bedGraph (.bw if chrom sizes are known) (default: exon)",
+        required=False,
+        choices=["exon", "transcript"],
+        type=str,
+        nargs="?",
+        const="exon",
+    )
+    base_parser.add_argument(
+        "-cb",
+        "--coverage_bin",
+        help="Average --coverage over bins of this many bp instead of writing it per base",
+        required=False,
+        type=int,
+    )
+    base_parser.add_argument(
+        "-bb",
+        "--bigbed",
+        help="Write the annotation as BigBed with bedToBigBed: plain BED12 or annotated BED12+2 with orthology relation and loss class (default: annotated); needs --genome or --chrom_sizes",
+        required=False,
+        choices=["plain", "annotated"],
+        type=str,
+        nargs="?",
+        const="annotated",
+    )
+    base_parser.add_argument(
+        "-cs",
+        "--chrom_sizes",
+        help="Path to a chrom.sizes file for bigWig/bigBed tracks (computed from --genome if not provided)",
+        required=False,
+        type=str,
+    )
+
+
+def haplotype_branch(subparsers):
+    haplotype_parser = subparsers.add_parser("haplotype", help="Haplotype mode")
+    haplotype_parser.add_argument(
+        "-hp",
+        "--haplotype_path",
+        help="Path to TOGA results directories separated by commas (path1,path2,path3)",
+        required=True,
+        type=str,
+    )
+    haplotype_parser.add_argument(
+        "-r",
+        "--rule",
+        help="Rule to merge haplotype assemblies (default: I>PI>UL>L>M>PM>PG>abs)",
+        required=False,
+        type=str,
+        default="I>PI>UL>L>M>PM>PG>NF",
+    )
+    haplotype_parser.add_argument(
+        "-s",
+        "--source",
+        help="Source of the haplotype classes (query, loss)",
+        required=False,
+        type=str,
+        choices=["query", "loss"],
+        default="loss",
+    )
+
+
+def diff_branch(subparsers):
+    diff_parser = subparsers.add_parser("diff", help="Diff mode")
+    diff_parser.add_argument(
+        "-a",
+        "--run_a",
+        help="Path to the old TOGA results directory (before)",
+        required=True,
+        type=str,
+    )
+    diff_parser.add_argument(
+        "-b",
+        "--run_b",
+        help="Path to the new TOGA results directory (after), where the diff is written",
+        required=True,
+        type=str,
+    )
+
+
+def matrix_branch(subparsers):
+    matrix_parser = subparsers.add_parser(
+        "matrix", help="Gene x species class matrix of several TOGA runs"
+    )
+    matrix_parser.add_argument(
+        "-mp",
+        "--matrix_paths",
+        help="Comma-separated paths to TOGA results directories",
+        required=True,
+        type=str,
+    )
+    matrix_parser.add_argument(
+        "-n",
+        "--names",
+        help="Comma-separated species names, in the order of --matrix_paths (default: directory names)",
+        required=False,
+        type=str,
+    )
+    matrix_parser.add_argument(
+        "-sh",
+        "--shape",
+        help="Wide matrix (one column per species) or long table (gene, species, class) (default: wide)",
+        required=False,
+        choices=["wide", "long"],
+        type=str,
+        default="wide",
+    )
+    matrix_parser.add_argument(
+        "-rc",
+        "--recode",
+        help="Class mapping applied to the matrix classes, as a two-column file or inline (e.g. PI=uncertain,UL=uncertain)",
+        required=False,
+        type=str,
+    )
+    matrix_parser.add_argument(
+        "-sy",
+        "--symbols",
+        help="Comma-separated gene ID -> symbol tables, one per run or one for all; genes are joined on normalized symbols",
+        required=False,
+        type=str,
+    )
+    matrix_parser.add_argument(
+        "-al",
+        "--aliases",
+        help="Comma-separated HGNC/MGI-style alias tables (approved symbol, then alias/previous symbol columns) used with --symbols",
+        required=False,
+        type=str,
+    )
+    matrix_parser.add_argument(
+        "-o",
+        "--output",
+        help="Path to the output .tsv (default: class_matrix.{shape}.tsv in the first run)",
+        required=False,
+        type=str,
+    )
+
+
+def synthetic_branch(subparsers):
+    synthetic_parser = subparsers.add_parser(
+        "synthetic", help="Write a synthetic TOGA results directory"
+    )
+    synthetic_parser.add_argument(
+        "-o",
+        "--outdir",
+        help="Output directory",
+        required=True,
+        type=str,
+    )
+    synthetic_parser.add_argument(
+        "-n",
+        "--n_genes",
+        help="Number of reference genes (default: 20)",
+        required=False,
+        type=int,
+        default=20,
+    )
+    synthetic_parser.add_argument(
+        "-sd",
+        "--seed",
+        help="Random seed (default: 0)",
+        required=False,
+        type=int,
+        default=0,
+    )
+    synthetic_parser.add_argument(
+        "-fr",
+        "--fragments",
+        help="Genes projected as two pieces at contig ends (default: 2)",
+        required=False,
+        type=int,
+        default=2,
+    )
+    synthetic_parser.add_argument(
+        "-mm",
+        "--many2many",
+        help="Pairs of genes with a many2many relation (default: 2)",
+        required=False,
+        type=int,
+        default=2,
+    )
+    synthetic_parser.add_argument(
+        "-nc",
+        "--noncoding",
+        help="Non-coding projections (default: 2)",
+        required=False,
+        type=int,
+        default=2,
+    )
+    synthetic_parser.add_argument(
+        "-sc",
+        "--split_codons",
+        help="Minus-strand projections with codons split by introns (default: 2)",
+        required=False,
+        type=int,
+        default=2,
+    )
+    synthetic_parser.add_argument(
+        "-g",
+        "--genome",
+        help="Also write a query genome matching the projections",
+        required=False,
+        action="store_true",
+    )
+
+
+def windows_branch(subparsers):
+    windows_parser = subparsers.add_parser(
+        "windows", help="Windowed statistics of a .bed/.gtf/.gff for genome scans"
+    )
+    windows_parser.add_argument(
+        "-i",
+        "--input",
+        help="Path to a .bed or .gtf/.gff file",
+        required=True,
+        type=str,
+    )
+    windows_parser.add_argument(
+        "-w",
+        "--window",
+        help="Window size in bp",
+        required=True,
+        type=int,
+    )
+    windows_parser.add_argument(
+        "-s",
+        "--step",
+        help="Distance between window starts (default: the window size)",
+        required=False,
+        type=int,
+    )
+    windows_parser.add_argument(
+        "-v",
+        "--stats",
+        help=f"Comma-separated statistics per window, from {','.join(STATS)} (default: count)",
+        required=False,
+        type=str,
+        default="count",
+    )
+    windows_parser.add_argument(
+        "-f",
+        "--feature",
+        help="Only use this .gtf/.gff feature type (e.g. gene, exon)",
+        required=False,
+        type=str,
+    )
+    windows_parser.add_argument(
+        "-cs",
+        "--chrom_sizes",
+        help="chrom.sizes file to scan whole contigs (default: up to the last feature)",
+        required=False,
+        type=str,
+    )
+    windows_parser.add_argument(
+        "-o",
+        "--output",
+        help="Path to the output .tsv",
+        required=True,
+        type=str,
+    )
+
+
+def migrate_branch(subparsers):
+    migrate_parser = subparsers.add_parser(
+        "migrate", help="Upgrade a query table of an older postoga to the current schema"
+    )
+    migrate_parser.add_argument(
+        "-i",
+        "--input",
+        help="Path to a query_table.tsv",
+        required=True,
+        type=str,
+    )
+    migrate_parser.add_argument(
+        "-o",
+        "--output",
+        help="Path to the migrated table (default: overwrite --input)",
+        required=False,
+        type=str,
+    )
+
+
+def convert_branch(subparsers):
+    convert_parser = subparsers.add_parser(
+        "convert", help="Convert a BED12 annotation to .gtf/.gff3 at a given output"
+    )
+    convert_parser.add_argument(
+        "-b",
+        "--bed",
+        help="Path to a .bed file, optionally compressed",
+        required=True,
+        type=str,
+    )
+    convert_parser.add_argument(
+        "-iso",
+        "--isoforms",
+        help="Path to the gene -> transcript isoforms file",
+        required=True,
+        type=str,
+    )
+    convert_parser.add_argument(
+        "-o",
+        "--output",
+        help="Path to the converted annotation, '-' for stdout (default: next to --bed)",
+        required=False,
+        type=str,
+    )
+    convert_parser.add_argument(
+        "-f",
+        "--format",
+        help="Output format, taking precedence over the --output extension",
+        required=False,
+        choices=["gtf", "gff3"],
+        type=str,
+    )
+    convert_parser.add_argument(
+        "-c",
+        "--compress",
+        help="Output compression, taking precedence over the --output extension (default: from --output, else none)",
+        required=False,
+        choices=["none", "gz", "bgzf", "zstd", "xz"],
+        type=str,
+    )
+    convert_parser.add_argument(
+        "-cm",
+        "--chrom_map",
+        help="Two-column (name, alias) table to rename the chromosomes of the converted annotation with",
+        required=False,
+        type=str,
+    )
+    convert_parser.add_argument(
+        "-uc",
+        "--unmapped_chroms",
+        help="What to do with lines on chromosomes missing from --chrom_map: error or drop (default: error)",
+        required=False,
+        choices=["error", "drop"],
+        type=str,
+        default="error",
+    )
+
+
+def tobed_branch(subparsers):
+    tobed_parser = subparsers.add_parser(
+        "tobed", help="Convert a .gtf/.gff annotation back to BED12"
+    )
+    tobed_parser.add_argument(
+        "-i",
+        "--input",
+        help="Path to a .gtf/.gff annotation, optionally gzipped",
+        required=True,
+        type=str,
+    )
+    tobed_parser.add_argument(
+        "-o",
+        "--output",
+        help="Path to the BED12 file (default: <input stem>.bed next to --input)",
+        required=False,
+        type=str,
+    )
+    tobed_parser.add_argument(
+        "-iso",
+        "--isoforms",
+        help="Path to write the gene -> transcript isoforms file to, if any",
+        required=False,
+        type=str,
+    )
+    tobed_parser.add_argument(
+        "-f",
+        "--format",
+        help="Format of --input, bypassing its detection (e.g. for files without an extension)",
+        required=False,
+        choices=["gtf", "gff"],
+        type=str,
+    )
+
+
+def reformat_branch(subparsers):
+    reformat_parser = subparsers.add_parser(
+        "reformat",
+        help="Normalize a third-party .fasta (wrapping, case, duplicate names) before using it",
+    )
+    reformat_parser.add_argument(
+        "-i",
+        "--input",
+        help="Path to a .fasta file, optionally compressed",
+        required=True,
+        type=str,
+    )
+    reformat_parser.add_argument(
+        "-o",
+        "--output",
+        help="Path to the normalized .fasta (compressed if it ends in .gz, .zst or .xz)",
+        required=True,
+        type=str,
+    )
+    reformat_parser.add_argument(
+        "-w",
+        "--wrap",
+        help="Sequence line width, 0 for single-line sequences (default: 60)",
+        required=False,
+        type=int,
+        default=60,
+    )
+    reformat_parser.add_argument(
+        "-u",
+        "--upper",
+        help="Upper-case every sequence (drops soft-masking)",
+        required=False,
+        action="store_true",
+    )
+    reformat_parser.add_argument(
+        "-d",
+        "--dedup",
+        help="Policy for records sharing a name (first header word); kept as they are if not given",
+        required=False,
+        type=str,
+        choices=Constants.REFORMAT_DUPLICATE_POLICIES,
+    )
+
+
+def remap_branch(subparsers):
+    remap_parser = subparsers.add_parser(
+        "remap", help="Move a .bed/.gtf/.gff annotation onto a revised assembly"
+    )
+    remap_parser.add_argument(
+        "-i",
+        "--input",
+        help="Path to a .bed/.gtf/.gff annotation",
+        required=True,
+        type=str,
+    )
+    remap_parser.add_argument(
+        "-a",
+        "--agp",
+        help="Path to an AGP file (old sequences as components) or offset table (old_chrom, old_start, old_end, new_chrom, new_start, strand; 0-based)",
+        required=True,
+        type=str,
+    )
+    remap_parser.add_argument(
+        "-o",
+        "--output",
+        help="Path to the remapped annotation (default: <input>.remapped.<ext> next to --input)",
+        required=False,
+        type=str,
+    )
+
+
+def parser():
+    """Argument parser for postoga"""
+    app = argparse.ArgumentParser()
+    subparsers = app.add_subparsers(dest="mode", help="Select mode")
+
+    base_branch(subparsers)
+    haplotype_branch(subparsers)
+    diff_branch(subparsers)
+    matrix_branch(subparsers)
+    synthetic_branch(subparsers)
+    windows_branch(subparsers)
+    migrate_branch(subparsers)
+    remap_branch(subparsers)
+    convert_branch(subparsers)
+    tobed_branch(subparsers)
+    reformat_branch(subparsers)
+
+    if len(sys.argv) < 2:
+        app.print_help()
+        sys.exit(0)
+
+    args = app.parse_args()
+
+    if args.mode == "base" and not args.genome:
+        flags = [f"--{x}" for x in Constants.GENOME_STEPS if getattr(args, x)]
+        if flags:
+            app.error(f"{', '.join(flags)} require --genome")
+
+    if args.mode == "base" and args.gap_attribute and args.to != "gff":
+        app.error("--gap_attribute requires --to gff")
+
+    if args.mode == "base" and args.gff_profile and args.to != "gff":
+        app.error("--gff_profile requires --to gff")
+
+    if args.mode == "base" and args.retrocopy_gff and not args.retrocopies:
+        app.error("--retrocopy_gff requires --retrocopies")
+
+    if args.mode == "base" and args.bigbed:
+        if not (args.genome or args.chrom_sizes):
+            app.error("--bigbed requires --genome or --chrom_sizes")
+
+    if args.mode == "base" and args.contig_ends is not None:
+        if not (args.genome or args.chrom_sizes):
+            app.error("--contig_ends requires --genome or --chrom_sizes")
+
+    return args
+
+
+def main():
+    args = parser()
+
+    try:
+        master = TogaDir(args)
+        # Every mode locks the directory it logs to, base runs also lay it out
+        with Workspace(
+            os.path.dirname(master.log.log_file), layout=args.mode == "base"
+        ):
+            master.run()
+    except PostogaError as e:
+        logging.error(e)
+        sys.exit(f"postoga: error: {e}")
 
 
 if __name__ == "__main__":
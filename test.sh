@@ -11,4 +11,48 @@ else
     echo "Directory $DIR not found, clone the repository again or contact the developer"
 fi
 
-head -n 9 $LOG
\ No newline at end of file
+head -n 9 $LOG
+
+# the converted annotation is non-empty and holds one transcript per .bed line
+check_annotation() {
+    python3 - "$1" "$2" <<'EOF'
+import sys
+from modules.gxf import parse_attributes
+
+bed, gxf = sys.argv[1:]
+fmt = "gtf" if gxf.endswith(".gtf") else "gff"
+key = "transcript_id" if fmt == "gtf" else "Parent"
+
+with open(bed) as f:
+    expected = {line.split("\t")[3] for line in f if line.strip()}
+with open(gxf) as f:
+    lines = [x.split("\t") for x in f if not x.startswith("#") and x.strip()]
+transcripts = {parse_attributes(x[8], fmt)[key] for x in lines if x[2] == "exon"}
+
+assert expected, f"{bed} is empty"
+assert len(transcripts) == len(expected), (gxf, len(transcripts), len(expected))
+print(f"{gxf}: {len(transcripts)} transcripts, as in {bed}")
+EOF
+}
+
+check_annotation $DIR/filtered.bed $DIR/filtered.gff || exit 1
+
+# reserved characters in .gff attribute values are percent-encoded and decoded back
+python3 -c '
+from modules.gxf import gff_attributes, parse_attributes
+column = gff_attributes({"product": "ATP synthase, H+ transporting", "Parent": "G1,G2"})
+assert column == "product=ATP synthase%2C H+ transporting;Parent=G1,G2;", column
+assert parse_attributes(column, "gff")["product"] == "ATP synthase, H+ transporting"
+' || exit 1
+
+# synthetic TOGA results with fragments, many2many, non-coding and split codons
+SYNTHETIC=$(mktemp -d)
+
+./postoga.py synthetic --outdir $SYNTHETIC --seed 1 \
+    && ./postoga.py $MODE --path $SYNTHETIC --to gtf \
+    && head -n 9 $SYNTHETIC/postoga.log \
+    && check_annotation $SYNTHETIC/query_annotation.bed $SYNTHETIC/query_annotation.gtf
+STATUS=$?
+
+rm -rf $SYNTHETIC
+exit $STATUS